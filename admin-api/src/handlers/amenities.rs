@@ -0,0 +1,160 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use shared::amenities::{is_valid_slug, set_property_amenities};
+use shared::auth::{perm, Require};
+use shared::errors::AppError;
+use shared::models::Amenity;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::middleware::RequireAdmin;
+use crate::models::{
+    ApiResponse, CreateAmenityRequest, SetPropertyAmenitiesRequest, UpdateAmenityRequest,
+};
+use crate::AppState;
+
+fn check_slug(slug: &str) -> Result<(), AppError> {
+    if !is_valid_slug(slug) {
+        return Err(AppError::BadRequest(
+            "Slug must be lowercase letters, digits and hyphens".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/admin/amenities
+pub async fn list_amenities(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<Amenity>>>, AppError> {
+    let amenities =
+        sqlx::query_as::<_, Amenity>("SELECT * FROM amenities ORDER BY sort_order, name")
+            .fetch_all(&state.pool)
+            .await?;
+
+    Ok(Json(ApiResponse::success(amenities)))
+}
+
+/// POST /api/admin/amenities
+pub async fn create_amenity(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateAmenityRequest>,
+) -> Result<Json<ApiResponse<Amenity>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+    check_slug(&payload.slug)?;
+
+    let amenity = sqlx::query_as::<_, Amenity>(
+        r#"
+        INSERT INTO amenities (id, slug, name, icon, category, sort_order, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(&payload.slug)
+    .bind(&payload.name)
+    .bind(&payload.icon)
+    .bind(&payload.category)
+    .bind(payload.sort_order.unwrap_or(0))
+    .fetch_one(&state.pool)
+    .await
+    .map_err(AppError::on_constraint(
+        "amenities_slug_key",
+        AppError::Conflict("An amenity with this slug already exists".to_string()),
+    ))?;
+
+    Ok(Json(ApiResponse::success(amenity)))
+}
+
+/// PUT /api/admin/amenities/:id
+pub async fn update_amenity(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateAmenityRequest>,
+) -> Result<Json<ApiResponse<Amenity>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+    if let Some(ref slug) = payload.slug {
+        check_slug(slug)?;
+    }
+
+    let amenity = sqlx::query_as::<_, Amenity>(
+        r#"
+        UPDATE amenities
+        SET
+            slug = COALESCE($2, slug),
+            name = COALESCE($3, name),
+            icon = COALESCE($4, icon),
+            category = COALESCE($5, category),
+            sort_order = COALESCE($6, sort_order)
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&payload.slug)
+    .bind(&payload.name)
+    .bind(&payload.icon)
+    .bind(&payload.category)
+    .bind(payload.sort_order)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(AppError::on_constraint(
+        "amenities_slug_key",
+        AppError::Conflict("An amenity with this slug already exists".to_string()),
+    ))?
+    .ok_or_else(|| AppError::NotFound(format!("Amenity {id} not found")))?;
+
+    Ok(Json(ApiResponse::success(amenity)))
+}
+
+/// DELETE /api/admin/amenities/:id
+/// Removes the amenity from every property that lists it.
+pub async fn delete_amenity(
+    _: Require<perm::DeleteAmenities>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let result = sqlx::query("DELETE FROM amenities WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Amenity {id} not found")));
+    }
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Amenity deleted"
+    }))))
+}
+
+/// PUT /api/admin/properties/:id/amenities
+///
+/// Replace the property's amenity list.
+pub async fn set_amenities(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(property_id): Path<Uuid>,
+    Json(payload): Json<SetPropertyAmenitiesRequest>,
+) -> Result<Json<ApiResponse<Vec<Amenity>>>, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    sqlx::query("SELECT id FROM properties WHERE id = $1 FOR NO KEY UPDATE")
+        .bind(property_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Property {property_id} not found")))?;
+
+    let amenities = set_property_amenities(&mut tx, property_id, &payload.amenity_slugs).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse::success(amenities)))
+}
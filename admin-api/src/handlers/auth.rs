@@ -1,25 +1,48 @@
 use axum::extract::State;
 use axum::Json;
-use shared::auth::{create_token, verify_password};
+use shared::auth::{
+    create_token, verify_password, Audience, Claims, Require, ACCESS_TOKEN_TTL_MINUTES,
+};
+use shared::client_ip::ClientIp;
 use shared::errors::AppError;
+use shared::login_attempts::begin_login_attempt;
+use shared::models::User;
+use shared::refresh_tokens::{
+    issue_tokens, revoke_refresh_token, revoke_user_sessions, rotate_refresh_token, TokenPair,
+};
+use shared::two_factor::{self, CHALLENGE_TTL_MINUTES};
 use std::sync::Arc;
+use uuid::Uuid;
 use validator::Validate;
 
-use crate::models::{AdminLoginRequest, ApiResponse, AuthResponse, UserResponse};
+use crate::middleware::{OptionalAdmin, RequireAdmin};
+use crate::models::{
+    AdminLoginRequest, AdminLoginResponse, ApiResponse, AuthResponse, LogoutRequest,
+    RecoveryCodesResponse, RefreshRequest, TwoFactorChallenge, TwoFactorCodeRequest,
+    TwoFactorEnableRequest, TwoFactorEnabledResponse, TwoFactorSetupRequest,
+    TwoFactorSetupResponse, TwoFactorVerifyRequest, UserResponse,
+};
 use crate::AppState;
 
 /// POST /api/admin/auth/login
 ///
 /// Authenticate an admin user. Only users with the `Admin` role are
-/// permitted to log in through this endpoint.
+/// permitted to log in through this endpoint. Accounts with two-factor
+/// authentication, or without it while it is mandatory, get a challenge token
+/// to finish the login with instead of a session.
 pub async fn admin_login(
     State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
     Json(payload): Json<AdminLoginRequest>,
-) -> Result<Json<ApiResponse<AuthResponse>>, AppError> {
+) -> Result<Json<ApiResponse<AdminLoginResponse>>, AppError> {
     payload
         .validate()
         .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
 
+    // Refuse locked-out emails and addresses before checking the password.
+    // Every rejection below counts as a failed attempt.
+    let attempt = begin_login_attempt(&state.pool, &payload.email, ip).await?;
+
     // Look up the user by email.
     let user = sqlx::query_as::<_, UserResponse>(
         r#"
@@ -33,15 +56,17 @@ pub async fn admin_login(
     .await?
     .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
 
-    // We also need the password hash (not in UserResponse for security).
-    let row = sqlx::query_scalar::<_, Option<String>>(
-        "SELECT password_hash FROM users WHERE email = $1",
-    )
-    .bind(&payload.email)
-    .fetch_one(&state.pool)
-    .await?;
+    // We also need the password hash (not in UserResponse for security), the
+    // token version to embed in the access token and the two-factor state.
+    let (password_hash, token_version, totp_enabled) =
+        sqlx::query_as::<_, (Option<String>, i32, bool)>(
+            "SELECT password_hash, token_version, totp_enabled FROM users WHERE email = $1",
+        )
+        .bind(&payload.email)
+        .fetch_one(&state.pool)
+        .await?;
 
-    let password_hash = row.ok_or_else(|| {
+    let password_hash = password_hash.ok_or_else(|| {
         AppError::Unauthorized("This account uses Google sign-in and has no password".to_string())
     })?;
 
@@ -63,8 +88,313 @@ pub async fn admin_login(
         return Err(AppError::Unauthorized("Account is deactivated".to_string()));
     }
 
-    // Create JWT.
-    let token = create_token(user.id, &user.email, &user.role, &state.jwt_secret)?;
+    // Hand out a challenge for the second step. The attempt stays a failure
+    // until that step succeeds, so a stolen password alone cannot be used to
+    // keep resetting the lockout while guessing codes.
+    if totp_enabled || state.require_two_factor {
+        let challenge_token = two_factor::issue_challenge(&state.pool, user.id, attempt).await?;
+        return Ok(Json(ApiResponse::success(AdminLoginResponse::TwoFactor(
+            TwoFactorChallenge {
+                challenge_token,
+                setup_required: !totp_enabled,
+                expires_in: CHALLENGE_TTL_MINUTES * 60,
+            },
+        ))));
+    }
+
+    attempt.succeeded(&state.pool).await?;
+
+    // Create the access and refresh tokens.
+    let tokens = issue_tokens(
+        &state.pool,
+        user.id,
+        &user.email,
+        &user.role,
+        token_version,
+        Audience::Admin,
+        &state.jwt_secret,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(
+        AdminLoginResponse::Authenticated(AuthResponse { tokens, user }),
+    )))
+}
+
+/// POST /api/admin/auth/2fa/verify
+///
+/// Second login step: exchange the challenge token from `/login` and a code
+/// from the authenticator app, or a recovery code, for a session. Wrong codes
+/// count as failed logins.
+pub async fn verify_two_factor(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<TwoFactorVerifyRequest>,
+) -> Result<Json<ApiResponse<AuthResponse>>, AppError> {
+    let user_id = two_factor::challenge_user(&state.pool, &payload.challenge_token).await?;
+    let user = load_user(&state, user_id).await?;
+    let attempt = begin_login_attempt(&state.pool, &user.email, ip).await?;
+
+    if !two_factor::verify_second_factor(&state.pool, &user, &payload.code).await? {
+        return Err(AppError::Unauthorized(
+            "Invalid authentication code".to_string(),
+        ));
+    }
+
+    two_factor::complete_challenge(&state.pool, &payload.challenge_token).await?;
+    attempt.succeeded(&state.pool).await?;
+
+    Ok(Json(ApiResponse::success(
+        start_session(&state, user).await?,
+    )))
+}
+
+/// POST /api/admin/auth/2fa/setup
+///
+/// Start enrolling an authenticator app: returns a new secret, its
+/// `otpauth://` provisioning URI and that URI as an SVG QR code. Two-factor
+/// authentication is switched on by `/2fa/enable` once the app's first code
+/// is confirmed.
+pub async fn setup_two_factor(
+    auth: OptionalAdmin,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<TwoFactorSetupRequest>,
+) -> Result<Json<ApiResponse<TwoFactorSetupResponse>>, AppError> {
+    let user = enrolling_user(&state, &auth, payload.challenge_token.as_deref()).await?;
+
+    let secret = two_factor::begin_enrolment(&state.pool, user.id).await?;
+    let otpauth_url = two_factor::provisioning_uri(&secret, &user.email)?;
+    let qr_code_svg = two_factor::qr_code_svg(&otpauth_url)?;
+
+    Ok(Json(ApiResponse::success(TwoFactorSetupResponse {
+        secret,
+        otpauth_url,
+        qr_code_svg,
+    })))
+}
+
+/// POST /api/admin/auth/2fa/enable
+///
+/// Finish enrolment with a code from the authenticator app and return the
+/// recovery codes, which are shown only this once. When enrolling with a
+/// challenge token this also completes the login and returns a session.
+pub async fn enable_two_factor(
+    auth: OptionalAdmin,
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<TwoFactorEnableRequest>,
+) -> Result<Json<ApiResponse<TwoFactorEnabledResponse>>, AppError> {
+    let user = enrolling_user(&state, &auth, payload.challenge_token.as_deref()).await?;
+
+    // Enrolling during a login: wrong codes count as failed logins.
+    let login = match (&auth.0, &payload.challenge_token) {
+        (None, Some(token)) => {
+            let attempt = begin_login_attempt(&state.pool, &user.email, ip).await?;
+            Some((token, attempt))
+        }
+        _ => None,
+    };
+
+    let recovery_codes = two_factor::confirm_enrolment(&state.pool, &user, &payload.code).await?;
+
+    let session = match login {
+        Some((token, attempt)) => {
+            two_factor::complete_challenge(&state.pool, token).await?;
+            attempt.succeeded(&state.pool).await?;
+            Some(start_session(&state, user).await?)
+        }
+        None => None,
+    };
+
+    Ok(Json(ApiResponse::success(TwoFactorEnabledResponse {
+        recovery_codes,
+        session,
+    })))
+}
+
+/// POST /api/admin/auth/2fa/disable
+///
+/// Turn two-factor authentication off, confirming with a current code. Not
+/// allowed while two-factor authentication is mandatory.
+pub async fn disable_two_factor(
+    Require { claims, .. }: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    if state.require_two_factor {
+        return Err(AppError::Forbidden(
+            "Two-factor authentication is required for admin portal users".to_string(),
+        ));
+    }
+
+    let user = load_user(&state, user_id_from_claims(&claims)?).await?;
+    confirm_second_factor(&state, &user, &payload.code).await?;
+
+    let mut conn = state.pool.acquire().await?;
+    two_factor::disable(&mut conn, user.id).await?;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Two-factor authentication disabled"
+    }))))
+}
+
+/// POST /api/admin/auth/2fa/recovery-codes
+///
+/// Replace the recovery codes, confirming with a current code. Earlier
+/// recovery codes stop working.
+pub async fn regenerate_recovery_codes(
+    Require { claims, .. }: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<ApiResponse<RecoveryCodesResponse>>, AppError> {
+    let user = load_user(&state, user_id_from_claims(&claims)?).await?;
+    confirm_second_factor(&state, &user, &payload.code).await?;
+
+    let recovery_codes = two_factor::regenerate_recovery_codes(&state.pool, user.id).await?;
+
+    Ok(Json(ApiResponse::success(RecoveryCodesResponse {
+        recovery_codes,
+    })))
+}
+
+/// POST /api/admin/auth/refresh
+///
+/// Exchange a refresh token for a new access token, rotating the refresh
+/// token. The account must still be active and hold an admin-portal role.
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<ApiResponse<TokenPair>>, AppError> {
+    let (user_id, refresh_token) =
+        rotate_refresh_token(&state.pool, &payload.refresh_token, Audience::Admin).await?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
+
+    ensure_portal_access(&user)?;
+
+    let token = create_token(
+        user.id,
+        &user.email,
+        &user.role,
+        user.token_version,
+        Audience::Admin,
+        &state.jwt_secret,
+    )?;
+
+    Ok(Json(ApiResponse::success(TokenPair {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+    })))
+}
+
+/// POST /api/admin/auth/logout
+///
+/// Revoke the session the refresh token belongs to, or with `all_devices`
+/// every session of the account.
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LogoutRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let user_id = revoke_refresh_token(&state.pool, &payload.refresh_token).await?;
+
+    if let (Some(user_id), true) = (user_id, payload.all_devices) {
+        let mut conn = state.pool.acquire().await?;
+        revoke_user_sessions(&mut conn, user_id).await?;
+    }
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Logged out"
+    }))))
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn load_user(state: &AppState, id: Uuid) -> Result<User, AppError> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))
+}
+
+fn user_id_from_claims(claims: &Claims) -> Result<Uuid, AppError> {
+    claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))
+}
+
+/// The account must still hold an admin-portal role and be active.
+fn ensure_portal_access(user: &User) -> Result<(), AppError> {
+    if !user.role.is_admin_portal_role() {
+        return Err(AppError::Unauthorized(
+            "Only admin portal users can access this portal".to_string(),
+        ));
+    }
+    if !user.is_active {
+        return Err(AppError::Unauthorized("Account is deactivated".to_string()));
+    }
+    Ok(())
+}
+
+/// Issue tokens for a user who has completed every login step.
+async fn start_session(state: &AppState, user: User) -> Result<AuthResponse, AppError> {
+    ensure_portal_access(&user)?;
 
-    Ok(Json(ApiResponse::success(AuthResponse { token, user })))
+    let tokens = issue_tokens(
+        &state.pool,
+        user.id,
+        &user.email,
+        &user.role,
+        user.token_version,
+        Audience::Admin,
+        &state.jwt_secret,
+    )
+    .await?;
+
+    Ok(AuthResponse {
+        tokens,
+        user: UserResponse::from(user),
+    })
+}
+
+/// The user enrolling in two-factor authentication: the logged-in admin, or
+/// the holder of a challenge token from a login that requires enrolment.
+async fn enrolling_user(
+    state: &AppState,
+    auth: &OptionalAdmin,
+    challenge_token: Option<&str>,
+) -> Result<User, AppError> {
+    let user_id = match (&auth.0, challenge_token) {
+        (Some(claims), _) => user_id_from_claims(claims)?,
+        (None, Some(token)) => two_factor::challenge_user(&state.pool, token).await?,
+        (None, None) => {
+            return Err(AppError::Unauthorized(
+                "Authentication required".to_string(),
+            ))
+        }
+    };
+    load_user(state, user_id).await
+}
+
+/// Require a current code before changing two-factor settings.
+async fn confirm_second_factor(state: &AppState, user: &User, code: &str) -> Result<(), AppError> {
+    if !user.totp_enabled {
+        return Err(AppError::BadRequest(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+    if !two_factor::verify_second_factor(&state.pool, user, code).await? {
+        return Err(AppError::BadRequest(
+            "Invalid authentication code".to_string(),
+        ));
+    }
+    Ok(())
 }
@@ -0,0 +1,97 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use shared::blocked_dates::save_blocked_range;
+use shared::errors::AppError;
+use shared::models::BlockedDate;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::middleware::RequireAdmin;
+use crate::models::{ApiResponse, BlockedDateRequest};
+use crate::AppState;
+
+/// GET /api/admin/properties/:id/blocked-dates
+pub async fn list_blocked_dates(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(property_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<BlockedDate>>>, AppError> {
+    let blocks = sqlx::query_as::<_, BlockedDate>(
+        "SELECT * FROM blocked_dates WHERE property_id = $1 ORDER BY start_date",
+    )
+    .bind(property_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse::success(blocks)))
+}
+
+/// POST /api/admin/properties/:id/blocked-dates
+///
+/// Block a date range. Overlapping or adjacent blocks are merged into one,
+/// which is returned.
+pub async fn create_blocked_dates(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(property_id): Path<Uuid>,
+    Json(payload): Json<BlockedDateRequest>,
+) -> Result<Json<ApiResponse<BlockedDate>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let block = save_blocked_range(
+        &state.pool,
+        property_id,
+        payload.start_date,
+        payload.end_date,
+        payload.reason.as_deref(),
+        None,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(block)))
+}
+
+/// PUT /api/admin/properties/:id/blocked-dates/:block_id
+pub async fn update_blocked_dates(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path((property_id, block_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<BlockedDateRequest>,
+) -> Result<Json<ApiResponse<BlockedDate>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let block = save_blocked_range(
+        &state.pool,
+        property_id,
+        payload.start_date,
+        payload.end_date,
+        payload.reason.as_deref(),
+        Some(block_id),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(block)))
+}
+
+/// DELETE /api/admin/properties/:id/blocked-dates/:block_id
+pub async fn delete_blocked_dates(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path((property_id, block_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<BlockedDate>>, AppError> {
+    let block = sqlx::query_as::<_, BlockedDate>(
+        "DELETE FROM blocked_dates WHERE id = $1 AND property_id = $2 RETURNING *",
+    )
+    .bind(block_id)
+    .bind(property_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Blocked date range {block_id} not found")))?;
+
+    Ok(Json(ApiResponse::success(block)))
+}
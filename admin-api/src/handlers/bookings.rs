@@ -1,13 +1,20 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
+use chrono::{DateTime, Utc};
+use shared::auth::Require;
+use shared::cancellation::refund_for_booking;
 use shared::errors::AppError;
-use shared::models::Booking;
+use shared::models::{Booking, BookingStatus, PropertyRules, Refund};
+use shared::notifications;
+use shared::pagination::next_cursor;
+use shared::payments::{self, PaymentSummary};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::middleware::RequireAdmin;
 use crate::models::{
-    ApiResponse, BookingFilterParams, PaginatedResponse, UpdateBookingStatusRequest,
+    ApiResponse, BookingFilterParams, BookingStatusHistoryEntry, PaginatedResponse,
+    UpdateBookingStatusRequest,
 };
 use crate::AppState;
 
@@ -15,13 +22,14 @@ use crate::AppState;
 ///
 /// List all bookings with pagination and optional status/property_id filter.
 pub async fn list_bookings(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Query(params): Query<BookingFilterParams>,
 ) -> Result<Json<ApiResponse<PaginatedResponse<Booking>>>, AppError> {
     let pagination = params.pagination();
     let limit = pagination.limit();
     let offset = pagination.offset();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
 
     let status_str = params.status.as_ref().map(|s| {
         serde_json::to_value(s)
@@ -30,52 +38,62 @@ pub async fn list_bookings(
             .unwrap_or_default()
     });
 
-    let bookings = sqlx::query_as::<_, Booking>(
+    let mut bookings = sqlx::query_as::<_, Booking>(
         r#"
         SELECT *
         FROM bookings
         WHERE ($1::text IS NULL OR status::text = $1)
             AND ($2::uuid IS NULL OR property_id = $2)
-        ORDER BY created_at DESC
-        LIMIT $3 OFFSET $4
+            AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $5 OFFSET $6
         "#,
     )
     .bind(&status_str)
     .bind(params.property_id)
-    .bind(limit)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit + 1)
     .bind(offset)
     .fetch_all(&state.pool)
     .await?;
 
-    let total = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*)
-        FROM bookings
-        WHERE ($1::text IS NULL OR status::text = $1)
-            AND ($2::uuid IS NULL OR property_id = $2)
-        "#,
-    )
-    .bind(&status_str)
-    .bind(params.property_id)
-    .fetch_one(&state.pool)
-    .await?;
+    let total = if pagination.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar::<_, i64>(
+                r#"
+                SELECT COUNT(*)
+                FROM bookings
+                WHERE ($1::text IS NULL OR status::text = $1)
+                    AND ($2::uuid IS NULL OR property_id = $2)
+                "#,
+            )
+            .bind(&status_str)
+            .bind(params.property_id)
+            .fetch_one(&state.pool)
+            .await?,
+        )
+    };
 
-    let total_pages = (total as f64 / limit as f64).ceil() as i64;
+    let next_cursor = next_cursor(&mut bookings, limit, "created_at", |row| {
+        (row.created_at.to_rfc3339(), row.id)
+    });
 
-    Ok(Json(ApiResponse::success(PaginatedResponse {
-        items: bookings,
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(
+        bookings,
+        &pagination,
         total,
-        page: pagination.current_page(),
-        per_page: limit,
-        total_pages,
-    })))
+        next_cursor,
+    ))))
 }
 
 /// GET /api/admin/bookings/:id
 ///
 /// Get a single booking by ID.
 pub async fn get_booking(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Booking>>, AppError> {
@@ -91,24 +109,70 @@ pub async fn get_booking(
 /// PUT /api/admin/bookings/:id/status
 ///
 /// Update the status of a booking (confirm, check-in, check-out, cancel, refund).
+/// Only moves allowed by [`BookingStatus::allowed_transitions`] are accepted;
+/// every change is recorded in `booking_status_history`.
+/// Moving to refunded issues the refunds still owed and fails with 409
+/// unless they have all gone through.
 pub async fn update_booking_status(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    Require { claims, .. }: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateBookingStatusRequest>,
 ) -> Result<Json<ApiResponse<Booking>>, AppError> {
-    use shared::models::BookingStatus;
+    let admin_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let current: Booking = sqlx::query_as("SELECT * FROM bookings WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Booking {id} not found")))?;
+
+    current.status.check_transition(&payload.status)?;
+
+    // A booking is refunded when its refunds have gone through, not when an
+    // admin says so: issue whatever is still owed, which moves the booking
+    // on once everything owed has been refunded.
+    if payload.status == BookingStatus::Refunded {
+        tx.rollback().await?;
+        if let Err(e) = payments::issue_refunds(&state.pool, state.payments.as_ref(), id).await {
+            tracing::error!("Refund for booking {id} failed: {e}");
+        }
+        let booking = sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = $1")
+            .bind(id)
+            .fetch_one(&state.pool)
+            .await?;
+        if booking.status != BookingStatus::Refunded {
+            return Err(AppError::Conflict(
+                "The booking's refunds have not all gone through; see its payments".to_string(),
+            ));
+        }
+        return Ok(Json(ApiResponse::success(booking)));
+    }
+
+    // If cancelling, also set cancelled_at and cancellation_reason, and record
+    // the refund due under the property's cancellation policy.
+    let booking = if payload.status == BookingStatus::Cancelled {
+        let rules: Option<PropertyRules> =
+            sqlx::query_as("SELECT * FROM property_rules WHERE property_id = $1")
+                .bind(current.property_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        let cancelled_at = Utc::now();
+        let quote = refund_for_booking(&current, rules.as_ref(), cancelled_at);
 
-    // If cancelling, also set cancelled_at and cancellation_reason.
-    let booking = if payload.status == BookingStatus::Cancelled
-        || payload.status == BookingStatus::Refunded
-    {
         sqlx::query_as::<_, Booking>(
             r#"
             UPDATE bookings
             SET status = $2,
-                cancelled_at = NOW(),
+                cancelled_at = COALESCE(cancelled_at, $4),
                 cancellation_reason = COALESCE($3, cancellation_reason),
+                refund_amount = COALESCE(refund_amount, $5),
                 updated_at = NOW()
             WHERE id = $1
             RETURNING *
@@ -117,7 +181,9 @@ pub async fn update_booking_status(
         .bind(id)
         .bind(&payload.status)
         .bind(&payload.reason)
-        .fetch_optional(&state.pool)
+        .bind(cancelled_at)
+        .bind(quote.refund_amount)
+        .fetch_one(&mut *tx)
         .await?
     } else {
         sqlx::query_as::<_, Booking>(
@@ -130,12 +196,110 @@ pub async fn update_booking_status(
         )
         .bind(id)
         .bind(&payload.status)
-        .fetch_optional(&state.pool)
+        .fetch_one(&mut *tx)
         .await?
     };
 
-    let booking =
-        booking.ok_or_else(|| AppError::NotFound(format!("Booking {id} not found")))?;
+    sqlx::query(
+        r#"
+        INSERT INTO booking_status_history (booking_id, from_status, to_status, changed_by, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(id)
+    .bind(&current.status)
+    .bind(&booking.status)
+    .bind(admin_id)
+    .bind(&payload.reason)
+    .execute(&mut *tx)
+    .await?;
+
+    notifications::booking_status_changed(&mut tx, id, &booking.status).await?;
+
+    tx.commit().await?;
+
+    // Refund what the guest has paid beyond what they forfeit. A failed
+    // refund stays on record and can be retried with POST .../refunds.
+    if booking.status != BookingStatus::Cancelled {
+        return Ok(Json(ApiResponse::success(booking)));
+    }
+    if let Err(e) = payments::issue_refunds(&state.pool, state.payments.as_ref(), id).await {
+        tracing::error!("Refund for cancelled booking {id} failed: {e}");
+    }
+    let booking = sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = $1")
+        .bind(id)
+        .fetch_one(&state.pool)
+        .await?;
 
     Ok(Json(ApiResponse::success(booking)))
 }
+
+/// GET /api/admin/bookings/:id/payments
+///
+/// The payments and refunds on a booking.
+pub async fn get_booking_payments(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PaymentSummary>>, AppError> {
+    let booking = sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Booking {id} not found")))?;
+
+    let summary = payments::payment_summary(&state.pool, &booking).await?;
+
+    Ok(Json(ApiResponse::success(summary)))
+}
+
+/// POST /api/admin/bookings/:id/refunds
+///
+/// Issue whatever refund is still owed on a cancelled booking, e.g. after a
+/// refund failed at the payment provider. Returns the refunds issued, which
+/// is empty when nothing is owed.
+pub async fn issue_refunds(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<Refund>>>, AppError> {
+    let refunds = payments::issue_refunds(&state.pool, state.payments.as_ref(), id).await?;
+
+    Ok(Json(ApiResponse::success(refunds)))
+}
+
+/// GET /api/admin/bookings/:id/history
+///
+/// Every status change of a booking, oldest first, with who made it.
+pub async fn get_booking_history(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<BookingStatusHistoryEntry>>>, AppError> {
+    let exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM bookings WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&state.pool)
+            .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("Booking {id} not found")));
+    }
+
+    let history = sqlx::query_as::<_, BookingStatusHistoryEntry>(
+        r#"
+        SELECT h.id, h.from_status, h.to_status, h.changed_by,
+               u.full_name AS changed_by_name, u.email AS changed_by_email,
+               h.reason, h.created_at
+        FROM booking_status_history h
+        LEFT JOIN users u ON u.id = h.changed_by
+        WHERE h.booking_id = $1
+        ORDER BY h.created_at ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse::success(history)))
+}
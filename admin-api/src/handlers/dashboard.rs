@@ -13,7 +13,7 @@ use crate::AppState;
 ///
 /// Aggregate statistics for the admin dashboard.
 pub async fn get_stats(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<ApiResponse<DashboardStats>>, AppError> {
     // Total properties.
@@ -27,6 +27,13 @@ pub async fn get_stats(
             .fetch_one(&state.pool)
             .await?;
 
+    // Listings waiting for review.
+    let pending_review_properties = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM properties WHERE listing_status = 'pending_review'",
+    )
+    .fetch_one(&state.pool)
+    .await?;
+
     // Total users.
     let total_users = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
         .fetch_one(&state.pool)
@@ -45,10 +52,11 @@ pub async fn get_stats(
     .await?;
 
     // Total views across all properties.
-    let total_views =
-        sqlx::query_scalar::<_, i64>("SELECT COALESCE(SUM(view_count::bigint), 0)::bigint FROM properties")
-            .fetch_one(&state.pool)
-            .await?;
+    let total_views = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(SUM(view_count::bigint), 0)::bigint FROM properties",
+    )
+    .fetch_one(&state.pool)
+    .await?;
 
     // Properties grouped by type.
     let properties_by_type = sqlx::query_as::<_, TypeCount>(
@@ -101,6 +109,7 @@ pub async fn get_stats(
     let stats = DashboardStats {
         total_properties,
         active_properties,
+        pending_review_properties,
         total_users,
         total_inquiries,
         new_inquiries,
@@ -0,0 +1,111 @@
+use axum::extract::{Multipart, Path, State};
+use axum::Json;
+use shared::auth::{perm, Require};
+use shared::currency::{self, parse_rates_file, MAX_RATES_FILE_BYTES};
+use shared::errors::AppError;
+use shared::models::ExchangeRate;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::middleware::RequireAdmin;
+use crate::models::{ApiResponse, SetExchangeRateRequest};
+use crate::AppState;
+
+/// GET /api/admin/exchange-rates
+///
+/// Every rate, as units of the currency per one US dollar.
+pub async fn list_exchange_rates(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<ExchangeRate>>>, AppError> {
+    let rates = sqlx::query_as::<_, ExchangeRate>("SELECT * FROM exchange_rates ORDER BY currency")
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(ApiResponse::success(rates)))
+}
+
+/// PUT /api/admin/exchange-rates/:currency
+///
+/// Add a currency or change its rate.
+pub async fn set_exchange_rate(
+    Require { claims, .. }: Require<perm::ManageExchangeRates>,
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+    Json(payload): Json<SetExchangeRateRequest>,
+) -> Result<Json<ApiResponse<ExchangeRate>>, AppError> {
+    let admin_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))?;
+
+    let rate = currency::set_rates(
+        &state.pool,
+        &[(code, payload.rate)],
+        "manual",
+        Some(admin_id),
+    )
+    .await?
+    .pop()
+    .ok_or_else(|| AppError::Internal("Exchange rate was not saved".to_string()))?;
+
+    Ok(Json(ApiResponse::success(rate)))
+}
+
+/// DELETE /api/admin/exchange-rates/:currency
+///
+/// Refused while any listing is priced in the currency.
+pub async fn delete_exchange_rate(
+    _: Require<perm::ManageExchangeRates>,
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    currency::delete_rate(&state.pool, &code).await?;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Exchange rate deleted"
+    }))))
+}
+
+/// POST /api/admin/exchange-rates/import
+///
+/// Import rates from an uploaded CSV or JSON file (multipart field `file`).
+/// Currencies in the file are added or updated; others are left alone. If any
+/// line is invalid, nothing is imported.
+pub async fn import_exchange_rates(
+    Require { claims, .. }: Require<perm::ManageExchangeRates>,
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<Json<ApiResponse<Vec<ExchangeRate>>>, AppError> {
+    let admin_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))?;
+
+    let mut file: Option<Vec<u8>> = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read multipart field: {e}")))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read file data: {e}")))?;
+        if data.len() > MAX_RATES_FILE_BYTES {
+            return Err(AppError::BadRequest(
+                "Exchange rate file is too large".to_string(),
+            ));
+        }
+        file = Some(data.to_vec());
+    }
+
+    let data = file.ok_or_else(|| AppError::BadRequest("No file provided".to_string()))?;
+    let rates = parse_rates_file(&data)?;
+    let saved = currency::set_rates(&state.pool, &rates, "import", Some(admin_id)).await?;
+
+    Ok(Json(ApiResponse::success(saved)))
+}
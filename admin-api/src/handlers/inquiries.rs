@@ -1,7 +1,9 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
+use chrono::{DateTime, Utc};
 use shared::errors::AppError;
 use shared::models::Inquiry;
+use shared::pagination::next_cursor;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -15,13 +17,14 @@ use crate::AppState;
 ///
 /// List all inquiries with pagination and optional status filter.
 pub async fn list_inquiries(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Query(params): Query<InquiryFilterParams>,
 ) -> Result<Json<ApiResponse<PaginatedResponse<Inquiry>>>, AppError> {
     let pagination = params.pagination();
     let limit = pagination.limit();
     let offset = pagination.offset();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
 
     let status_str = params.status.as_ref().map(|s| {
         serde_json::to_value(s)
@@ -30,48 +33,58 @@ pub async fn list_inquiries(
             .unwrap_or_default()
     });
 
-    let inquiries = sqlx::query_as::<_, Inquiry>(
+    let mut inquiries = sqlx::query_as::<_, Inquiry>(
         r#"
         SELECT *
         FROM inquiries
         WHERE ($1::text IS NULL OR status::text = $1)
-        ORDER BY created_at DESC
-        LIMIT $2 OFFSET $3
+            AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4 OFFSET $5
         "#,
     )
     .bind(&status_str)
-    .bind(limit)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit + 1)
     .bind(offset)
     .fetch_all(&state.pool)
     .await?;
 
-    let total = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*)
-        FROM inquiries
-        WHERE ($1::text IS NULL OR status::text = $1)
-        "#,
-    )
-    .bind(&status_str)
-    .fetch_one(&state.pool)
-    .await?;
+    let total = if pagination.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar::<_, i64>(
+                r#"
+                SELECT COUNT(*)
+                FROM inquiries
+                WHERE ($1::text IS NULL OR status::text = $1)
+                "#,
+            )
+            .bind(&status_str)
+            .fetch_one(&state.pool)
+            .await?,
+        )
+    };
 
-    let total_pages = (total as f64 / limit as f64).ceil() as i64;
+    let next_cursor = next_cursor(&mut inquiries, limit, "created_at", |row| {
+        (row.created_at.to_rfc3339(), row.id)
+    });
 
-    Ok(Json(ApiResponse::success(PaginatedResponse {
-        items: inquiries,
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(
+        inquiries,
+        &pagination,
         total,
-        page: pagination.current_page(),
-        per_page: limit,
-        total_pages,
-    })))
+        next_cursor,
+    ))))
 }
 
 /// GET /api/admin/inquiries/:id
 ///
 /// Get a single inquiry by ID.
 pub async fn get_inquiry(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Inquiry>>, AppError> {
@@ -88,7 +101,7 @@ pub async fn get_inquiry(
 ///
 /// Update the status of an inquiry (e.g., New -> Read -> Replied -> Closed).
 pub async fn update_inquiry_status(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateInquiryStatusRequest>,
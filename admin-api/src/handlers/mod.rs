@@ -1,6 +1,9 @@
+pub mod amenities;
 pub mod auth;
+pub mod blocked_dates;
 pub mod bookings;
 pub mod dashboard;
+pub mod exchange_rates;
 pub mod inquiries;
 pub mod properties;
 pub mod reviews;
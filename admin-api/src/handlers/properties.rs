@@ -1,31 +1,44 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
+use chrono::{DateTime, Utc};
+use shared::amenities::set_property_amenities;
+use shared::auth::{perm, Require};
+use shared::currency::listing_currency;
 use shared::errors::AppError;
-use shared::models::Property;
+use shared::listings::{self, ReviewDecision};
+use shared::models::{ListingStatus, Property};
+use shared::pagination::next_cursor;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::middleware::{RequireAdmin, RequireAdminOrAbove};
+use crate::middleware::RequireAdmin;
 use crate::models::{
-    slugify, ApiResponse, CreatePropertyRequest, PaginatedResponse, PropertyFilterParams,
+    slugify, ApiResponse, CreatePropertyRequest, ListingStatusHistoryEntry, PaginatedResponse,
+    PropertyFilterParams, ReviewListingRequest, ReviewQueueItem, ReviewQueueParams,
     UpdatePropertyRequest,
 };
 use crate::AppState;
 
+fn admin_id(sub: &str) -> Result<Uuid, AppError> {
+    sub.parse()
+        .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))
+}
+
 /// GET /api/admin/properties
 pub async fn list_properties(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Query(params): Query<PropertyFilterParams>,
 ) -> Result<Json<ApiResponse<PaginatedResponse<Property>>>, AppError> {
     let pagination = params.pagination();
     let limit = pagination.limit();
     let offset = pagination.offset();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
 
     let search_pattern = params.search.as_ref().map(|s| format!("%{s}%"));
 
-    let rows = sqlx::query_as::<_, Property>(
+    let mut rows = sqlx::query_as::<_, Property>(
         r#"
         SELECT *
         FROM properties
@@ -36,8 +49,10 @@ pub async fn list_properties(
             AND ($4::bool IS NULL OR is_featured = $4)
             AND ($5::bool IS NULL OR is_active = $5)
             AND ($6::text IS NULL OR title ILIKE $6 OR description ILIKE $6)
-        ORDER BY created_at DESC
-        LIMIT $7 OFFSET $8
+            AND ($7::listing_status IS NULL OR listing_status = $7)
+            AND ($8::timestamptz IS NULL OR (created_at, id) < ($8, $9))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $10 OFFSET $11
         "#,
     )
     .bind(params.property_type.as_ref().map(|t| {
@@ -56,57 +71,69 @@ pub async fn list_properties(
     .bind(params.is_featured)
     .bind(params.is_active)
     .bind(&search_pattern)
-    .bind(limit)
+    .bind(params.listing_status)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit + 1)
     .bind(offset)
     .fetch_all(&state.pool)
     .await?;
 
-    let total = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*)
-        FROM properties
-        WHERE
-            ($1::text IS NULL OR property_type::text = $1)
-            AND ($2::text IS NULL OR listing_type::text = $2)
-            AND ($3::text IS NULL OR area ILIKE $3)
-            AND ($4::bool IS NULL OR is_featured = $4)
-            AND ($5::bool IS NULL OR is_active = $5)
-            AND ($6::text IS NULL OR title ILIKE $6 OR description ILIKE $6)
-        "#,
-    )
-    .bind(params.property_type.as_ref().map(|t| {
-        serde_json::to_value(t)
-            .ok()
-            .and_then(|v| v.as_str().map(String::from))
-            .unwrap_or_default()
-    }))
-    .bind(params.listing_type.as_ref().map(|t| {
-        serde_json::to_value(t)
-            .ok()
-            .and_then(|v| v.as_str().map(String::from))
-            .unwrap_or_default()
-    }))
-    .bind(params.area.as_ref().map(|a| format!("%{a}%")))
-    .bind(params.is_featured)
-    .bind(params.is_active)
-    .bind(&search_pattern)
-    .fetch_one(&state.pool)
-    .await?;
+    let total = if pagination.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar::<_, i64>(
+                r#"
+                SELECT COUNT(*)
+                FROM properties
+                WHERE
+                    ($1::text IS NULL OR property_type::text = $1)
+                    AND ($2::text IS NULL OR listing_type::text = $2)
+                    AND ($3::text IS NULL OR area ILIKE $3)
+                    AND ($4::bool IS NULL OR is_featured = $4)
+                    AND ($5::bool IS NULL OR is_active = $5)
+                    AND ($6::text IS NULL OR title ILIKE $6 OR description ILIKE $6)
+                    AND ($7::listing_status IS NULL OR listing_status = $7)
+                "#,
+            )
+            .bind(params.property_type.as_ref().map(|t| {
+                serde_json::to_value(t)
+                    .ok()
+                    .and_then(|v| v.as_str().map(String::from))
+                    .unwrap_or_default()
+            }))
+            .bind(params.listing_type.as_ref().map(|t| {
+                serde_json::to_value(t)
+                    .ok()
+                    .and_then(|v| v.as_str().map(String::from))
+                    .unwrap_or_default()
+            }))
+            .bind(params.area.as_ref().map(|a| format!("%{a}%")))
+            .bind(params.is_featured)
+            .bind(params.is_active)
+            .bind(&search_pattern)
+            .bind(params.listing_status)
+            .fetch_one(&state.pool)
+            .await?,
+        )
+    };
 
-    let total_pages = (total as f64 / limit as f64).ceil() as i64;
+    let next_cursor = next_cursor(&mut rows, limit, "created_at", |row| {
+        (row.created_at.to_rfc3339(), row.id)
+    });
 
-    Ok(Json(ApiResponse::success(PaginatedResponse {
-        items: rows,
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(
+        rows,
+        &pagination,
         total,
-        page: pagination.current_page(),
-        per_page: limit,
-        total_pages,
-    })))
+        next_cursor,
+    ))))
 }
 
 /// GET /api/admin/properties/:id
 pub async fn get_property(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Property>>, AppError> {
@@ -120,35 +147,40 @@ pub async fn get_property(
 }
 
 /// POST /api/admin/properties
+///
+/// Listings created by admins are approved straight away.
 pub async fn create_property(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    Require { claims, .. }: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Json(payload): Json<CreatePropertyRequest>,
 ) -> Result<Json<ApiResponse<Property>>, AppError> {
     payload
         .validate()
         .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+    let admin_id = admin_id(&claims.sub)?;
 
     let id = Uuid::new_v4();
     let slug = format!("{}-{}", slugify(&payload.title), &id.to_string()[..8]);
     let features = payload.features.unwrap_or(serde_json::json!([]));
     let images = payload.images.unwrap_or(serde_json::json!([]));
-    let currency = payload.currency.unwrap_or_else(|| "USD".to_string());
+    let currency = listing_currency(&state.pool, payload.currency.as_deref()).await?;
     let is_featured = payload.is_featured.unwrap_or(false);
 
+    let mut tx = state.pool.begin().await?;
+
     let property = sqlx::query_as::<_, Property>(
         r#"
         INSERT INTO properties (
             id, owner_id, title, slug, description, property_type, listing_type,
             price, price_period, currency, area, address, latitude, longitude,
             bedrooms, bathrooms, land_size_sqm, building_size_sqm, year_built,
-            features, images, thumbnail_url, is_featured, is_active, view_count
+            features, images, thumbnail_url, is_featured, listing_status, view_count
         )
         VALUES (
             $1, $2, $3, $4, $5, $6, $7,
             $8, $9, $10, $11, $12, $13, $14,
             $15, $16, $17, $18, $19,
-            $20, $21, $22, $23, true, 0
+            $20, $21, $22, $23, 'approved', 0
         )
         RETURNING *
         "#,
@@ -176,15 +208,31 @@ pub async fn create_property(
     .bind(&images)
     .bind(&payload.thumbnail_url)
     .bind(is_featured)
-    .fetch_one(&state.pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    listings::record_status(
+        &mut tx,
+        id,
+        None,
+        ListingStatus::Approved,
+        Some(admin_id),
+        None,
+    )
+    .await?;
+
+    if let Some(ref slugs) = payload.amenity_slugs {
+        set_property_amenities(&mut tx, id, slugs).await?;
+    }
+
+    tx.commit().await?;
+
     Ok(Json(ApiResponse::success(property)))
 }
 
 /// PUT /api/admin/properties/:id
 pub async fn update_property(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdatePropertyRequest>,
@@ -195,6 +243,11 @@ pub async fn update_property(
         .await?
         .ok_or_else(|| AppError::NotFound(format!("Property {id} not found")))?;
 
+    let currency = match payload.currency {
+        Some(ref code) => listing_currency(&state.pool, Some(code)).await?,
+        None => existing.currency,
+    };
+
     let title_changed = payload.title.is_some();
     let title = payload.title.unwrap_or(existing.title);
     let new_slug = if title_changed {
@@ -203,6 +256,8 @@ pub async fn update_property(
         existing.slug
     };
 
+    let mut tx = state.pool.begin().await?;
+
     let property = sqlx::query_as::<_, Property>(
         r#"
         UPDATE properties
@@ -226,7 +281,7 @@ pub async fn update_property(
     .bind(payload.listing_type.unwrap_or(existing.listing_type))
     .bind(payload.price.unwrap_or(existing.price))
     .bind(payload.price_period.or(existing.price_period))
-    .bind(payload.currency.unwrap_or(existing.currency))
+    .bind(&currency)
     .bind(payload.area.unwrap_or(existing.area))
     .bind(payload.address.or(existing.address))
     .bind(payload.latitude.or(existing.latitude))
@@ -241,33 +296,51 @@ pub async fn update_property(
     .bind(payload.thumbnail_url.or(existing.thumbnail_url))
     .bind(payload.is_featured.unwrap_or(existing.is_featured))
     .bind(payload.owner_id.unwrap_or(existing.owner_id))
-    .fetch_one(&state.pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    if let Some(ref slugs) = payload.amenity_slugs {
+        set_property_amenities(&mut tx, id, slugs).await?;
+    }
+
+    tx.commit().await?;
+
     Ok(Json(ApiResponse::success(property)))
 }
 
 /// DELETE /api/admin/properties/:id
-/// Only admin and super_admin can delete (soft-delete) properties.
+/// Only admin and super_admin can delete (soft-delete) properties. Deleted
+/// listings are archived; deleting an archived listing changes nothing.
 pub async fn delete_property(
-    RequireAdminOrAbove(_claims, _role): RequireAdminOrAbove,
+    Require { claims, .. }: Require<perm::DeleteProperties>,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Property>>, AppError> {
-    let property = sqlx::query_as::<_, Property>(
-        "UPDATE properties SET is_active = false, updated_at = NOW() WHERE id = $1 RETURNING *",
-    )
-    .bind(id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| AppError::NotFound(format!("Property {id} not found")))?;
+    let admin_id = admin_id(&claims.sub)?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let current =
+        sqlx::query_as::<_, Property>("SELECT * FROM properties WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Property {id} not found")))?;
+
+    let property = if current.listing_status == ListingStatus::Archived {
+        current
+    } else {
+        listings::change_status(&mut tx, id, ListingStatus::Archived, admin_id, None).await?
+    };
+
+    tx.commit().await?;
 
     Ok(Json(ApiResponse::success(property)))
 }
 
 /// PUT /api/admin/properties/:id/toggle-featured
 pub async fn toggle_featured(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Property>>, AppError> {
@@ -281,3 +354,148 @@ pub async fn toggle_featured(
 
     Ok(Json(ApiResponse::success(property)))
 }
+
+/// GET /api/admin/properties/review-queue
+///
+/// Listings awaiting review, longest-waiting first.
+pub async fn review_queue(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ReviewQueueParams>,
+) -> Result<Json<ApiResponse<PaginatedResponse<ReviewQueueItem>>>, AppError> {
+    let pagination = params.pagination();
+    let limit = pagination.limit();
+    let after = pagination.after::<DateTime<Utc>>("submitted_at")?;
+
+    let mut rows = sqlx::query_as::<_, ReviewQueueItem>(
+        r#"
+        SELECT p.*, o.full_name AS owner_name, o.email AS owner_email, s.submitted_at
+        FROM properties p
+        JOIN users o ON o.id = p.owner_id
+        CROSS JOIN LATERAL (
+            SELECT COALESCE(MAX(h.created_at), p.created_at) AS submitted_at
+            FROM listing_status_history h
+            WHERE h.property_id = p.id AND h.to_status = 'pending_review'
+        ) s
+        WHERE p.listing_status = 'pending_review'
+            AND ($1::timestamptz IS NULL OR (s.submitted_at, p.id) > ($1, $2))
+        ORDER BY s.submitted_at ASC, p.id ASC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(after.map(|(submitted_at, _)| submitted_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit + 1)
+    .bind(pagination.offset())
+    .fetch_all(&state.pool)
+    .await?;
+
+    let total = if pagination.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM properties WHERE listing_status = 'pending_review'",
+            )
+            .fetch_one(&state.pool)
+            .await?,
+        )
+    };
+
+    let next_cursor = next_cursor(&mut rows, limit, "submitted_at", |row| {
+        (row.submitted_at.to_rfc3339(), row.property.id)
+    });
+
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(
+        rows,
+        &pagination,
+        total,
+        next_cursor,
+    ))))
+}
+
+/// POST /api/admin/properties/:id/approve
+///
+/// Publish a listing that is awaiting review or was rejected. The optional
+/// reason is passed on to the owner.
+pub async fn approve_property(
+    Require { claims, .. }: Require<perm::ModerateListings>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ReviewListingRequest>,
+) -> Result<Json<ApiResponse<Property>>, AppError> {
+    review_property(&state, &claims.sub, id, ReviewDecision::Approve, &payload).await
+}
+
+/// POST /api/admin/properties/:id/reject
+///
+/// Turn down a listing that is awaiting review, or take down a live one. The
+/// owner is told the reason and can edit and resubmit the listing.
+pub async fn reject_property(
+    Require { claims, .. }: Require<perm::ModerateListings>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ReviewListingRequest>,
+) -> Result<Json<ApiResponse<Property>>, AppError> {
+    if payload.reason().is_none() {
+        return Err(AppError::BadRequest(
+            "A reason is required to reject a listing".to_string(),
+        ));
+    }
+    review_property(&state, &claims.sub, id, ReviewDecision::Reject, &payload).await
+}
+
+async fn review_property(
+    state: &AppState,
+    sub: &str,
+    id: Uuid,
+    decision: ReviewDecision,
+    payload: &ReviewListingRequest,
+) -> Result<Json<ApiResponse<Property>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+    let admin_id = admin_id(sub)?;
+
+    let mut tx = state.pool.begin().await?;
+    let property = listings::review(&mut tx, id, decision, admin_id, payload.reason()).await?;
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse::success(property)))
+}
+
+/// GET /api/admin/properties/:id/history
+///
+/// Every status change of a listing, oldest first, with who made it.
+pub async fn get_property_history(
+    _: RequireAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<ListingStatusHistoryEntry>>>, AppError> {
+    let exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM properties WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&state.pool)
+            .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("Property {id} not found")));
+    }
+
+    let history = sqlx::query_as::<_, ListingStatusHistoryEntry>(
+        r#"
+        SELECT h.id, h.from_status, h.to_status, h.changed_by,
+               u.full_name AS changed_by_name, u.email AS changed_by_email,
+               h.reason, h.created_at
+        FROM listing_status_history h
+        LEFT JOIN users u ON u.id = h.changed_by
+        WHERE h.property_id = $1
+        ORDER BY h.created_at ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse::success(history)))
+}
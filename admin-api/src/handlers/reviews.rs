@@ -1,7 +1,10 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
+use chrono::{DateTime, Utc};
 use shared::errors::AppError;
 use shared::models::Review;
+use shared::notifications;
+use shared::pagination::next_cursor;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -13,63 +16,84 @@ use crate::AppState;
 ///
 /// List all reviews with pagination and optional is_approved filter.
 pub async fn list_reviews(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Query(params): Query<ReviewFilterParams>,
 ) -> Result<Json<ApiResponse<PaginatedResponse<Review>>>, AppError> {
     let pagination = params.pagination();
     let limit = pagination.limit();
     let offset = pagination.offset();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
 
-    let reviews = sqlx::query_as::<_, Review>(
+    let mut reviews = sqlx::query_as::<_, Review>(
         r#"
         SELECT *
         FROM reviews
         WHERE ($1::bool IS NULL OR is_approved = $1)
             AND ($2::bool IS NULL OR is_flagged = $2)
-        ORDER BY created_at DESC
-        LIMIT $3 OFFSET $4
+            AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $5 OFFSET $6
         "#,
     )
     .bind(params.is_approved)
     .bind(params.is_flagged)
-    .bind(limit)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit + 1)
     .bind(offset)
     .fetch_all(&state.pool)
     .await?;
 
-    let total = sqlx::query_scalar::<_, i64>(
-        r#"
-        SELECT COUNT(*)
-        FROM reviews
-        WHERE ($1::bool IS NULL OR is_approved = $1)
-            AND ($2::bool IS NULL OR is_flagged = $2)
-        "#,
-    )
-    .bind(params.is_approved)
-    .bind(params.is_flagged)
-    .fetch_one(&state.pool)
-    .await?;
+    let total = if pagination.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar::<_, i64>(
+                r#"
+                SELECT COUNT(*)
+                FROM reviews
+                WHERE ($1::bool IS NULL OR is_approved = $1)
+                    AND ($2::bool IS NULL OR is_flagged = $2)
+                "#,
+            )
+            .bind(params.is_approved)
+            .bind(params.is_flagged)
+            .fetch_one(&state.pool)
+            .await?,
+        )
+    };
 
-    let total_pages = (total as f64 / limit as f64).ceil() as i64;
+    let next_cursor = next_cursor(&mut reviews, limit, "created_at", |row| {
+        (row.created_at.to_rfc3339(), row.id)
+    });
 
-    Ok(Json(ApiResponse::success(PaginatedResponse {
-        items: reviews,
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(
+        reviews,
+        &pagination,
         total,
-        page: pagination.current_page(),
-        per_page: limit,
-        total_pages,
-    })))
+        next_cursor,
+    ))))
 }
 
 /// PUT /api/admin/reviews/:id/approve
 ///
 /// Approve a review (sets is_approved=true) and recalculates the property avg_rating.
+/// The property owner is notified the first time a review is approved.
 pub async fn approve_review(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Review>>, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    let was_approved =
+        sqlx::query_scalar::<_, bool>("SELECT is_approved FROM reviews WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Review {id} not found")))?;
+
     let review = sqlx::query_as::<_, Review>(
         r#"
         UPDATE reviews
@@ -79,9 +103,15 @@ pub async fn approve_review(
         "#,
     )
     .bind(id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| AppError::NotFound(format!("Review {id} not found")))?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Approving an already published review again does not notify twice.
+    if !was_approved {
+        notifications::review_published(&mut tx, id).await?;
+    }
+
+    tx.commit().await?;
 
     Ok(Json(ApiResponse::success(review)))
 }
@@ -90,7 +120,7 @@ pub async fn approve_review(
 ///
 /// Flag a review (sets is_flagged=true).
 pub async fn flag_review(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Review>>, AppError> {
@@ -114,7 +144,7 @@ pub async fn flag_review(
 ///
 /// Delete a review and recalculate the property avg_rating.
 pub async fn delete_review(
-    RequireAdmin(_claims, _role): RequireAdmin,
+    _: RequireAdmin,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Review>>, AppError> {
@@ -1,13 +1,16 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
-use shared::auth::hash_password;
+use chrono::{DateTime, Utc};
+use shared::auth::{hash_password, perm, Require};
 use shared::errors::AppError;
 use shared::models::UserRole;
+use shared::pagination::next_cursor;
+use shared::refresh_tokens::revoke_user_sessions;
+use shared::two_factor;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::middleware::RequireAdminOrAbove;
 use crate::models::{
     ApiResponse, CreateUserRequest, PaginatedResponse, PaginationParams, UpdateUserRequest,
     UserResponse,
@@ -18,46 +21,57 @@ use crate::AppState;
 ///
 /// List all users with pagination. Only admin and super_admin can access.
 pub async fn list_users(
-    RequireAdminOrAbove(_claims, _role): RequireAdminOrAbove,
+    _: Require<perm::ManageUsers>,
     State(state): State<Arc<AppState>>,
     Query(params): Query<PaginationParams>,
 ) -> Result<Json<ApiResponse<PaginatedResponse<UserResponse>>>, AppError> {
     let limit = params.limit();
     let offset = params.offset();
+    let after = params.after::<DateTime<Utc>>("created_at")?;
 
-    let users = sqlx::query_as::<_, UserResponse>(
+    let mut users = sqlx::query_as::<_, UserResponse>(
         r#"
         SELECT id, email, full_name, phone, avatar_url, role, is_active, created_at, updated_at
         FROM users
-        ORDER BY created_at DESC
-        LIMIT $1 OFFSET $2
+        WHERE $1::timestamptz IS NULL OR (created_at, id) < ($1, $2)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3 OFFSET $4
         "#,
     )
-    .bind(limit)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit + 1)
     .bind(offset)
     .fetch_all(&state.pool)
     .await?;
 
-    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
-        .fetch_one(&state.pool)
-        .await?;
+    let total = if params.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
+                .fetch_one(&state.pool)
+                .await?,
+        )
+    };
 
-    let total_pages = (total as f64 / limit as f64).ceil() as i64;
+    let next_cursor = next_cursor(&mut users, limit, "created_at", |row| {
+        (row.created_at.to_rfc3339(), row.id)
+    });
 
-    Ok(Json(ApiResponse::success(PaginatedResponse {
-        items: users,
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(
+        users,
+        &params,
         total,
-        page: params.current_page(),
-        per_page: limit,
-        total_pages,
-    })))
+        next_cursor,
+    ))))
 }
 
 /// GET /api/admin/users/:id
 ///
 /// Get a single user by ID. Only admin and super_admin can access.
 pub async fn get_user(
-    RequireAdminOrAbove(_claims, _role): RequireAdminOrAbove,
+    _: Require<perm::ManageUsers>,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
@@ -83,7 +97,9 @@ pub async fn get_user(
 ///   - super_admin can create any role
 ///   - admin can only create operational, agent, or user roles
 pub async fn create_user(
-    RequireAdminOrAbove(_claims, caller_role): RequireAdminOrAbove,
+    Require {
+        role: caller_role, ..
+    }: Require<perm::ManageUsers>,
     State(state): State<Arc<AppState>>,
     Json(payload): Json<CreateUserRequest>,
 ) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
@@ -130,7 +146,7 @@ pub async fn create_user(
     .bind(&payload.full_name)
     .bind(&payload.phone)
     .bind(&payload.avatar_url)
-    .bind(&payload.role)
+    .bind(payload.role)
     .bind(now)
     .fetch_one(&state.pool)
     .await?;
@@ -141,9 +157,12 @@ pub async fn create_user(
 /// PUT /api/admin/users/:id
 ///
 /// Update an existing user. Only provided (non-null) fields are changed.
-/// Role changes are enforced by the caller's own role permissions.
+/// Role changes are enforced by the caller's own role permissions. Changing
+/// the email, role or password revokes the user's sessions.
 pub async fn update_user(
-    RequireAdminOrAbove(_claims, caller_role): RequireAdminOrAbove,
+    Require {
+        role: caller_role, ..
+    }: Require<perm::ManageUsers>,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateUserRequest>,
@@ -209,7 +228,14 @@ pub async fn update_user(
         None
     };
 
+    // Access tokens embed the email and role, and a password change should
+    // sign out whoever knew the old one.
+    let revoke_sessions = password_update.is_some()
+        || payload.email.as_ref().is_some_and(|e| *e != existing.email)
+        || payload.role.as_ref().is_some_and(|r| *r != existing.role);
+
     let now = chrono::Utc::now();
+    let mut tx = state.pool.begin().await?;
 
     // Build the update. If a new password was supplied, also update password_hash.
     let user = if let Some(ref pw_hash) = password_update {
@@ -236,7 +262,7 @@ pub async fn update_user(
         .bind(payload.avatar_url.or(existing.avatar_url))
         .bind(payload.role.unwrap_or(existing.role))
         .bind(now)
-        .fetch_one(&state.pool)
+        .fetch_one(&mut *tx)
         .await?
     } else {
         sqlx::query_as::<_, UserResponse>(
@@ -260,19 +286,28 @@ pub async fn update_user(
         .bind(payload.avatar_url.or(existing.avatar_url))
         .bind(payload.role.unwrap_or(existing.role))
         .bind(now)
-        .fetch_one(&state.pool)
+        .fetch_one(&mut *tx)
         .await?
     };
 
+    if revoke_sessions {
+        revoke_user_sessions(&mut tx, id).await?;
+    }
+
+    tx.commit().await?;
+
     Ok(Json(ApiResponse::success(user)))
 }
 
 /// PUT /api/admin/users/:id/toggle-active
 ///
 /// Toggle the `is_active` flag on a user. Only admin+ can do this.
+/// Deactivating a user also revokes all of their sessions.
 /// Cannot deactivate users with higher/equal privilege (unless super_admin).
 pub async fn toggle_active(
-    RequireAdminOrAbove(_claims, caller_role): RequireAdminOrAbove,
+    Require {
+        role: caller_role, ..
+    }: Require<perm::ManageUsers>,
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
@@ -291,6 +326,8 @@ pub async fn toggle_active(
         ));
     }
 
+    let mut tx = state.pool.begin().await?;
+
     let user = sqlx::query_as::<_, UserResponse>(
         r#"
         UPDATE users
@@ -300,9 +337,46 @@ pub async fn toggle_active(
         "#,
     )
     .bind(id)
-    .fetch_optional(&state.pool)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| AppError::NotFound(format!("User {id} not found")))?;
 
+    // A deactivated user is signed out everywhere straight away.
+    if !user.is_active {
+        revoke_user_sessions(&mut tx, id).await?;
+    }
+
+    tx.commit().await?;
+
     Ok(Json(ApiResponse::success(user)))
 }
+
+/// DELETE /api/admin/users/:id/two-factor
+///
+/// Remove a user's authenticator and recovery codes, for when both are lost,
+/// and sign them out everywhere. Only super_admin can do this. If two-factor
+/// authentication is mandatory they enrol again at their next login.
+pub async fn reset_two_factor(
+    _: Require<perm::ResetTwoFactor>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("User {id} not found")));
+    }
+
+    two_factor::disable(&mut tx, id).await?;
+    revoke_user_sessions(&mut tx, id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Two-factor authentication reset"
+    }))))
+}
@@ -4,7 +4,9 @@ mod models;
 mod routes;
 
 use axum::Router;
+use shared::payment_provider::PaymentProvider;
 use sqlx::PgPool;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
@@ -14,6 +16,11 @@ use tower_http::trace::TraceLayer;
 pub struct AppState {
     pub pool: PgPool,
     pub jwt_secret: String,
+    /// Admin-portal users must set up two-factor authentication before they
+    /// can log in (`ADMIN_REQUIRE_2FA=true`).
+    pub require_two_factor: bool,
+    /// Issues refunds on cancelled bookings.
+    pub payments: Arc<dyn PaymentProvider>,
 }
 
 #[tokio::main]
@@ -31,12 +38,20 @@ async fn main() {
 
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    let require_two_factor = std::env::var("ADMIN_REQUIRE_2FA").is_ok_and(|v| v == "true");
+    let payments =
+        shared::payment_provider::from_env().expect("Invalid payment provider configuration");
 
     let pool = shared::db::create_pool(&database_url)
         .await
         .expect("Failed to create database pool");
 
-    let state = Arc::new(AppState { pool, jwt_secret });
+    let state = Arc::new(AppState {
+        pool,
+        jwt_secret,
+        require_two_factor,
+        payments,
+    });
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -56,5 +71,11 @@ async fn main() {
         .await
         .expect("Failed to bind address");
 
-    axum::serve(listener, app).await.expect("Server error");
+    // Connection info gives handlers the client address for login rate limiting.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Server error");
 }
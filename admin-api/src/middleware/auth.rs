@@ -1,120 +1,57 @@
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
-use shared::auth::{verify_token, Claims};
+use shared::auth::{perm, Audience, Claims, Require, TokenAuthority};
 use shared::errors::AppError;
-use shared::models::UserRole;
+use sqlx::PgPool;
 use std::sync::Arc;
 
 use crate::AppState;
 
-// ---------------------------------------------------------------------------
-// Helper: extract and validate JWT, returning claims + parsed role
-// ---------------------------------------------------------------------------
-
-fn parse_role_from_claims(claims: &Claims) -> Result<UserRole, AppError> {
-    claims
-        .role
-        .parse::<UserRole>()
-        .map_err(|_| AppError::Unauthorized(format!("Unknown role: {}", claims.role)))
-}
-
-async fn extract_claims(
-    parts: &mut Parts,
-    state: &Arc<AppState>,
-) -> Result<(Claims, UserRole), AppError> {
-    let auth_header = parts
-        .headers
-        .get("Authorization")
-        .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| AppError::Unauthorized("Missing Authorization header".to_string()))?;
-
-    let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
-        AppError::Unauthorized("Invalid Authorization header format".to_string())
-    })?;
-
-    let claims = verify_token(token, &state.jwt_secret)?;
-    let role = parse_role_from_claims(&claims)?;
-
-    Ok((claims, role))
-}
-
-// ---------------------------------------------------------------------------
-// RequireAdmin: any admin-portal role (super_admin | admin | operational)
-// ---------------------------------------------------------------------------
-
-/// Extractor that verifies the caller has any admin-portal role.
-/// All three roles (super_admin, admin, operational) pass this check.
-#[derive(Debug, Clone)]
-pub struct RequireAdmin(pub Claims, pub UserRole);
-
-impl FromRequestParts<Arc<AppState>> for RequireAdmin {
-    type Rejection = AppError;
-
-    async fn from_request_parts(
-        parts: &mut Parts,
-        state: &Arc<AppState>,
-    ) -> Result<Self, Self::Rejection> {
-        let (claims, role) = extract_claims(parts, state).await?;
+impl TokenAuthority for AppState {
+    fn jwt_secret(&self) -> &str {
+        &self.jwt_secret
+    }
 
-        if !role.is_admin_portal_role() {
-            return Err(AppError::Unauthorized("Admin portal access required".to_string()));
-        }
+    fn pool(&self) -> &PgPool {
+        &self.pool
+    }
 
-        Ok(RequireAdmin(claims, role))
+    fn audience(&self) -> Audience {
+        Audience::Admin
     }
 }
 
 // ---------------------------------------------------------------------------
-// RequireAdminOrAbove: only super_admin or admin (not operational)
+// RequireAdmin: any admin-portal role (super_admin | admin | operational)
 // ---------------------------------------------------------------------------
 
-/// Extractor that requires at least the `admin` role.
-/// Operational team members are rejected.
-#[derive(Debug, Clone)]
-pub struct RequireAdminOrAbove(pub Claims, pub UserRole);
-
-impl FromRequestParts<Arc<AppState>> for RequireAdminOrAbove {
-    type Rejection = AppError;
-
-    async fn from_request_parts(
-        parts: &mut Parts,
-        state: &Arc<AppState>,
-    ) -> Result<Self, Self::Rejection> {
-        let (claims, role) = extract_claims(parts, state).await?;
-
-        if !role.can_manage_users() {
-            return Err(AppError::Unauthorized(
-                "Admin or Super Admin access required".to_string(),
-            ));
-        }
-
-        Ok(RequireAdminOrAbove(claims, role))
-    }
-}
+/// Extractor that verifies the caller has any admin-portal role. Endpoints
+/// needing more than that ask for the specific permission instead, e.g.
+/// `Require<perm::ManageUsers>`.
+pub type RequireAdmin = Require<perm::AdminPortal>;
 
 // ---------------------------------------------------------------------------
-// RequireSuperAdmin: only super_admin
+// OptionalAdmin: an admin-portal user if a token is sent, otherwise nothing
 // ---------------------------------------------------------------------------
 
-/// Extractor that requires the `super_admin` role.
+/// Extractor for endpoints that also accept other proof of identity, such
+/// as a two-factor challenge token. Yields `None` when no Authorization
+/// header is sent; a header that is sent must hold a valid admin token.
 #[derive(Debug, Clone)]
-pub struct RequireSuperAdmin(pub Claims, pub UserRole);
+pub struct OptionalAdmin(pub Option<Claims>);
 
-impl FromRequestParts<Arc<AppState>> for RequireSuperAdmin {
+impl FromRequestParts<Arc<AppState>> for OptionalAdmin {
     type Rejection = AppError;
 
     async fn from_request_parts(
         parts: &mut Parts,
         state: &Arc<AppState>,
     ) -> Result<Self, Self::Rejection> {
-        let (claims, role) = extract_claims(parts, state).await?;
-
-        if role != UserRole::SuperAdmin {
-            return Err(AppError::Unauthorized(
-                "Super Admin access required".to_string(),
-            ));
+        if !parts.headers.contains_key("Authorization") {
+            return Ok(OptionalAdmin(None));
         }
 
-        Ok(RequireSuperAdmin(claims, role))
+        let admin = RequireAdmin::from_request_parts(parts, state).await?;
+        Ok(OptionalAdmin(Some(admin.claims)))
     }
 }
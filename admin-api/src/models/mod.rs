@@ -1,7 +1,11 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use shared::models::{BookingStatus, InquiryStatus, ListingType, PricePeriod, PropertyType, UserRole};
+use shared::models::{
+    BookingStatus, InquiryStatus, ListingStatus, ListingType, PricePeriod, Property, PropertyType,
+    User, UserRole,
+};
+use shared::refresh_tokens::TokenPair;
 use uuid::Uuid;
 use validator::Validate;
 
@@ -41,33 +45,41 @@ impl<T: Serialize> ApiResponse<T> {
 // Pagination
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
-pub struct PaginationParams {
-    pub page: Option<i64>,
-    pub per_page: Option<i64>,
-}
-
-impl PaginationParams {
-    pub fn offset(&self) -> i64 {
-        (self.current_page() - 1) * self.limit()
-    }
-
-    pub fn limit(&self) -> i64 {
-        self.per_page.unwrap_or(20).clamp(1, 100)
-    }
-
-    pub fn current_page(&self) -> i64 {
-        self.page.unwrap_or(1).max(1)
-    }
-}
+pub use shared::pagination::PaginationParams;
 
 #[derive(Debug, Serialize)]
 pub struct PaginatedResponse<T: Serialize> {
     pub items: Vec<T>,
-    pub total: i64,
-    pub page: i64,
+    /// `total`, `page` and `total_pages` are left out of cursor pages, which
+    /// skip counting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,
     pub per_page: i64,
-    pub total_pages: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<i64>,
+    /// Pass as `cursor` to fetch the next page; `null` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<T: Serialize> PaginatedResponse<T> {
+    pub fn new(
+        items: Vec<T>,
+        pagination: &PaginationParams,
+        total: Option<i64>,
+        next_cursor: Option<String>,
+    ) -> Self {
+        let per_page = pagination.limit();
+        Self {
+            items,
+            total,
+            page: total.map(|_| pagination.current_page()),
+            per_page,
+            total_pages: total.map(|total| (total + per_page - 1) / per_page),
+            next_cursor,
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -84,10 +96,89 @@ pub struct AdminLoginRequest {
 
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
-    pub token: String,
+    #[serde(flatten)]
+    pub tokens: TokenPair,
     pub user: UserResponse,
 }
 
+/// Result of the password step of an admin login: a session, or a challenge
+/// to finish with a second factor.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum AdminLoginResponse {
+    Authenticated(AuthResponse),
+    TwoFactor(TwoFactorChallenge),
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallenge {
+    pub challenge_token: String,
+    /// The account has no authenticator yet and two-factor authentication is
+    /// mandatory: enrol with the challenge token to finish logging in.
+    pub setup_required: bool,
+    /// Seconds until the challenge token expires.
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorVerifyRequest {
+    pub challenge_token: String,
+    /// A code from the authenticator app, or a recovery code.
+    pub code: String,
+}
+
+/// Enrolment is authorised by an admin access token, or by the challenge
+/// token from a login that requires enrolment first.
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorSetupRequest {
+    pub challenge_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_url: String,
+    pub qr_code_svg: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorEnableRequest {
+    pub code: String,
+    pub challenge_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnabledResponse {
+    pub recovery_codes: Vec<String>,
+    /// The session, when enrolment finished a login.
+    #[serde(flatten)]
+    pub session: Option<AuthResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    /// A code from the authenticator app, or a recovery code.
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: String,
+    /// End every session of the account, not just this one.
+    #[serde(default)]
+    pub all_devices: bool,
+}
+
 // ---------------------------------------------------------------------------
 // User DTOs
 // ---------------------------------------------------------------------------
@@ -105,6 +196,22 @@ pub struct UserResponse {
     pub updated_at: DateTime<Utc>,
 }
 
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            email: user.email,
+            full_name: user.full_name,
+            phone: user.phone,
+            avatar_url: user.avatar_url,
+            role: user.role,
+            is_active: user.is_active,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateUserRequest {
     #[validate(email(message = "Invalid email address"))]
@@ -159,6 +266,8 @@ pub struct CreatePropertyRequest {
     pub thumbnail_url: Option<String>,
     pub is_featured: Option<bool>,
     pub owner_id: Uuid,
+    /// Amenities to attach, by slug.
+    pub amenity_slugs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -184,6 +293,8 @@ pub struct UpdatePropertyRequest {
     pub thumbnail_url: Option<String>,
     pub is_featured: Option<bool>,
     pub owner_id: Option<Uuid>,
+    /// Replaces the property's amenities when given.
+    pub amenity_slugs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -195,7 +306,9 @@ pub struct PropertyFilterParams {
     pub area: Option<String>,
     pub is_featured: Option<bool>,
     pub is_active: Option<bool>,
+    pub listing_status: Option<ListingStatus>,
     pub search: Option<String>,
+    pub cursor: Option<String>,
 }
 
 impl PropertyFilterParams {
@@ -203,10 +316,76 @@ impl PropertyFilterParams {
         PaginationParams {
             page: self.page,
             per_page: self.per_page,
+            cursor: self.cursor.clone(),
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// Moderation DTOs
+// ---------------------------------------------------------------------------
+
+/// An admin's decision on a listing. The reason is sent to the owner; it is
+/// required when rejecting.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewListingRequest {
+    #[validate(length(max = 2000, message = "Reason must be at most 2000 characters"))]
+    pub reason: Option<String>,
+}
+
+impl ReviewListingRequest {
+    /// The reason with surrounding whitespace removed, or `None` if blank.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQueueParams {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+impl ReviewQueueParams {
+    pub fn pagination(&self) -> PaginationParams {
+        PaginationParams {
+            page: self.page,
+            per_page: self.per_page,
+            cursor: self.cursor.clone(),
+        }
+    }
+}
+
+/// A listing awaiting review, with its owner and when it was (last)
+/// submitted.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReviewQueueItem {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub property: Property,
+    pub owner_name: String,
+    pub owner_email: String,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// A row of `listing_status_history` with the name of the user who made the
+/// change (`None` for changes made by migrations and deleted users).
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ListingStatusHistoryEntry {
+    pub id: Uuid,
+    pub from_status: Option<ListingStatus>,
+    pub to_status: ListingStatus,
+    pub changed_by: Option<Uuid>,
+    pub changed_by_name: Option<String>,
+    pub changed_by_email: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 // ---------------------------------------------------------------------------
 // Inquiry DTOs
 // ---------------------------------------------------------------------------
@@ -221,6 +400,7 @@ pub struct InquiryFilterParams {
     pub page: Option<i64>,
     pub per_page: Option<i64>,
     pub status: Option<InquiryStatus>,
+    pub cursor: Option<String>,
 }
 
 impl InquiryFilterParams {
@@ -228,6 +408,7 @@ impl InquiryFilterParams {
         PaginationParams {
             page: self.page,
             per_page: self.per_page,
+            cursor: self.cursor.clone(),
         }
     }
 }
@@ -240,6 +421,8 @@ impl InquiryFilterParams {
 pub struct DashboardStats {
     pub total_properties: i64,
     pub active_properties: i64,
+    /// Listings waiting in the moderation queue.
+    pub pending_review_properties: i64,
     pub total_users: i64,
     pub total_inquiries: i64,
     pub new_inquiries: i64,
@@ -297,12 +480,27 @@ pub struct UpdateBookingStatusRequest {
     pub reason: Option<String>,
 }
 
+/// A row of `booking_status_history` with the name of the user who made
+/// the change (`None` for system changes and deleted users).
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BookingStatusHistoryEntry {
+    pub id: Uuid,
+    pub from_status: Option<BookingStatus>,
+    pub to_status: BookingStatus,
+    pub changed_by: Option<Uuid>,
+    pub changed_by_name: Option<String>,
+    pub changed_by_email: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BookingFilterParams {
     pub page: Option<i64>,
     pub per_page: Option<i64>,
     pub status: Option<BookingStatus>,
     pub property_id: Option<Uuid>,
+    pub cursor: Option<String>,
 }
 
 impl BookingFilterParams {
@@ -310,10 +508,60 @@ impl BookingFilterParams {
         PaginationParams {
             page: self.page,
             per_page: self.per_page,
+            cursor: self.cursor.clone(),
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// Blocked date DTOs
+// ---------------------------------------------------------------------------
+
+/// Body for creating or editing a blocked date range. Both dates are
+/// blocked (the range is inclusive).
+#[derive(Debug, Deserialize, Validate)]
+pub struct BlockedDateRequest {
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    #[validate(length(max = 255, message = "Reason must be at most 255 characters"))]
+    pub reason: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Amenity DTOs
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAmenityRequest {
+    #[validate(length(min = 1, max = 100, message = "Slug must be 1-100 characters"))]
+    pub slug: String,
+    #[validate(length(min = 1, max = 255, message = "Name must be 1-255 characters"))]
+    pub name: String,
+    #[validate(length(min = 1, max = 50, message = "Icon must be 1-50 characters"))]
+    pub icon: String,
+    #[validate(length(min = 1, max = 100, message = "Category must be 1-100 characters"))]
+    pub category: String,
+    pub sort_order: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateAmenityRequest {
+    #[validate(length(min = 1, max = 100, message = "Slug must be 1-100 characters"))]
+    pub slug: Option<String>,
+    #[validate(length(min = 1, max = 255, message = "Name must be 1-255 characters"))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 50, message = "Icon must be 1-50 characters"))]
+    pub icon: Option<String>,
+    #[validate(length(min = 1, max = 100, message = "Category must be 1-100 characters"))]
+    pub category: Option<String>,
+    pub sort_order: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetPropertyAmenitiesRequest {
+    pub amenity_slugs: Vec<String>,
+}
+
 // ---------------------------------------------------------------------------
 // Review DTOs
 // ---------------------------------------------------------------------------
@@ -324,6 +572,7 @@ pub struct ReviewFilterParams {
     pub per_page: Option<i64>,
     pub is_approved: Option<bool>,
     pub is_flagged: Option<bool>,
+    pub cursor: Option<String>,
 }
 
 impl ReviewFilterParams {
@@ -331,9 +580,20 @@ impl ReviewFilterParams {
         PaginationParams {
             page: self.page,
             per_page: self.per_page,
+            cursor: self.cursor.clone(),
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// Exchange rate DTOs
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct SetExchangeRateRequest {
+    /// Units of the currency per one US dollar.
+    pub rate: Decimal,
+}
+
 // Re-export slugify from shared crate
 pub use shared::utils::slugify;
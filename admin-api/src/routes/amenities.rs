@@ -0,0 +1,21 @@
+use axum::{
+    routing::{get, put},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::handlers;
+use crate::AppState;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(handlers::amenities::list_amenities).post(handlers::amenities::create_amenity),
+        )
+        .route(
+            "/{id}",
+            put(handlers::amenities::update_amenity).delete(handlers::amenities::delete_amenity),
+        )
+        .with_state(state)
+}
@@ -7,5 +7,15 @@ use crate::AppState;
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/login", post(handlers::auth::admin_login))
+        .route("/refresh", post(handlers::auth::refresh))
+        .route("/logout", post(handlers::auth::logout))
+        .route("/2fa/verify", post(handlers::auth::verify_two_factor))
+        .route("/2fa/setup", post(handlers::auth::setup_two_factor))
+        .route("/2fa/enable", post(handlers::auth::enable_two_factor))
+        .route("/2fa/disable", post(handlers::auth::disable_two_factor))
+        .route(
+            "/2fa/recovery-codes",
+            post(handlers::auth::regenerate_recovery_codes),
+        )
         .with_state(state)
 }
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, put},
+    routing::{get, post, put},
     Router,
 };
 use std::sync::Arc;
@@ -15,5 +15,14 @@ pub fn routes(state: Arc<AppState>) -> Router {
             "/{id}/status",
             put(handlers::bookings::update_booking_status),
         )
+        .route(
+            "/{id}/history",
+            get(handlers::bookings::get_booking_history),
+        )
+        .route(
+            "/{id}/payments",
+            get(handlers::bookings::get_booking_payments),
+        )
+        .route("/{id}/refunds", post(handlers::bookings::issue_refunds))
         .with_state(state)
 }
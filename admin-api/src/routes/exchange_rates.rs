@@ -0,0 +1,23 @@
+use axum::{
+    routing::{get, post, put},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::handlers;
+use crate::AppState;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(handlers::exchange_rates::list_exchange_rates))
+        .route(
+            "/import",
+            post(handlers::exchange_rates::import_exchange_rates),
+        )
+        .route(
+            "/{currency}",
+            put(handlers::exchange_rates::set_exchange_rate)
+                .delete(handlers::exchange_rates::delete_exchange_rate),
+        )
+        .with_state(state)
+}
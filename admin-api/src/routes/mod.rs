@@ -1,6 +1,8 @@
+pub mod amenities;
 pub mod auth;
 pub mod bookings;
 pub mod dashboard;
+pub mod exchange_rates;
 pub mod inquiries;
 pub mod properties;
 pub mod reviews;
@@ -16,9 +18,11 @@ pub fn admin_routes(state: Arc<AppState>) -> Router {
     Router::new()
         .nest("/auth", auth::routes(state.clone()))
         .nest("/properties", properties::routes(state.clone()))
+        .nest("/amenities", amenities::routes(state.clone()))
         .nest("/users", users::routes(state.clone()))
         .nest("/inquiries", inquiries::routes(state.clone()))
         .nest("/bookings", bookings::routes(state.clone()))
         .nest("/reviews", reviews::routes(state.clone()))
         .nest("/dashboard", dashboard::routes(state.clone()))
+        .nest("/exchange-rates", exchange_rates::routes(state.clone()))
 }
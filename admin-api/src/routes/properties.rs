@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, put},
+    routing::{get, post, put},
     Router,
 };
 use std::sync::Arc;
@@ -13,6 +13,7 @@ pub fn routes(state: Arc<AppState>) -> Router {
             "/",
             get(handlers::properties::list_properties).post(handlers::properties::create_property),
         )
+        .route("/review-queue", get(handlers::properties::review_queue))
         .route(
             "/{id}",
             get(handlers::properties::get_property)
@@ -23,5 +24,25 @@ pub fn routes(state: Arc<AppState>) -> Router {
             "/{id}/toggle-featured",
             put(handlers::properties::toggle_featured),
         )
+        .route(
+            "/{id}/approve",
+            post(handlers::properties::approve_property),
+        )
+        .route("/{id}/reject", post(handlers::properties::reject_property))
+        .route(
+            "/{id}/history",
+            get(handlers::properties::get_property_history),
+        )
+        .route("/{id}/amenities", put(handlers::amenities::set_amenities))
+        .route(
+            "/{id}/blocked-dates",
+            get(handlers::blocked_dates::list_blocked_dates)
+                .post(handlers::blocked_dates::create_blocked_dates),
+        )
+        .route(
+            "/{id}/blocked-dates/{block_id}",
+            put(handlers::blocked_dates::update_blocked_dates)
+                .delete(handlers::blocked_dates::delete_blocked_dates),
+        )
         .with_state(state)
 }
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, put},
+    routing::{delete, get, put},
     Router,
 };
 use std::sync::Arc;
@@ -18,5 +18,9 @@ pub fn routes(state: Arc<AppState>) -> Router {
             get(handlers::users::get_user).put(handlers::users::update_user),
         )
         .route("/{id}/toggle-active", put(handlers::users::toggle_active))
+        .route(
+            "/{id}/two-factor",
+            delete(handlers::users::reset_two_factor),
+        )
         .with_state(state)
 }
@@ -1,21 +1,32 @@
 use axum::extract::State;
 use axum::Json;
-use shared::auth::{create_token, hash_password, verify_password};
+use shared::auth::{
+    create_token, hash_password, verify_password, Audience, ACCESS_TOKEN_TTL_MINUTES,
+};
+use shared::client_ip::ClientIp;
 use shared::errors::AppError;
-use shared::google::verify_google_token;
+use shared::login_attempts::begin_login_attempt;
+use shared::mailer::Email;
 use shared::models::User;
+use shared::refresh_tokens::{
+    issue_tokens, revoke_refresh_token, revoke_user_sessions, rotate_refresh_token, TokenPair,
+};
+use shared::user_tokens::{consume_user_token, issue_user_token, TokenPurpose};
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::middleware::auth::RequireAuth;
 use crate::models::{
-    ApiResponse, CreateUserRequest, GoogleLoginRequest, LoginRequest, LoginResponse, UserResponse,
+    ApiResponse, CreateUserRequest, ForgotPasswordRequest, GoogleLoginRequest, LoginRequest,
+    LoginResponse, LogoutRequest, RefreshRequest, ResetPasswordRequest, UserResponse,
+    VerifyEmailRequest,
 };
 use crate::AppState;
 
 /// POST /api/v1/auth/register
 ///
-/// Create a new user account and return a JWT.
+/// Create a new user account and return an access and refresh token.
 pub async fn register(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<CreateUserRequest>,
@@ -55,11 +66,26 @@ pub async fn register(
     .fetch_one(&state.pool)
     .await?;
 
+    // The account is usable straight away; a mail outage should not block
+    // sign-up, and the user can ask for another email later.
+    if let Err(e) = send_verification_email(&state, &user).await {
+        tracing::warn!(user_id = %user.id, "Failed to send verification email: {e}");
+    }
+
     // Create JWT
-    let token = create_token(user.id, &user.email, &user.role, &state.jwt_secret)?;
+    let tokens = issue_tokens(
+        &state.pool,
+        user.id,
+        &user.email,
+        &user.role,
+        user.token_version,
+        Audience::Api,
+        &state.jwt_secret,
+    )
+    .await?;
 
     let response = LoginResponse {
-        token,
+        tokens,
         user: UserResponse::from(user),
     };
 
@@ -68,11 +94,15 @@ pub async fn register(
 
 /// POST /api/v1/auth/login
 ///
-/// Authenticate with email and password, return a JWT.
+/// Authenticate with email and password, return an access and refresh token.
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<ApiResponse<LoginResponse>>, AppError> {
+    // Refuse locked-out emails and addresses before checking the password
+    let attempt = begin_login_attempt(&state.pool, &payload.email, ip).await?;
+
     // Find user by email
     let user: User = sqlx::query_as("SELECT * FROM users WHERE email = $1 AND is_active = true")
         .bind(&payload.email)
@@ -94,12 +124,22 @@ pub async fn login(
             "Invalid email or password".to_string(),
         ));
     }
+    attempt.succeeded(&state.pool).await?;
 
     // Create JWT
-    let token = create_token(user.id, &user.email, &user.role, &state.jwt_secret)?;
+    let tokens = issue_tokens(
+        &state.pool,
+        user.id,
+        &user.email,
+        &user.role,
+        user.token_version,
+        Audience::Api,
+        &state.jwt_secret,
+    )
+    .await?;
 
     let response = LoginResponse {
-        token,
+        tokens,
         user: UserResponse::from(user),
     };
 
@@ -115,7 +155,7 @@ pub async fn google_login(
     Json(payload): Json<GoogleLoginRequest>,
 ) -> Result<Json<ApiResponse<LoginResponse>>, AppError> {
     // Verify the Google ID token
-    let google_user = verify_google_token(&payload.credential, &state.google_client_id).await?;
+    let google_user = state.google.verify(&payload.credential).await?;
 
     // Try to find existing user by google_id first
     let user: Option<User> = sqlx::query_as("SELECT * FROM users WHERE google_id = $1")
@@ -177,12 +217,228 @@ pub async fn google_login(
         .await?;
 
     // Create app JWT
-    let token = create_token(user.id, &user.email, &user.role, &state.jwt_secret)?;
+    let tokens = issue_tokens(
+        &state.pool,
+        user.id,
+        &user.email,
+        &user.role,
+        user.token_version,
+        Audience::Api,
+        &state.jwt_secret,
+    )
+    .await?;
 
     let response = LoginResponse {
-        token,
+        tokens,
         user: UserResponse::from(user),
     };
 
     Ok(Json(ApiResponse::success(response)))
 }
+
+/// POST /api/v1/auth/refresh
+///
+/// Exchange a refresh token for a new access token. The refresh token is
+/// rotated: the response carries its replacement and the old one stops working.
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<ApiResponse<TokenPair>>, AppError> {
+    let (user_id, refresh_token) =
+        rotate_refresh_token(&state.pool, &payload.refresh_token, Audience::Api).await?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = $1 AND is_active = true")
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Account is deactivated".to_string()))?;
+
+    let token = create_token(
+        user.id,
+        &user.email,
+        &user.role,
+        user.token_version,
+        Audience::Api,
+        &state.jwt_secret,
+    )?;
+
+    Ok(Json(ApiResponse::success(TokenPair {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+    })))
+}
+
+/// POST /api/v1/auth/logout
+///
+/// Revoke the session the refresh token belongs to. With `all_devices`, every
+/// session of the account is ended and its outstanding access tokens are
+/// rejected from then on. Unknown tokens are accepted, so logging out twice
+/// is harmless.
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LogoutRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let user_id = revoke_refresh_token(&state.pool, &payload.refresh_token).await?;
+
+    if let (Some(user_id), true) = (user_id, payload.all_devices) {
+        let mut conn = state.pool.acquire().await?;
+        revoke_user_sessions(&mut conn, user_id).await?;
+    }
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Logged out"
+    }))))
+}
+
+/// POST /api/v1/auth/verify-email
+///
+/// Confirm the account's email address with the token from the verification
+/// email.
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let user_id =
+        consume_user_token(&state.pool, &payload.token, TokenPurpose::EmailVerification).await?;
+
+    sqlx::query("UPDATE users SET email_verified = true, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&state.pool)
+        .await?;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Email verified"
+    }))))
+}
+
+/// POST /api/v1/auth/resend-verification
+///
+/// Send a new verification email to the authenticated user. Links from
+/// earlier emails stop working.
+pub async fn resend_verification(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    if user.email_verified {
+        return Err(AppError::Conflict("Email is already verified".to_string()));
+    }
+
+    send_verification_email(&state, &user).await?;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Verification email sent"
+    }))))
+}
+
+/// POST /api/v1/auth/forgot-password
+///
+/// Email a password reset link. The response is the same whether or not the
+/// address belongs to an account, so it cannot be used to discover users.
+pub async fn forgot_password(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let user: Option<User> =
+        sqlx::query_as("SELECT * FROM users WHERE email = $1 AND is_active = true")
+            .bind(&payload.email)
+            .fetch_optional(&state.pool)
+            .await?;
+
+    if let Some(user) = user {
+        let token = issue_user_token(&state.pool, user.id, TokenPurpose::PasswordReset).await?;
+        let email = Email {
+            to: user.email.clone(),
+            subject: "Reset your MyBaliVilla password".to_string(),
+            body: format!(
+                "Hi {},\n\n\
+                 Someone asked to reset the password for your MyBaliVilla account. \
+                 To choose a new password, open this link within the next hour:\n\n\
+                 {}/reset-password?token={token}\n\n\
+                 If this wasn't you, you can ignore this email; your password has not changed.\n",
+                user.full_name, state.app_url
+            ),
+            html: None,
+        };
+        if let Err(e) = state.mailer.send(&email).await {
+            tracing::warn!(user_id = %user.id, "Failed to send password reset email: {e}");
+        }
+    }
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "If an account exists for this email, a reset link has been sent"
+    }))))
+}
+
+/// POST /api/v1/auth/reset-password
+///
+/// Set a new password with the token from the reset email. Every existing
+/// session of the account is signed out, and the email address counts as
+/// verified since the user has just proven access to it.
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let user_id =
+        consume_user_token(&state.pool, &payload.token, TokenPurpose::PasswordReset).await?;
+    let password_hash = hash_password(&payload.password)?;
+
+    let mut tx = state.pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $2, email_verified = true, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&password_hash)
+    .execute(&mut *tx)
+    .await?;
+
+    revoke_user_sessions(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Password has been reset. Please log in with your new password."
+    }))))
+}
+
+async fn send_verification_email(state: &AppState, user: &User) -> Result<(), AppError> {
+    let token = issue_user_token(&state.pool, user.id, TokenPurpose::EmailVerification).await?;
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Confirm your MyBaliVilla email address".to_string(),
+        body: format!(
+            "Hi {},\n\n\
+             Welcome to MyBaliVilla! Please confirm your email address by opening this link:\n\n\
+             {}/verify-email?token={token}\n\n\
+             You need a confirmed address to book stays or list a property. \
+             The link is valid for 48 hours.\n",
+            user.full_name, state.app_url
+        ),
+        html: None,
+    };
+
+    state.mailer.send(&email).await
+}
@@ -1,10 +1,17 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
+use rust_decimal::Decimal;
+use shared::currency::DisplayCurrency;
 use shared::errors::AppError;
+use shared::models::{PricePeriod, PricingTier, PropertyRules, RentalDurationType};
+use shared::pricing::{quote_stay, ListingPrice, PriceQuote};
+use sqlx::PgPool;
 use std::sync::Arc;
+use uuid::Uuid;
 
 use crate::models::{
     ApiResponse, AvailabilityQuery, BlockedDateRange, PricingTierResponse, PropertyRulesResponse,
+    QuoteQuery, QuoteResponse,
 };
 use crate::AppState;
 
@@ -15,12 +22,11 @@ pub async fn get_availability(
     Query(query): Query<AvailabilityQuery>,
 ) -> Result<Json<ApiResponse<Vec<BlockedDateRange>>>, AppError> {
     // Get property ID from slug
-    let property: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT id FROM properties WHERE slug = $1 AND is_active = true",
-    )
-    .bind(&slug)
-    .fetch_optional(&state.pool)
-    .await?;
+    let property: Option<(uuid::Uuid,)> =
+        sqlx::query_as("SELECT id FROM properties WHERE slug = $1 AND is_active = true")
+            .bind(&slug)
+            .fetch_optional(&state.pool)
+            .await?;
 
     let (property_id,) =
         property.ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
@@ -89,3 +95,93 @@ pub async fn get_property_pricing(
 
     Ok(Json(ApiResponse::success(tiers)))
 }
+
+/// Load the house rules for a property, if the owner has set any.
+pub(crate) async fn load_property_rules(
+    pool: &PgPool,
+    property_id: Uuid,
+) -> Result<Option<PropertyRules>, AppError> {
+    let rules = sqlx::query_as("SELECT * FROM property_rules WHERE property_id = $1")
+        .bind(property_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(rules)
+}
+
+/// Price a stay at an active property using its pricing tiers.
+///
+/// Shared by the quote endpoint and booking creation so that the price shown
+/// to the guest is exactly the price charged.
+pub(crate) async fn quote_for_property(
+    pool: &PgPool,
+    property_id: Uuid,
+    check_in: chrono::NaiveDate,
+    check_out: chrono::NaiveDate,
+    duration_type: Option<&RentalDurationType>,
+) -> Result<PriceQuote, AppError> {
+    let (price, price_period, currency): (Decimal, Option<PricePeriod>, String) = sqlx::query_as(
+        "SELECT price, price_period, currency FROM properties WHERE id = $1 AND is_active = true",
+    )
+    .bind(property_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    let tiers: Vec<PricingTier> =
+        sqlx::query_as("SELECT * FROM pricing_tiers WHERE property_id = $1 AND is_active = true")
+            .bind(property_id)
+            .fetch_all(pool)
+            .await?;
+
+    let listing = ListingPrice {
+        price,
+        price_period: price_period.as_ref(),
+        currency: &currency,
+    };
+
+    quote_stay(&tiers, listing, check_in, check_out, duration_type)
+}
+
+/// GET /api/v1/properties/:slug/quote
+pub async fn get_quote(
+    State(state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    Query(query): Query<QuoteQuery>,
+) -> Result<Json<ApiResponse<QuoteResponse>>, AppError> {
+    if query.guests.is_some_and(|g| g < 1) {
+        return Err(AppError::BadRequest(
+            "At least 1 guest required".to_string(),
+        ));
+    }
+    let display =
+        DisplayCurrency::from_param(&state.pool, query.display_currency.as_deref()).await?;
+
+    let (property_id,): (Uuid,) =
+        sqlx::query_as("SELECT id FROM properties WHERE slug = $1 AND is_active = true")
+            .bind(&slug)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    if let (Some(guests), Some(rules)) = (
+        query.guests,
+        load_property_rules(&state.pool, property_id).await?,
+    ) {
+        rules.check_booking(guests, false, None)?;
+    }
+
+    let quote = quote_for_property(
+        &state.pool,
+        property_id,
+        query.check_in,
+        query.check_out,
+        query.duration_type.as_ref(),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(QuoteResponse::new(
+        quote,
+        display.as_ref(),
+    ))))
+}
@@ -0,0 +1,147 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use shared::blocked_dates::save_blocked_range;
+use shared::errors::AppError;
+use shared::models::BlockedDate;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::middleware::auth::RequireAuth;
+use crate::models::{ApiResponse, BlockedDateRequest};
+use crate::AppState;
+
+/// Make sure `owner_id` owns the property. Other users get a 404 so they
+/// cannot probe for property IDs.
+pub(crate) async fn ensure_owner(
+    pool: &PgPool,
+    property_id: Uuid,
+    owner_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query("SELECT id FROM properties WHERE id = $1 AND owner_id = $2")
+        .bind(property_id)
+        .bind(owner_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    Ok(())
+}
+
+/// GET /api/v1/properties/:id/blocked-dates
+pub async fn list_blocked_dates(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<BlockedDate>>>, AppError> {
+    let owner_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    ensure_owner(&state.pool, property_id, owner_id).await?;
+
+    let blocks: Vec<BlockedDate> =
+        sqlx::query_as("SELECT * FROM blocked_dates WHERE property_id = $1 ORDER BY start_date")
+            .bind(property_id)
+            .fetch_all(&state.pool)
+            .await?;
+
+    Ok(Json(ApiResponse::success(blocks)))
+}
+
+/// POST /api/v1/properties/:id/blocked-dates
+///
+/// Block a date range. Overlapping or adjacent blocks are merged into one,
+/// which is returned.
+pub async fn create_blocked_dates(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    Json(payload): Json<BlockedDateRequest>,
+) -> Result<Json<ApiResponse<BlockedDate>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let owner_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    ensure_owner(&state.pool, property_id, owner_id).await?;
+
+    let block = save_blocked_range(
+        &state.pool,
+        property_id,
+        payload.start_date,
+        payload.end_date,
+        payload.reason.as_deref(),
+        None,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(block)))
+}
+
+/// PUT /api/v1/properties/:id/blocked-dates/:block_id
+pub async fn update_blocked_dates(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path((property_id, block_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<BlockedDateRequest>,
+) -> Result<Json<ApiResponse<BlockedDate>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let owner_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    ensure_owner(&state.pool, property_id, owner_id).await?;
+
+    let block = save_blocked_range(
+        &state.pool,
+        property_id,
+        payload.start_date,
+        payload.end_date,
+        payload.reason.as_deref(),
+        Some(block_id),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(block)))
+}
+
+/// DELETE /api/v1/properties/:id/blocked-dates/:block_id
+pub async fn delete_blocked_dates(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path((property_id, block_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let owner_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    ensure_owner(&state.pool, property_id, owner_id).await?;
+
+    let result = sqlx::query("DELETE FROM blocked_dates WHERE id = $1 AND property_id = $2")
+        .bind(block_id)
+        .bind(property_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(
+            "Blocked date range not found".to_string(),
+        ));
+    }
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "message": "Blocked dates removed"
+    }))))
+}
@@ -1,12 +1,24 @@
 use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
 use axum::Json;
+use chrono::{DateTime, Utc};
+use shared::auth::ensure_email_verified;
+use shared::cancellation::{refund_for_booking, RefundQuote};
+use shared::currency::DisplayCurrency;
 use shared::errors::AppError;
+use shared::models::{Booking, BookingStatus};
+use shared::notifications;
+use shared::pagination::{next_cursor, PaginationParams};
+use shared::payments;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::handlers::availability::{load_property_rules, quote_for_property};
 use crate::middleware::auth::RequireAuth;
-use crate::models::{ApiResponse, BookingFilters, BookingResponse, CreateBookingRequest};
+use crate::models::{
+    ApiResponse, BookingFilters, BookingResponse, CreateBookingRequest, DisplayCurrencyQuery,
+};
 use crate::AppState;
 
 /// POST /api/v1/bookings
@@ -24,18 +36,32 @@ pub async fn create_booking(
         .parse()
         .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
 
-    // Verify property exists and is active
-    let property: Option<(Uuid, rust_decimal::Decimal, String)> = sqlx::query_as(
-        "SELECT id, price, currency FROM properties WHERE id = $1 AND is_active = true",
+    ensure_email_verified(&state.pool, guest_id).await?;
+
+    // Price the stay with the shared pricing engine (also verifies the
+    // property exists and is active).
+    let quote = quote_for_property(
+        &state.pool,
+        payload.property_id,
+        payload.check_in,
+        payload.check_out,
+        payload.duration_type.as_ref(),
     )
-    .bind(payload.property_id)
-    .fetch_optional(&state.pool)
     .await?;
 
-    let (_, base_price, currency) =
-        property.ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+    // Enforce the owner's house rules
+    if let Some(rules) = load_property_rules(&state.pool, payload.property_id).await? {
+        rules.check_booking(
+            payload.num_guests,
+            payload.has_pets,
+            payload.expected_arrival_time,
+        )?;
+    }
 
-    // Check for overlapping bookings
+    // Check for overlapping bookings. This is only a fast path: the
+    // bookings_no_overlap constraint is what actually prevents two concurrent
+    // requests from both inserting, and the insert below maps it to the same
+    // error.
     let overlap: Option<(Uuid,)> = sqlx::query_as(
         r#"SELECT id FROM bookings
            WHERE property_id = $1
@@ -49,16 +75,16 @@ pub async fn create_booking(
     .await?;
 
     if overlap.is_some() {
-        return Err(AppError::BadRequest(
+        return Err(AppError::Conflict(
             "Property is not available for these dates".to_string(),
         ));
     }
 
-    // Check blocked dates
+    // Check blocked dates (both ends of a blocked range are blocked)
     let blocked: Option<(Uuid,)> = sqlx::query_as(
         r#"SELECT id FROM blocked_dates
            WHERE property_id = $1
-           AND start_date < $3 AND end_date > $2"#,
+           AND start_date < $3 AND end_date >= $2"#,
     )
     .bind(payload.property_id)
     .bind(payload.check_in)
@@ -72,87 +98,109 @@ pub async fn create_booking(
         ));
     }
 
-    let days = (payload.check_out - payload.check_in).num_days();
-    let duration_count = match payload.duration_type {
-        shared::models::RentalDurationType::Nightly => days as i32,
-        shared::models::RentalDurationType::Weekly => (days / 7).max(1) as i32,
-        shared::models::RentalDurationType::Monthly => (days / 30).max(1) as i32,
-        shared::models::RentalDurationType::Yearly => (days / 365).max(1) as i32,
-    };
-
-    let total_price = base_price * rust_decimal::Decimal::from(duration_count);
+    let mut tx = state.pool.begin().await?;
 
     let id = Uuid::new_v4();
     let booking: BookingResponse = sqlx::query_as(
         r#"INSERT INTO bookings (
-            id, property_id, guest_id, check_in, check_out, num_guests,
-            special_requests, base_price, total_price, currency,
-            duration_type, duration_count, status
+            id, property_id, guest_id, pricing_tier_id, check_in, check_out, num_guests,
+            special_requests, base_price, cleaning_fee, service_fee, total_price, currency,
+            duration_type, duration_count, has_pets, expected_arrival_time, status
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, 'pending')
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, 'pending')
         RETURNING *"#,
     )
     .bind(id)
     .bind(payload.property_id)
     .bind(guest_id)
+    .bind(quote.pricing_tier_id)
     .bind(payload.check_in)
     .bind(payload.check_out)
     .bind(payload.num_guests)
     .bind(&payload.special_requests)
-    .bind(base_price)
-    .bind(total_price)
-    .bind(&currency)
-    .bind(&payload.duration_type)
-    .bind(duration_count)
-    .fetch_one(&state.pool)
-    .await?;
+    .bind(quote.base_price)
+    .bind(quote.cleaning_fee)
+    .bind(quote.service_fee)
+    .bind(quote.total_price)
+    .bind(&quote.currency)
+    .bind(&quote.duration_type)
+    .bind(quote.duration_count)
+    .bind(payload.has_pets)
+    .bind(payload.expected_arrival_time)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::on_constraint(
+        "bookings_no_overlap",
+        AppError::Conflict("Property is not available for these dates".to_string()),
+    ))?;
+
+    record_status_change(&mut *tx, &booking, None, guest_id).await?;
+    notifications::booking_created(&mut tx, booking.id).await?;
+
+    tx.commit().await?;
 
     Ok(Json(ApiResponse::success(booking)))
 }
 
 /// GET /api/v1/bookings
+///
+/// The body stays a plain array for compatibility; the cursor for the next
+/// page, if there is one, is returned in the `X-Next-Cursor` header.
 pub async fn list_my_bookings(
     State(state): State<Arc<AppState>>,
     RequireAuth(claims): RequireAuth,
     Query(filters): Query<BookingFilters>,
-) -> Result<Json<ApiResponse<Vec<BookingResponse>>>, AppError> {
+) -> Result<(HeaderMap, Json<ApiResponse<Vec<BookingResponse>>>), AppError> {
     let guest_id: Uuid = claims
         .sub
         .parse()
         .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
 
-    let page = filters.page.unwrap_or(1).max(1);
-    let per_page = filters.per_page.unwrap_or(20).clamp(1, 100);
-    let offset = (page - 1) * per_page;
-
-    let bookings: Vec<BookingResponse> = if let Some(ref status) = filters.status {
-        sqlx::query_as(
-            r#"SELECT * FROM bookings
-               WHERE guest_id = $1 AND status::text = $2
-               ORDER BY created_at DESC
-               OFFSET $3 LIMIT $4"#,
-        )
-        .bind(guest_id)
-        .bind(status)
-        .bind(offset)
-        .bind(per_page)
-        .fetch_all(&state.pool)
-        .await?
-    } else {
-        sqlx::query_as(
-            r#"SELECT * FROM bookings
-               WHERE guest_id = $1
-               ORDER BY created_at DESC
-               OFFSET $2 LIMIT $3"#,
-        )
-        .bind(guest_id)
-        .bind(offset)
-        .bind(per_page)
-        .fetch_all(&state.pool)
-        .await?
+    let pagination = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        cursor: filters.cursor.clone(),
     };
+    let per_page = pagination.limit();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
+    let display =
+        DisplayCurrency::from_param(&state.pool, filters.display_currency.as_deref()).await?;
+
+    let mut bookings: Vec<BookingResponse> = sqlx::query_as(
+        r#"SELECT * FROM bookings
+           WHERE guest_id = $1
+           AND ($2::text IS NULL OR status::text = $2)
+           AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4))
+           ORDER BY created_at DESC, id DESC
+           OFFSET $5 LIMIT $6"#,
+    )
+    .bind(guest_id)
+    .bind(&filters.status)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(pagination.offset())
+    .bind(per_page + 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    if let Some(ref display) = display {
+        for booking in bookings.iter_mut() {
+            booking.show_prices_in(display);
+        }
+    }
 
-    Ok(Json(ApiResponse::success(bookings)))
+    let mut headers = HeaderMap::new();
+    if let Some(cursor) = next_cursor(&mut bookings, per_page, "created_at", |b| {
+        (b.created_at.to_rfc3339(), b.id)
+    }) {
+        headers.insert(
+            HeaderName::from_static("x-next-cursor"),
+            HeaderValue::from_str(&cursor)
+                .map_err(|_| AppError::Internal("Invalid cursor header".to_string()))?,
+        );
+    }
+
+    Ok((headers, Json(ApiResponse::success(bookings))))
 }
 
 /// GET /api/v1/bookings/:id
@@ -160,13 +208,16 @@ pub async fn get_booking(
     State(state): State<Arc<AppState>>,
     RequireAuth(claims): RequireAuth,
     Path(booking_id): Path<Uuid>,
+    Query(query): Query<DisplayCurrencyQuery>,
 ) -> Result<Json<ApiResponse<BookingResponse>>, AppError> {
     let guest_id: Uuid = claims
         .sub
         .parse()
         .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+    let display =
+        DisplayCurrency::from_param(&state.pool, query.display_currency.as_deref()).await?;
 
-    let booking: BookingResponse =
+    let mut booking: BookingResponse =
         sqlx::query_as("SELECT * FROM bookings WHERE id = $1 AND guest_id = $2")
             .bind(booking_id)
             .bind(guest_id)
@@ -174,10 +225,93 @@ pub async fn get_booking(
             .await?
             .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
 
+    if let Some(ref display) = display {
+        booking.show_prices_in(display);
+    }
+
     Ok(Json(ApiResponse::success(booking)))
 }
 
+/// Append a row to `booking_status_history` for a booking that has just
+/// moved from `from` (`None` when it was just created) to its current status.
+async fn record_status_change<'e, E>(
+    executor: E,
+    booking: &BookingResponse,
+    from: Option<&BookingStatus>,
+    changed_by: Uuid,
+) -> Result<(), AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"INSERT INTO booking_status_history (booking_id, from_status, to_status, changed_by)
+           VALUES ($1, $2, $3, $4)"#,
+    )
+    .bind(booking.id)
+    .bind(from)
+    .bind(&booking.status)
+    .bind(changed_by)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Load one of the guest's bookings that is still open for cancellation.
+async fn load_cancellable_booking<'e, E>(
+    executor: E,
+    booking_id: Uuid,
+    guest_id: Uuid,
+    for_update: bool,
+) -> Result<Booking, AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let sql = if for_update {
+        "SELECT * FROM bookings WHERE id = $1 AND guest_id = $2 FOR UPDATE"
+    } else {
+        "SELECT * FROM bookings WHERE id = $1 AND guest_id = $2"
+    };
+
+    let booking: Booking = sqlx::query_as(sql)
+        .bind(booking_id)
+        .bind(guest_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
+
+    booking.status.check_transition(&BookingStatus::Cancelled)?;
+
+    Ok(booking)
+}
+
+/// GET /api/v1/bookings/:id/cancellation-preview
+///
+/// Preview the refund the guest would receive if they cancelled now.
+pub async fn preview_cancellation(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(booking_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<RefundQuote>>, AppError> {
+    let guest_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    let booking = load_cancellable_booking(&state.pool, booking_id, guest_id, false).await?;
+    let rules = load_property_rules(&state.pool, booking.property_id).await?;
+
+    let quote = refund_for_booking(&booking, rules.as_ref(), Utc::now());
+
+    Ok(Json(ApiResponse::success(quote)))
+}
+
 /// PUT /api/v1/bookings/:id/cancel
+///
+/// Cancel a booking and record the refund due under the property's
+/// cancellation policy, calculated exactly as the preview endpoint does.
+/// Whatever of that the guest has paid is refunded through the payment
+/// provider.
 pub async fn cancel_booking(
     State(state): State<Arc<AppState>>,
     RequireAuth(claims): RequireAuth,
@@ -188,17 +322,157 @@ pub async fn cancel_booking(
         .parse()
         .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
 
+    let mut tx = state.pool.begin().await?;
+
+    let booking = load_cancellable_booking(&mut *tx, booking_id, guest_id, true).await?;
+    let rules = load_property_rules(&state.pool, booking.property_id).await?;
+    let previous_status = booking.status.clone();
+
+    let cancelled_at = Utc::now();
+    let quote = refund_for_booking(&booking, rules.as_ref(), cancelled_at);
+
     let booking: BookingResponse = sqlx::query_as(
         r#"UPDATE bookings
-           SET status = 'cancelled', cancelled_at = NOW()
-           WHERE id = $1 AND guest_id = $2 AND status IN ('pending', 'confirmed')
+           SET status = 'cancelled', cancelled_at = $2, refund_amount = $3
+           WHERE id = $1
            RETURNING *"#,
     )
     .bind(booking_id)
-    .bind(guest_id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| AppError::BadRequest("Cannot cancel this booking".to_string()))?;
+    .bind(cancelled_at)
+    .bind(quote.refund_amount)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_status_change(&mut *tx, &booking, Some(&previous_status), guest_id).await?;
+    notifications::booking_status_changed(&mut tx, booking_id, &BookingStatus::Cancelled).await?;
+
+    tx.commit().await?;
+
+    // Return what the guest has paid beyond what they forfeit. The booking is
+    // cancelled either way; a failed refund stays on record to be retried
+    // from the admin portal.
+    if let Err(e) = payments::issue_refunds(&state.pool, state.payments.as_ref(), booking_id).await
+    {
+        tracing::error!("Refund for cancelled booking {booking_id} failed: {e}");
+    }
+    let booking: BookingResponse = sqlx::query_as("SELECT * FROM bookings WHERE id = $1")
+        .bind(booking_id)
+        .fetch_one(&state.pool)
+        .await?;
 
     Ok(Json(ApiResponse::success(booking)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::auth::Claims;
+    use shared::google::{GoogleVerifier, JwkSet, StaticKeySource};
+    use shared::payment_provider::FakePaymentProvider;
+    use tokio::task::JoinSet;
+
+    /// Fire several identical booking requests at once and check that the
+    /// database lets exactly one of them through.
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_concurrent_bookings_cannot_overlap() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = shared::db::create_pool(&database_url).await.unwrap();
+        let (chat_events, _) = tokio::sync::broadcast::channel(1);
+        let state = Arc::new(AppState {
+            pool: pool.clone(),
+            jwt_secret: "test-secret".to_string(),
+            google: Arc::new(GoogleVerifier::new(
+                "",
+                Arc::new(StaticKeySource::new(JwkSet { keys: vec![] })),
+            )),
+            chat_events,
+            mailer: Arc::new(shared::mailer::MemoryMailer::new()),
+            app_url: "http://localhost:3000".to_string(),
+            payments: Arc::new(FakePaymentProvider::new("whsec_test")),
+        });
+
+        let owner_id = Uuid::new_v4();
+        let guest_id = Uuid::new_v4();
+        let property_id = Uuid::new_v4();
+        for id in [owner_id, guest_id] {
+            sqlx::query(
+                "INSERT INTO users (id, email, full_name, role, email_verified) VALUES ($1, $2, 'Test', 'user', true)",
+            )
+            .bind(id)
+            .bind(format!("{id}@example.com"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            r#"INSERT INTO properties (id, owner_id, title, slug, property_type, listing_type,
+                                       price, price_period, area, listing_status)
+               VALUES ($1, $2, 'Race Villa', $3, 'villa', 'short_term_rent',
+                       100, 'per_night', 'Canggu', 'approved')"#,
+        )
+        .bind(property_id)
+        .bind(owner_id)
+        .bind(format!("race-villa-{property_id}"))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let check_in = chrono::NaiveDate::from_ymd_opt(2030, 3, 10).unwrap();
+        let mut attempts = JoinSet::new();
+        for i in 0..8 {
+            let state = state.clone();
+            let claims = Claims {
+                sub: guest_id.to_string(),
+                email: format!("{guest_id}@example.com"),
+                role: "user".to_string(),
+                exp: usize::MAX,
+                ver: 0,
+                aud: "api".to_string(),
+            };
+            let payload = CreateBookingRequest {
+                property_id,
+                check_in: check_in + chrono::Duration::days(i % 2),
+                check_out: check_in + chrono::Duration::days(5),
+                num_guests: 1,
+                special_requests: None,
+                duration_type: None,
+                has_pets: false,
+                expected_arrival_time: None,
+            };
+            attempts.spawn(create_booking(
+                State(state),
+                RequireAuth(claims),
+                Json(payload),
+            ));
+        }
+
+        let mut succeeded = 0;
+        while let Some(result) = attempts.join_next().await {
+            match result.unwrap() {
+                Ok(_) => succeeded += 1,
+                Err(AppError::Conflict(msg)) => {
+                    assert_eq!(msg, "Property is not available for these dates")
+                }
+                Err(other) => panic!("unexpected error: {other}"),
+            }
+        }
+        assert_eq!(succeeded, 1);
+
+        let active: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM bookings WHERE property_id = $1 AND status = $2",
+        )
+        .bind(property_id)
+        .bind(BookingStatus::Pending)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(active, 1);
+
+        sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(vec![owner_id, guest_id])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
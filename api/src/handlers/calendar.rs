@@ -0,0 +1,193 @@
+use axum::extract::{Multipart, Path, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::{NaiveDate, Utc};
+use shared::blocked_dates::{replace_imported_blocks, ImportSummary};
+use shared::calendar::{
+    fetch_calendar, parse_calendar, render_calendar, CalendarEvent, MAX_FEED_BYTES,
+};
+use shared::errors::AppError;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::handlers::blocked_dates::ensure_owner;
+use crate::middleware::auth::RequireAuth;
+use crate::models::{ApiResponse, CalendarImportRequest};
+use crate::AppState;
+
+/// GET /api/v1/properties/:slug/calendar.ics
+///
+/// Public iCalendar feed of the nights a property is unavailable: confirmed
+/// and current stays plus blocked dates. Guest details are never included.
+pub async fn export_calendar(
+    State(state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let (property_id, title): (Uuid, String) =
+        sqlx::query_as("SELECT id, title FROM properties WHERE slug = $1 AND is_active = true")
+            .bind(&slug)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    let bookings: Vec<(Uuid, NaiveDate, NaiveDate)> = sqlx::query_as(
+        r#"SELECT id, check_in, check_out FROM bookings
+           WHERE property_id = $1
+           AND status IN ('confirmed', 'checked_in', 'checked_out')
+           ORDER BY check_in"#,
+    )
+    .bind(property_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let blocked: Vec<(Uuid, NaiveDate, NaiveDate)> = sqlx::query_as(
+        "SELECT id, start_date, end_date FROM blocked_dates WHERE property_id = $1 ORDER BY start_date",
+    )
+    .bind(property_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    // Bookings occupy the nights up to, but not including, check-out.
+    let booked_events = bookings
+        .into_iter()
+        .filter(|(_, check_in, check_out)| check_out > check_in)
+        .map(|(id, check_in, check_out)| CalendarEvent {
+            uid: format!("booking-{id}@mybalivilla.com"),
+            start_date: check_in,
+            end_date: check_out.pred_opt().unwrap_or(check_in),
+            summary: Some("Booked".to_string()),
+        });
+    let blocked_events = blocked
+        .into_iter()
+        .map(|(id, start_date, end_date)| CalendarEvent {
+            uid: format!("blocked-{id}@mybalivilla.com"),
+            start_date,
+            end_date,
+            summary: Some("Not available".to_string()),
+        });
+    let events: Vec<CalendarEvent> = booked_events.chain(blocked_events).collect();
+
+    let body = render_calendar(&title, &events, Utc::now());
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/calendar; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{slug}.ics\""),
+            ),
+        ],
+        body,
+    ))
+}
+
+/// POST /api/v1/properties/:id/calendar/import
+///
+/// Import an external calendar feed by URL. Blocks from a previous import of
+/// the same source are replaced, so the feed can be re-imported at any time.
+/// The URL must point at a public internet address; see
+/// [`fetch_calendar`].
+pub async fn import_calendar_url(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    Json(payload): Json<CalendarImportRequest>,
+) -> Result<Json<ApiResponse<ImportSummary>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let owner_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    // The URL identifies the feed unless the owner names it.
+    let source = payload.source.as_deref().unwrap_or(&payload.url);
+    if source.chars().count() > 255 {
+        return Err(AppError::BadRequest(
+            "Calendar URL is too long to identify the feed; provide a source name".to_string(),
+        ));
+    }
+
+    ensure_owner(&state.pool, property_id, owner_id).await?;
+
+    let feed = fetch_calendar(&payload.url).await?;
+    let events = parse_calendar(&feed)?;
+
+    let summary = replace_imported_blocks(&state.pool, property_id, source, &events).await?;
+
+    Ok(Json(ApiResponse::success(summary)))
+}
+
+/// POST /api/v1/properties/:id/calendar/import/upload
+///
+/// Import an uploaded `.ics` file (multipart field `file`). The optional
+/// `source` field names the feed; it defaults to the file name, so uploading
+/// a newer export of the same calendar replaces the earlier one.
+pub async fn import_calendar_file(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<ApiResponse<ImportSummary>>, AppError> {
+    let owner_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    ensure_owner(&state.pool, property_id, owner_id).await?;
+
+    let mut source: Option<String> = None;
+    let mut file: Option<(Option<String>, Vec<u8>)> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read multipart field: {e}")))?
+    {
+        match field.name() {
+            Some("source") => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read source: {e}")))?;
+                source = Some(value.trim().to_string()).filter(|s| !s.is_empty());
+            }
+            Some("file") => {
+                let filename = field.file_name().map(str::to_string);
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read file data: {e}")))?;
+                if data.len() > MAX_FEED_BYTES {
+                    return Err(AppError::BadRequest(
+                        "Calendar file is too large".to_string(),
+                    ));
+                }
+                file = Some((filename, data.to_vec()));
+            }
+            _ => continue,
+        }
+    }
+
+    let (filename, data) =
+        file.ok_or_else(|| AppError::BadRequest("No file provided".to_string()))?;
+
+    let source = source.or(filename).unwrap_or_else(|| "upload".to_string());
+    if source.chars().count() > 255 {
+        return Err(AppError::BadRequest(
+            "Source must be at most 255 characters".to_string(),
+        ));
+    }
+
+    let events = parse_calendar(&data)?;
+    let summary = replace_imported_blocks(&state.pool, property_id, &source, &events).await?;
+
+    Ok(Json(ApiResponse::success(summary)))
+}
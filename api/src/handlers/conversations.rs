@@ -0,0 +1,504 @@
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::Json;
+use shared::errors::AppError;
+use shared::models::{Conversation, Message};
+use sqlx::PgPool;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::middleware::auth::RequireAuth;
+use crate::models::{
+    ApiResponse, ChatEvent, ConversationSummary, CreateConversationRequest, MessageFilters,
+    SendMessageRequest,
+};
+use crate::AppState;
+
+/// Load a conversation, returning 404 unless `user_id` is one of its two
+/// participants (so non-participants cannot even learn that it exists).
+async fn load_conversation(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    user_id: Uuid,
+) -> Result<Conversation, AppError> {
+    sqlx::query_as::<_, Conversation>("SELECT * FROM conversations WHERE id = $1")
+        .bind(conversation_id)
+        .fetch_optional(pool)
+        .await?
+        .filter(|c| c.has_participant(user_id))
+        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))
+}
+
+/// Insert a message, bump the conversation's `last_message_at` and notify
+/// open streams.
+async fn insert_message(
+    state: &AppState,
+    conversation_id: Uuid,
+    sender_id: Uuid,
+    content: &str,
+) -> Result<Message, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    let message: Message = sqlx::query_as(
+        r#"INSERT INTO messages (id, conversation_id, sender_id, content, is_read, created_at)
+           VALUES ($1, $2, $3, $4, false, NOW())
+           RETURNING *"#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(conversation_id)
+    .bind(sender_id)
+    .bind(content)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE conversations SET last_message_at = $2 WHERE id = $1")
+        .bind(conversation_id)
+        .bind(message.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // A send error only means nobody is listening right now.
+    let _ = state.chat_events.send(ChatEvent::Message {
+        message: message.clone(),
+    });
+
+    Ok(message)
+}
+
+/// POST /api/v1/conversations
+///
+/// Start (or resume) a conversation with the owner of a property.
+pub async fn create_conversation(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Json(payload): Json<CreateConversationRequest>,
+) -> Result<Json<ApiResponse<Conversation>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    let (owner_id,): (Uuid,) =
+        sqlx::query_as("SELECT owner_id FROM properties WHERE id = $1 AND is_active = true")
+            .bind(payload.property_id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    if owner_id == user_id {
+        return Err(AppError::BadRequest(
+            "You cannot start a conversation about your own property".to_string(),
+        ));
+    }
+
+    // Reuse an existing thread between the same two users about this property.
+    let existing: Option<Conversation> = sqlx::query_as(
+        r#"SELECT * FROM conversations
+           WHERE property_id = $1
+           AND ((participant_1 = $2 AND participant_2 = $3)
+             OR (participant_1 = $3 AND participant_2 = $2))
+           ORDER BY created_at ASC
+           LIMIT 1"#,
+    )
+    .bind(payload.property_id)
+    .bind(user_id)
+    .bind(owner_id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    let conversation = match existing {
+        Some(c) => c,
+        None => sqlx::query_as(
+            r#"INSERT INTO conversations (id, property_id, participant_1, participant_2, created_at)
+                   VALUES ($1, $2, $3, $4, NOW())
+                   RETURNING *"#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(payload.property_id)
+        .bind(user_id)
+        .bind(owner_id)
+        .fetch_one(&state.pool)
+        .await?,
+    };
+
+    let conversation = if let Some(ref content) = payload.message {
+        let message = insert_message(&state, conversation.id, user_id, content).await?;
+        Conversation {
+            last_message_at: Some(message.created_at),
+            ..conversation
+        }
+    } else {
+        conversation
+    };
+
+    Ok(Json(ApiResponse::success(conversation)))
+}
+
+/// GET /api/v1/conversations
+///
+/// List the authenticated user's conversations, most recent first, with the
+/// number of unread messages from the other participant.
+pub async fn list_conversations(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+) -> Result<Json<ApiResponse<Vec<ConversationSummary>>>, AppError> {
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    let conversations: Vec<ConversationSummary> = sqlx::query_as(
+        r#"SELECT c.id, c.property_id, p.title AS property_title, p.slug AS property_slug,
+                  u.id AS other_participant_id, u.full_name AS other_participant_name,
+                  u.avatar_url AS other_participant_avatar,
+                  (SELECT m.content FROM messages m
+                   WHERE m.conversation_id = c.id
+                   ORDER BY m.created_at DESC LIMIT 1) AS last_message,
+                  c.last_message_at,
+                  (SELECT COUNT(*) FROM messages m
+                   WHERE m.conversation_id = c.id
+                   AND m.is_read = false AND m.sender_id != $1) AS unread_count,
+                  c.created_at
+           FROM conversations c
+           JOIN users u ON u.id = CASE WHEN c.participant_1 = $1
+                                       THEN c.participant_2 ELSE c.participant_1 END
+           LEFT JOIN properties p ON p.id = c.property_id
+           WHERE c.participant_1 = $1 OR c.participant_2 = $1
+           ORDER BY COALESCE(c.last_message_at, c.created_at) DESC"#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse::success(conversations)))
+}
+
+/// GET /api/v1/conversations/:id/messages
+///
+/// Page backwards through a conversation. Returns up to `limit` messages
+/// (newest first) created before the `before` timestamp.
+pub async fn list_messages(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(conversation_id): Path<Uuid>,
+    Query(filters): Query<MessageFilters>,
+) -> Result<Json<ApiResponse<Vec<Message>>>, AppError> {
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    load_conversation(&state.pool, conversation_id, user_id).await?;
+
+    let limit = filters.limit.unwrap_or(50).clamp(1, 100);
+
+    let messages: Vec<Message> = sqlx::query_as(
+        r#"SELECT * FROM messages
+           WHERE conversation_id = $1
+           AND ($2::timestamptz IS NULL OR created_at < $2)
+           ORDER BY created_at DESC
+           LIMIT $3"#,
+    )
+    .bind(conversation_id)
+    .bind(filters.before)
+    .bind(limit)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse::success(messages)))
+}
+
+/// POST /api/v1/conversations/:id/messages
+pub async fn send_message(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(conversation_id): Path<Uuid>,
+    Json(payload): Json<SendMessageRequest>,
+) -> Result<Json<ApiResponse<Message>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    load_conversation(&state.pool, conversation_id, user_id).await?;
+
+    let message = insert_message(&state, conversation_id, user_id, &payload.content).await?;
+
+    Ok(Json(ApiResponse::success(message)))
+}
+
+/// PUT /api/v1/conversations/:id/read
+///
+/// Mark every message from the other participant as read.
+pub async fn mark_read(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(conversation_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    load_conversation(&state.pool, conversation_id, user_id).await?;
+
+    let result = sqlx::query(
+        r#"UPDATE messages SET is_read = true
+           WHERE conversation_id = $1 AND sender_id != $2 AND is_read = false"#,
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        let _ = state.chat_events.send(ChatEvent::Read {
+            conversation_id,
+            reader_id: user_id,
+        });
+    }
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "marked_read": result.rows_affected()
+    }))))
+}
+
+/// GET /api/v1/conversations/:id/stream
+///
+/// Server-Sent Events stream of new messages and read receipts for a single
+/// conversation. Only the two participants may subscribe.
+pub async fn stream_conversation(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(conversation_id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    load_conversation(&state.pool, conversation_id, user_id).await?;
+
+    // Lagged receivers simply skip the events they missed; clients re-sync
+    // through the messages endpoint.
+    let stream = BroadcastStream::new(state.chat_events.subscribe()).filter_map(move |event| {
+        let event = event.ok()?;
+        if event.conversation_id() != conversation_id {
+            return None;
+        }
+        let name = match event {
+            ChatEvent::Message { .. } => "message",
+            ChatEvent::Read { .. } => "read",
+        };
+        Event::default().event(name).json_data(&event).ok().map(Ok)
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::auth::Claims;
+    use shared::google::{GoogleVerifier, JwkSet, StaticKeySource};
+    use shared::payment_provider::FakePaymentProvider;
+
+    fn auth(user_id: Uuid) -> RequireAuth {
+        RequireAuth(Claims {
+            sub: user_id.to_string(),
+            email: format!("{user_id}@example.com"),
+            role: "user".to_string(),
+            exp: usize::MAX,
+            ver: 0,
+            aud: "api".to_string(),
+        })
+    }
+
+    fn no_filters() -> Query<MessageFilters> {
+        Query(MessageFilters {
+            before: None,
+            limit: None,
+        })
+    }
+
+    /// Guest and host exchange messages; a third user cannot read, post to,
+    /// mark or stream the thread, and is told it does not exist.
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_message_flow_is_limited_to_participants() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = shared::db::create_pool(&database_url).await.unwrap();
+        let (chat_events, _) = tokio::sync::broadcast::channel(16);
+        let state = Arc::new(AppState {
+            pool: pool.clone(),
+            jwt_secret: "test-secret".to_string(),
+            google: Arc::new(GoogleVerifier::new(
+                "",
+                Arc::new(StaticKeySource::new(JwkSet { keys: vec![] })),
+            )),
+            chat_events,
+            mailer: Arc::new(shared::mailer::MemoryMailer::new()),
+            app_url: "http://localhost:3000".to_string(),
+            payments: Arc::new(FakePaymentProvider::new("whsec_test")),
+        });
+
+        let owner_id = Uuid::new_v4();
+        let guest_id = Uuid::new_v4();
+        let outsider_id = Uuid::new_v4();
+        let property_id = Uuid::new_v4();
+        for id in [owner_id, guest_id, outsider_id] {
+            sqlx::query(
+                "INSERT INTO users (id, email, full_name, role, email_verified) VALUES ($1, $2, 'Test', 'user', true)",
+            )
+            .bind(id)
+            .bind(format!("{id}@example.com"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            r#"INSERT INTO properties (id, owner_id, title, slug, property_type, listing_type,
+                                       price, area, listing_status)
+               VALUES ($1, $2, 'Chat Villa', $3, 'villa', 'sale_freehold',
+                       1000, 'Canggu', 'approved')"#,
+        )
+        .bind(property_id)
+        .bind(owner_id)
+        .bind(format!("chat-villa-{property_id}"))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut events = state.chat_events.subscribe();
+
+        let Json(created) = create_conversation(
+            State(state.clone()),
+            auth(guest_id),
+            Json(CreateConversationRequest {
+                property_id,
+                message: Some("Is it free in May?".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        let conversation = created.data.unwrap();
+        assert!(conversation.has_participant(owner_id));
+        assert!(conversation.last_message_at.is_some());
+        assert_eq!(
+            events.recv().await.unwrap().conversation_id(),
+            conversation.id
+        );
+
+        // Asking again resumes the same thread.
+        let Json(again) = create_conversation(
+            State(state.clone()),
+            auth(guest_id),
+            Json(CreateConversationRequest {
+                property_id,
+                message: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(again.data.unwrap().id, conversation.id);
+
+        let Json(reply) = send_message(
+            State(state.clone()),
+            auth(owner_id),
+            Path(conversation.id),
+            Json(SendMessageRequest {
+                content: "It is.".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply.data.unwrap().sender_id, owner_id);
+
+        let Json(messages) = list_messages(
+            State(state.clone()),
+            auth(guest_id),
+            Path(conversation.id),
+            no_filters(),
+        )
+        .await
+        .unwrap();
+        let contents: Vec<String> = messages
+            .data
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, ["It is.", "Is it free in May?"]);
+
+        let Json(read) = mark_read(State(state.clone()), auth(guest_id), Path(conversation.id))
+            .await
+            .unwrap();
+        assert_eq!(read.data.unwrap()["marked_read"], 1);
+
+        assert!(matches!(
+            list_messages(
+                State(state.clone()),
+                auth(outsider_id),
+                Path(conversation.id),
+                no_filters(),
+            )
+            .await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            send_message(
+                State(state.clone()),
+                auth(outsider_id),
+                Path(conversation.id),
+                Json(SendMessageRequest {
+                    content: "Hello?".to_string(),
+                }),
+            )
+            .await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            mark_read(
+                State(state.clone()),
+                auth(outsider_id),
+                Path(conversation.id)
+            )
+            .await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            stream_conversation(
+                State(state.clone()),
+                auth(outsider_id),
+                Path(conversation.id)
+            )
+            .await,
+            Err(AppError::NotFound(_))
+        ));
+        // An unknown id looks exactly the same.
+        assert!(matches!(
+            load_conversation(&pool, Uuid::new_v4(), guest_id).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(vec![owner_id, guest_id, outsider_id])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
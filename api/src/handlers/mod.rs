@@ -1,7 +1,12 @@
 pub mod amenities;
 pub mod auth;
 pub mod availability;
+pub mod blocked_dates;
 pub mod bookings;
+pub mod calendar;
+pub mod conversations;
+pub mod my_properties;
+pub mod payments;
 pub mod properties;
 pub mod reviews;
 pub mod uploads;
@@ -0,0 +1,489 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use shared::amenities::set_property_amenities;
+use shared::auth::Claims;
+use shared::currency::listing_currency;
+use shared::errors::AppError;
+use shared::listings;
+use shared::models::{Inquiry, ListingStatus};
+use shared::pagination::{next_cursor, PaginationParams};
+use shared::utils::slugify;
+use sqlx::PgConnection;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::handlers::properties::submitted_status;
+use crate::middleware::auth::RequireAuth;
+use crate::models::{
+    AmenityResponse, ApiResponse, BookingResponse, MyPropertyFilters, OwnerListFilters,
+    PropertyListResponse, PropertyResponse, PropertyStats, SetPropertyAmenitiesRequest,
+    UpdatePropertyRequest,
+};
+use crate::AppState;
+
+fn user_id(sub: &str) -> Result<Uuid, AppError> {
+    sub.parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))
+}
+
+/// Load one of the owner's properties, active or not. Other users get a 404
+/// so they cannot probe for property IDs.
+async fn load_own_property(
+    state: &AppState,
+    property_id: Uuid,
+    owner_id: Uuid,
+) -> Result<PropertyResponse, AppError> {
+    sqlx::query_as("SELECT * FROM properties WHERE id = $1 AND owner_id = $2")
+        .bind(property_id)
+        .bind(owner_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Property not found".to_string()))
+}
+
+/// Editing a live listing sends it back for review, unless the owner's
+/// listings skip review. Drafts and rejected listings keep their status
+/// until they are submitted.
+async fn resubmit_if_live(
+    conn: &mut PgConnection,
+    claims: &Claims,
+    property_id: Uuid,
+    status: ListingStatus,
+) -> Result<(), AppError> {
+    if status != ListingStatus::Approved || submitted_status(claims) == ListingStatus::Approved {
+        return Ok(());
+    }
+
+    listings::change_status(
+        conn,
+        property_id,
+        ListingStatus::PendingReview,
+        user_id(&claims.sub)?,
+        Some("Edited by the owner"),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Put the cursor for the next page, if there is one, in `X-Next-Cursor`.
+fn cursor_headers(cursor: Option<String>) -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+    if let Some(cursor) = cursor {
+        headers.insert(
+            HeaderName::from_static("x-next-cursor"),
+            HeaderValue::from_str(&cursor)
+                .map_err(|_| AppError::Internal("Invalid cursor header".to_string()))?,
+        );
+    }
+    Ok(headers)
+}
+
+/// GET /api/v1/me/properties
+///
+/// The caller's listings in every status, newest first.
+pub async fn list_my_properties(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Query(filters): Query<MyPropertyFilters>,
+) -> Result<Json<ApiResponse<PropertyListResponse>>, AppError> {
+    let owner_id = user_id(&claims.sub)?;
+
+    let pagination = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        cursor: filters.cursor.clone(),
+    };
+    let per_page = pagination.limit();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
+
+    let mut items: Vec<PropertyResponse> = sqlx::query_as(
+        r#"SELECT * FROM properties
+           WHERE owner_id = $1
+           AND ($2::bool IS NULL OR is_active = $2)
+           AND ($3::listing_status IS NULL OR listing_status = $3)
+           AND ($4::timestamptz IS NULL OR (created_at, id) < ($4, $5))
+           ORDER BY created_at DESC, id DESC
+           OFFSET $6 LIMIT $7"#,
+    )
+    .bind(owner_id)
+    .bind(filters.is_active)
+    .bind(filters.listing_status)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(pagination.offset())
+    .bind(per_page + 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let next_cursor = next_cursor(&mut items, per_page, "created_at", |p| {
+        (p.created_at.to_rfc3339(), p.id)
+    });
+
+    let total: Option<i64> = if pagination.is_cursor() {
+        None
+    } else {
+        Some(
+            sqlx::query_scalar(
+                r#"SELECT COUNT(*) FROM properties
+                   WHERE owner_id = $1
+                   AND ($2::bool IS NULL OR is_active = $2)
+                   AND ($3::listing_status IS NULL OR listing_status = $3)"#,
+            )
+            .bind(owner_id)
+            .bind(filters.is_active)
+            .bind(filters.listing_status)
+            .fetch_one(&state.pool)
+            .await?,
+        )
+    };
+
+    Ok(Json(ApiResponse::success(PropertyListResponse {
+        items,
+        total,
+        page: total.map(|_| pagination.current_page()),
+        per_page,
+        total_pages: total.map(|total| (total + per_page - 1) / per_page),
+        next_cursor,
+        facets: None,
+    })))
+}
+
+/// GET /api/v1/me/properties/:id
+pub async fn get_my_property(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PropertyResponse>>, AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    let property = load_own_property(&state, property_id, owner_id).await?;
+
+    Ok(Json(ApiResponse::success(property)))
+}
+
+/// PUT /api/v1/me/properties/:id
+///
+/// Edit one of the caller's listings. A live listing edited by a user whose
+/// listings need review (everyone but agents and admins) goes back into the
+/// moderation queue. Drafts and rejected listings keep their status until
+/// they are submitted.
+pub async fn update_my_property(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    Json(payload): Json<UpdatePropertyRequest>,
+) -> Result<Json<ApiResponse<PropertyResponse>>, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+    let owner_id = user_id(&claims.sub)?;
+    let existing = load_own_property(&state, property_id, owner_id).await?;
+
+    let currency = match payload.currency {
+        Some(ref code) => listing_currency(&state.pool, Some(code)).await?,
+        None => existing.currency,
+    };
+    let slug = match payload.title {
+        Some(ref title) => format!("{}-{}", slugify(title), &property_id.to_string()[..8]),
+        None => existing.slug,
+    };
+
+    let mut tx = state.pool.begin().await?;
+    resubmit_if_live(&mut tx, &claims, property_id, existing.listing_status).await?;
+
+    let property: PropertyResponse = sqlx::query_as(
+        r#"UPDATE properties
+           SET title = $3, slug = $4, description = $5, property_type = $6,
+               listing_type = $7, price = $8, price_period = $9, currency = $10,
+               area = $11, address = $12, latitude = $13, longitude = $14,
+               bedrooms = $15, bathrooms = $16, land_size_sqm = $17,
+               building_size_sqm = $18, year_built = $19, features = $20,
+               images = $21, thumbnail_url = $22, updated_at = NOW()
+           WHERE id = $1 AND owner_id = $2
+           RETURNING *"#,
+    )
+    .bind(property_id)
+    .bind(owner_id)
+    .bind(payload.title.unwrap_or(existing.title))
+    .bind(&slug)
+    .bind(payload.description.or(existing.description))
+    .bind(payload.property_type.unwrap_or(existing.property_type))
+    .bind(payload.listing_type.unwrap_or(existing.listing_type))
+    .bind(payload.price.unwrap_or(existing.price))
+    .bind(payload.price_period.or(existing.price_period))
+    .bind(&currency)
+    .bind(payload.area.unwrap_or(existing.area))
+    .bind(payload.address.or(existing.address))
+    .bind(payload.latitude.or(existing.latitude))
+    .bind(payload.longitude.or(existing.longitude))
+    .bind(payload.bedrooms.or(existing.bedrooms))
+    .bind(payload.bathrooms.or(existing.bathrooms))
+    .bind(payload.land_size_sqm.or(existing.land_size_sqm))
+    .bind(payload.building_size_sqm.or(existing.building_size_sqm))
+    .bind(payload.year_built.or(existing.year_built))
+    .bind(payload.features.unwrap_or(existing.features))
+    .bind(payload.images.unwrap_or(existing.images))
+    .bind(payload.thumbnail_url.or(existing.thumbnail_url))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(ref slugs) = payload.amenity_slugs {
+        set_property_amenities(&mut tx, property_id, slugs).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse::success(property)))
+}
+
+/// PUT /api/v1/me/properties/:id/amenities
+///
+/// Replace the amenity list of one of the caller's listings. Like any other
+/// edit, it sends a regular user's live listing back for review.
+pub async fn set_my_property_amenities(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    Json(payload): Json<SetPropertyAmenitiesRequest>,
+) -> Result<Json<ApiResponse<Vec<AmenityResponse>>>, AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    let existing = load_own_property(&state, property_id, owner_id).await?;
+
+    let mut tx = state.pool.begin().await?;
+    resubmit_if_live(&mut tx, &claims, property_id, existing.listing_status).await?;
+    let amenities = set_property_amenities(&mut tx, property_id, &payload.amenity_slugs).await?;
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse::success(
+        amenities.into_iter().map(AmenityResponse::from).collect(),
+    )))
+}
+
+/// PUT /api/v1/me/properties/:id/toggle-active
+///
+/// Take a live listing off the market (archive it) or put an archived one
+/// back. Listings of users whose listings need review go back through the
+/// moderation queue rather than straight to live.
+pub async fn toggle_my_property_active(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PropertyResponse>>, AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    let existing = load_own_property(&state, property_id, owner_id).await?;
+
+    let next = match existing.listing_status {
+        ListingStatus::Approved => ListingStatus::Archived,
+        ListingStatus::Archived => submitted_status(&claims),
+        _ => {
+            return Err(AppError::Conflict(format!(
+                "Only live or archived listings can be switched on or off; this one is {}",
+                existing.listing_status.as_str()
+            )))
+        }
+    };
+
+    let mut tx = state.pool.begin().await?;
+    listings::change_status(&mut tx, property_id, next, owner_id, None).await?;
+    tx.commit().await?;
+
+    let property = load_own_property(&state, property_id, owner_id).await?;
+
+    Ok(Json(ApiResponse::success(property)))
+}
+
+/// POST /api/v1/me/properties/:id/submit
+///
+/// Submit a draft, or a rejected listing after editing it, for review.
+/// Agents' and admins' listings go live straight away.
+pub async fn submit_my_property(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PropertyResponse>>, AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    let existing = load_own_property(&state, property_id, owner_id).await?;
+
+    if !matches!(
+        existing.listing_status,
+        ListingStatus::Draft | ListingStatus::Rejected
+    ) {
+        return Err(AppError::Conflict(format!(
+            "Only draft or rejected listings can be submitted; this one is {}",
+            existing.listing_status.as_str()
+        )));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    listings::change_status(
+        &mut tx,
+        property_id,
+        submitted_status(&claims),
+        owner_id,
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let property = load_own_property(&state, property_id, owner_id).await?;
+
+    Ok(Json(ApiResponse::success(property)))
+}
+
+/// GET /api/v1/me/properties/:id/stats
+///
+/// Views, saves, inquiries and bookings for one of the caller's listings.
+pub async fn get_my_property_stats(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PropertyStats>>, AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    let property = load_own_property(&state, property_id, owner_id).await?;
+
+    let (saved_count, inquiry_count, new_inquiry_count): (i64, i64, i64) = sqlx::query_as(
+        r#"SELECT
+               (SELECT COUNT(*) FROM saved_properties WHERE property_id = $1),
+               (SELECT COUNT(*) FROM inquiries WHERE property_id = $1),
+               (SELECT COUNT(*) FROM inquiries WHERE property_id = $1 AND status = 'new')"#,
+    )
+    .bind(property_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let by_status: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT status::text, COUNT(*) FROM bookings WHERE property_id = $1 GROUP BY status",
+    )
+    .bind(property_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let (upcoming_bookings, booked_revenue): (i64, Decimal) = sqlx::query_as(
+        r#"SELECT
+               COUNT(*) FILTER (WHERE status IN ('confirmed', 'checked_in') AND check_out > CURRENT_DATE),
+               COALESCE(SUM(total_price) FILTER (
+                   WHERE status IN ('confirmed', 'checked_in', 'checked_out') AND currency = $2
+               ), 0)
+           FROM bookings
+           WHERE property_id = $1"#,
+    )
+    .bind(property_id)
+    .bind(&property.currency)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse::success(PropertyStats {
+        property_id,
+        is_active: property.is_active,
+        listing_status: property.listing_status,
+        view_count: property.view_count,
+        saved_count,
+        inquiry_count,
+        new_inquiry_count,
+        bookings_by_status: by_status.into_iter().collect(),
+        upcoming_bookings,
+        booked_revenue,
+        currency: property.currency,
+    })))
+}
+
+/// GET /api/v1/me/properties/:id/inquiries
+///
+/// Inquiries about one of the caller's listings, newest first. The cursor for
+/// the next page, if there is one, is returned in the `X-Next-Cursor` header.
+pub async fn list_my_property_inquiries(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    Query(filters): Query<OwnerListFilters>,
+) -> Result<(HeaderMap, Json<ApiResponse<Vec<Inquiry>>>), AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    load_own_property(&state, property_id, owner_id).await?;
+
+    let pagination = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        cursor: filters.cursor.clone(),
+    };
+    let per_page = pagination.limit();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
+
+    let mut inquiries: Vec<Inquiry> = sqlx::query_as(
+        r#"SELECT * FROM inquiries
+           WHERE property_id = $1
+           AND ($2::text IS NULL OR status::text = $2)
+           AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4))
+           ORDER BY created_at DESC, id DESC
+           OFFSET $5 LIMIT $6"#,
+    )
+    .bind(property_id)
+    .bind(&filters.status)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(pagination.offset())
+    .bind(per_page + 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let cursor = next_cursor(&mut inquiries, per_page, "created_at", |i| {
+        (i.created_at.to_rfc3339(), i.id)
+    });
+
+    Ok((
+        cursor_headers(cursor)?,
+        Json(ApiResponse::success(inquiries)),
+    ))
+}
+
+/// GET /api/v1/me/properties/:id/bookings
+///
+/// Bookings of one of the caller's listings, newest first. The cursor for the
+/// next page, if there is one, is returned in the `X-Next-Cursor` header.
+pub async fn list_my_property_bookings(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(property_id): Path<Uuid>,
+    Query(filters): Query<OwnerListFilters>,
+) -> Result<(HeaderMap, Json<ApiResponse<Vec<BookingResponse>>>), AppError> {
+    let owner_id = user_id(&claims.sub)?;
+    load_own_property(&state, property_id, owner_id).await?;
+
+    let pagination = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        cursor: filters.cursor.clone(),
+    };
+    let per_page = pagination.limit();
+    let after = pagination.after::<DateTime<Utc>>("created_at")?;
+
+    let mut bookings: Vec<BookingResponse> = sqlx::query_as(
+        r#"SELECT * FROM bookings
+           WHERE property_id = $1
+           AND ($2::text IS NULL OR status::text = $2)
+           AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4))
+           ORDER BY created_at DESC, id DESC
+           OFFSET $5 LIMIT $6"#,
+    )
+    .bind(property_id)
+    .bind(&filters.status)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(pagination.offset())
+    .bind(per_page + 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let cursor = next_cursor(&mut bookings, per_page, "created_at", |b| {
+        (b.created_at.to_rfc3339(), b.id)
+    });
+
+    Ok((
+        cursor_headers(cursor)?,
+        Json(ApiResponse::success(bookings)),
+    ))
+}
@@ -0,0 +1,85 @@
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use serde_json::json;
+use shared::errors::AppError;
+use shared::models::Booking;
+use shared::payments::{self, PaymentSummary};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::middleware::auth::RequireAuth;
+use crate::models::{ApiResponse, PaymentIntentResponse, StartPaymentRequest};
+use crate::AppState;
+
+/// POST /api/v1/bookings/:id/payments
+///
+/// Start paying for one of the guest's bookings, either in full or as a
+/// deposit followed later by the balance. The booking is confirmed once the
+/// payment provider reports the payment succeeded.
+pub async fn start_payment(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(booking_id): Path<Uuid>,
+    Json(payload): Json<StartPaymentRequest>,
+) -> Result<Json<ApiResponse<PaymentIntentResponse>>, AppError> {
+    let guest_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    let mut payment = payments::start_payment(
+        &state.pool,
+        state.payments.as_ref(),
+        booking_id,
+        guest_id,
+        payload.kind,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success(PaymentIntentResponse {
+        client_secret: payment.client_secret.take(),
+        payment,
+    })))
+}
+
+/// GET /api/v1/bookings/:id/payments
+///
+/// The payments and refunds on one of the guest's bookings.
+pub async fn list_payments(
+    State(state): State<Arc<AppState>>,
+    RequireAuth(claims): RequireAuth,
+    Path(booking_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PaymentSummary>>, AppError> {
+    let guest_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Internal("Invalid user ID".to_string()))?;
+
+    let booking: Booking = sqlx::query_as("SELECT * FROM bookings WHERE id = $1 AND guest_id = $2")
+        .bind(booking_id)
+        .bind(guest_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
+
+    let summary = payments::payment_summary(&state.pool, &booking).await?;
+
+    Ok(Json(ApiResponse::success(summary)))
+}
+
+/// POST /api/v1/payments/webhook
+///
+/// Called by the payment provider. The signature is checked against the raw
+/// body before anything is applied. Errors make the provider retry later.
+pub async fn webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let event = state.payments.parse_webhook(&headers, &body)?;
+    payments::handle_webhook_event(&state.pool, state.payments.as_ref(), event).await?;
+
+    Ok(Json(json!({ "received": true })))
+}
@@ -1,26 +1,136 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use shared::amenities::set_property_amenities;
+use shared::auth::{ensure_email_verified, Claims, Permission};
+use shared::currency::{base_amount_sql, listing_currency, DisplayCurrency};
 use shared::errors::AppError;
+use shared::geo::{haversine_sql, validate_point, BoundingBox};
+use shared::listings;
+use shared::models::{ListingStatus, PricingTier};
+use shared::notifications;
+use shared::pagination::{try_next_cursor, PaginationParams};
+use shared::pricing::{quote_stay, ListingPrice};
 use shared::utils::slugify;
+use sqlx::PgPool;
+use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::middleware::auth::{OptionalAuth, RequireAuth};
 use crate::models::{
-    ApiResponse, AreaCount, CreateInquiryRequest, CreatePropertyRequest, PropertyFilters,
-    PropertyListResponse, PropertyResponse,
+    ApiResponse, AreaCount, CreateInquiryRequest, CreatePropertyRequest, DisplayCurrencyQuery,
+    PropertyFilters, PropertyListResponse, PropertyResponse, SearchFacets,
 };
 use crate::AppState;
 
+/// A column sort order for the property list.
+#[derive(Debug, Clone, Copy)]
+enum SortKey {
+    Newest,
+    Oldest,
+    PriceAsc,
+    PriceDesc,
+    Views,
+}
+
+impl SortKey {
+    /// The `sort_by` value, recorded in cursors.
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Newest => "newest",
+            SortKey::Oldest => "oldest",
+            SortKey::PriceAsc => "price_asc",
+            SortKey::PriceDesc => "price_desc",
+            SortKey::Views => "views",
+        }
+    }
+
+    /// Prices are compared in the base currency, whatever each listing is
+    /// priced in.
+    fn column(self) -> String {
+        match self {
+            SortKey::Newest | SortKey::Oldest => "p.created_at".to_string(),
+            SortKey::PriceAsc | SortKey::PriceDesc => base_amount_sql("p.price", "p.currency"),
+            SortKey::Views => "p.view_count".to_string(),
+        }
+    }
+
+    fn descending(self) -> bool {
+        matches!(self, SortKey::Newest | SortKey::PriceDesc | SortKey::Views)
+    }
+
+    fn order_clause(self) -> String {
+        let direction = if self.descending() { "DESC" } else { "ASC" };
+        format!("{} {direction}, p.id {direction}", self.column())
+    }
+
+    /// Rows after the cursor position. The key is bound as text and cast
+    /// back to the column type.
+    fn keyset_condition(self, key_param: usize, id_param: usize) -> String {
+        let cast = match self {
+            SortKey::Newest | SortKey::Oldest => "timestamptz",
+            SortKey::PriceAsc | SortKey::PriceDesc => "numeric",
+            SortKey::Views => "integer",
+        };
+        let op = if self.descending() { "<" } else { ">" };
+        format!(
+            "({}, p.id) {op} (${key_param}::{cast}, ${id_param})",
+            self.column()
+        )
+    }
+
+    /// The cursor position, with the key checked to parse as the column type.
+    fn after(self, pagination: &PaginationParams) -> Result<Option<(String, Uuid)>, AppError> {
+        let after = match self {
+            SortKey::Newest | SortKey::Oldest => pagination
+                .after::<DateTime<Utc>>(self.name())?
+                .map(|(key, id)| (key.to_rfc3339(), id)),
+            SortKey::PriceAsc | SortKey::PriceDesc => pagination
+                .after::<Decimal>(self.name())?
+                .map(|(key, id)| (key.to_string(), id)),
+            SortKey::Views => pagination
+                .after::<i32>(self.name())?
+                .map(|(key, id)| (key.to_string(), id)),
+        };
+        Ok(after)
+    }
+
+    /// The cursor key of `property`: the same value [`SortKey::column`]
+    /// orders by, so prices are the amount converted to the base currency.
+    fn value(self, property: &PropertyResponse) -> Result<String, AppError> {
+        let value = match self {
+            SortKey::Newest | SortKey::Oldest => property.created_at.to_rfc3339(),
+            SortKey::PriceAsc | SortKey::PriceDesc => property
+                .price_in_base
+                .ok_or_else(|| {
+                    AppError::Internal(format!(
+                        "No base-currency price for property {}",
+                        property.id
+                    ))
+                })?
+                .to_string(),
+            SortKey::Views => property.view_count.to_string(),
+        };
+        Ok(value)
+    }
+}
+
 /// GET /api/v1/properties
 pub async fn list_properties(
     State(state): State<Arc<AppState>>,
     Query(filters): Query<PropertyFilters>,
 ) -> Result<Json<ApiResponse<PropertyListResponse>>, AppError> {
-    let page = filters.page.unwrap_or(1).max(1);
-    let per_page = filters.per_page.unwrap_or(12).clamp(1, 100);
-    let offset = (page - 1) * per_page;
+    let pagination = PaginationParams {
+        page: filters.page,
+        per_page: Some(filters.per_page.unwrap_or(12)),
+        cursor: filters.cursor.clone(),
+    };
+    let page = pagination.current_page();
+    let per_page = pagination.limit();
+    let offset = pagination.offset();
 
     let mut conditions: Vec<String> = vec!["p.is_active = true".to_string()];
     let mut bind_idx: usize = 0;
@@ -29,16 +139,39 @@ pub async fn list_properties(
     struct BindValues {
         property_type: Option<String>,
         listing_type: Option<String>,
-        min_price: Option<f64>,
-        max_price: Option<f64>,
+        min_price: Option<Decimal>,
+        max_price: Option<Decimal>,
         bedrooms: Option<i32>,
         bathrooms: Option<i32>,
         area: Option<String>,
         search: Option<String>,
+        origin: Option<(f64, f64)>,
+        radius_km: Option<f64>,
+        bounds: Vec<BoundingBox>,
+        stay: Option<(NaiveDate, NaiveDate)>,
+        guests: Option<i32>,
+        amenities: Option<Vec<String>>,
+        after: Option<(String, Uuid)>,
     }
 
     let mut binds = BindValues::default();
 
+    let display =
+        DisplayCurrency::from_param(&state.pool, filters.display_currency.as_deref()).await?;
+
+    // Price bounds are given in the display currency (USD by default) and
+    // compared with each listing's price converted to USD.
+    let price_in_base = base_amount_sql("p.price", "p.currency");
+    let price_bound = |amount: f64| -> Result<Decimal, AppError> {
+        let amount = Decimal::try_from(amount).map_err(|_| {
+            AppError::BadRequest("min_price and max_price must be numbers".to_string())
+        })?;
+        Ok(match display {
+            Some(ref display) => display.to_base(amount),
+            None => amount,
+        })
+    };
+
     if let Some(ref pt) = filters.property_type {
         bind_idx += 1;
         conditions.push(format!("p.property_type::text = ${bind_idx}"));
@@ -53,14 +186,14 @@ pub async fn list_properties(
 
     if let Some(min) = filters.min_price {
         bind_idx += 1;
-        conditions.push(format!("p.price >= ${bind_idx}"));
-        binds.min_price = Some(min);
+        conditions.push(format!("{price_in_base} >= ${bind_idx}"));
+        binds.min_price = Some(price_bound(min)?);
     }
 
     if let Some(max) = filters.max_price {
         bind_idx += 1;
-        conditions.push(format!("p.price <= ${bind_idx}"));
-        binds.max_price = Some(max);
+        conditions.push(format!("{price_in_base} <= ${bind_idx}"));
+        binds.max_price = Some(price_bound(max)?);
     }
 
     if let Some(beds) = filters.bedrooms {
@@ -83,122 +216,397 @@ pub async fn list_properties(
         binds.area = Some(format!("%{area_pattern}%"));
     }
 
-    if let Some(ref s) = filters.search {
+    // Full-text match on the weighted search vector, falling back to trigram
+    // word similarity on title and area so that typos ("seminyk") still match.
+    let mut search_columns = String::new();
+    if let Some(s) = filters
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
         bind_idx += 1;
-        let param = bind_idx;
+        let query = format!("websearch_to_tsquery('english', ${bind_idx})");
+        conditions.push(format!(
+            "(p.search_vector @@ {query} OR ${bind_idx} <% p.title OR ${bind_idx} <% p.area)"
+        ));
+        search_columns = format!(
+            r#", (ts_rank(p.search_vector, {query}, 32) * 2
+                  + GREATEST(word_similarity(${bind_idx}, p.title), word_similarity(${bind_idx}, p.area)))::real AS relevance,
+               ts_headline('english', COALESCE(NULLIF(p.description, ''), p.title), {query},
+                           'StartSel=<mark>, StopSel=</mark>, MaxWords=30, MinWords=10, MaxFragments=2') AS snippet"#
+        );
+        binds.search = Some(s.to_string());
+    }
+
+    let origin = match (filters.lat, filters.lng) {
+        (Some(lat), Some(lng)) => {
+            validate_point(lat, lng)?;
+            Some((lat, lng))
+        }
+        (None, None) => None,
+        _ => {
+            return Err(AppError::BadRequest(
+                "lat and lng must be given together".to_string(),
+            ))
+        }
+    };
+
+    // Distance from the search point, for display, radius filtering and
+    // sorting. Properties without coordinates get no distance.
+    let mut distance_column = String::new();
+    if let Some((lat, lng)) = origin {
+        bind_idx += 2;
+        let distance = haversine_sql("p.latitude", "p.longitude", bind_idx - 1, bind_idx);
+        distance_column = format!(", {distance} AS distance_km");
+        binds.origin = Some((lat, lng));
+
+        if let Some(radius) = filters.radius_km {
+            if !(radius.is_finite() && radius > 0.0) {
+                return Err(AppError::BadRequest(
+                    "radius_km must be a positive number".to_string(),
+                ));
+            }
+            bind_idx += 1;
+            conditions.push(format!("{distance} <= ${bind_idx}"));
+            binds.radius_km = Some(radius);
+            // Narrow the candidates with the coordinate index first.
+            binds.bounds.push(BoundingBox::around(lat, lng, radius));
+        }
+    } else if filters.radius_km.is_some() {
+        return Err(AppError::BadRequest(
+            "radius_km requires lat and lng".to_string(),
+        ));
+    }
+
+    if let Some(ref bbox) = filters.bbox {
+        binds.bounds.push(BoundingBox::parse(bbox)?);
+    }
+
+    for bounds in &binds.bounds {
+        let (min_lat, max_lat, min_lng, max_lng) =
+            (bind_idx + 1, bind_idx + 2, bind_idx + 3, bind_idx + 4);
+        bind_idx += 4;
+        let lng_op = if bounds.crosses_antimeridian() {
+            "OR"
+        } else {
+            "AND"
+        };
+        conditions.push(format!(
+            "(p.latitude BETWEEN ${min_lat}::numeric AND ${max_lat}::numeric \
+             AND (p.longitude >= ${min_lng}::numeric {lng_op} p.longitude <= ${max_lng}::numeric))"
+        ));
+    }
+
+    // Only rentals free for the whole stay: no booking that still holds the
+    // dates and no blocked range (both ends of which are blocked).
+    match (filters.check_in, filters.check_out) {
+        (Some(check_in), Some(check_out)) => {
+            if check_out <= check_in {
+                return Err(AppError::BadRequest(
+                    "Check-out must be after check-in".to_string(),
+                ));
+            }
+            let (check_in_param, check_out_param) = (bind_idx + 1, bind_idx + 2);
+            bind_idx += 2;
+            conditions.push("p.listing_type IN ('short_term_rent', 'long_term_rent')".to_string());
+            conditions.push(format!(
+                r#"NOT EXISTS (
+                       SELECT 1 FROM bookings b
+                       WHERE b.property_id = p.id
+                       AND b.status NOT IN ('cancelled', 'refunded')
+                       AND b.check_in < ${check_out_param} AND b.check_out > ${check_in_param})"#
+            ));
+            conditions.push(format!(
+                r#"NOT EXISTS (
+                       SELECT 1 FROM blocked_dates bd
+                       WHERE bd.property_id = p.id
+                       AND bd.start_date < ${check_out_param} AND bd.end_date >= ${check_in_param})"#
+            ));
+            binds.stay = Some((check_in, check_out));
+        }
+        (None, None) => {}
+        _ => {
+            return Err(AppError::BadRequest(
+                "check_in and check_out must be given together".to_string(),
+            ))
+        }
+    }
+
+    if let Some(guests) = filters.guests {
+        if guests < 1 {
+            return Err(AppError::BadRequest(
+                "At least 1 guest required".to_string(),
+            ));
+        }
         bind_idx += 1;
         conditions.push(format!(
-            "(p.title ILIKE ${param} OR p.description ILIKE ${bind_idx} OR p.area ILIKE ${param})"
+            r#"NOT EXISTS (
+                   SELECT 1 FROM property_rules pr
+                   WHERE pr.property_id = p.id AND pr.max_guests < ${bind_idx})"#
         ));
-        binds.search = Some(format!("%{s}%"));
+        binds.guests = Some(guests);
     }
 
-    let where_clause = conditions.join(" AND ");
+    // Every listed amenity must be present.
+    if let Some(ref list) = filters.amenities {
+        let mut slugs: Vec<String> = list
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        slugs.sort();
+        slugs.dedup();
+        if !slugs.is_empty() {
+            bind_idx += 1;
+            conditions.push(format!(
+                r#"p.id IN (
+                       SELECT pa.property_id FROM property_amenities pa
+                       JOIN amenities a ON a.id = pa.amenity_id
+                       WHERE a.slug = ANY(${bind_idx})
+                       GROUP BY pa.property_id
+                       HAVING COUNT(*) = cardinality(${bind_idx}))"#
+            ));
+            binds.amenities = Some(slugs);
+        }
+    }
 
-    let order_clause = match filters.sort_by.as_deref() {
-        Some("price_asc") => "p.price ASC",
-        Some("price_desc") => "p.price DESC",
-        Some("oldest") => "p.created_at ASC",
-        Some("views") => "p.view_count DESC",
-        _ => "p.created_at DESC",
+    // Searches are ordered by relevance unless another order is requested.
+    // Column sorts break ties on the id, so they can also be paged by cursor.
+    let (sort_key, order_clause) = match filters.sort_by.as_deref() {
+        Some("relevance") | None if binds.search.is_some() => (
+            None,
+            "relevance DESC, p.created_at DESC, p.id DESC".to_string(),
+        ),
+        Some("distance") if binds.origin.is_some() => (
+            None,
+            "distance_km ASC NULLS LAST, p.created_at DESC, p.id DESC".to_string(),
+        ),
+        Some("distance") => {
+            return Err(AppError::BadRequest(
+                "sort_by=distance requires lat and lng".to_string(),
+            ))
+        }
+        sort_by => {
+            let key = match sort_by {
+                Some("price_asc") => SortKey::PriceAsc,
+                Some("price_desc") => SortKey::PriceDesc,
+                Some("oldest") => SortKey::Oldest,
+                Some("views") => SortKey::Views,
+                _ => SortKey::Newest,
+            };
+            (Some(key), key.order_clause())
+        }
     };
 
+    if let Some(key) = sort_key {
+        if let Some(after) = key.after(&pagination)? {
+            let (key_param, id_param) = (bind_idx + 1, bind_idx + 2);
+            bind_idx += 2;
+            conditions.push(key.keyset_condition(key_param, id_param));
+            binds.after = Some(after);
+        }
+    } else if pagination.is_cursor() {
+        return Err(AppError::BadRequest(
+            "Cursor pagination is not available when sorting by relevance or distance".to_string(),
+        ));
+    }
+
+    let where_clause = conditions.join(" AND ");
+
     let count_sql = format!("SELECT COUNT(*) as count FROM properties p WHERE {where_clause}");
 
     let offset_param = bind_idx + 1;
     let limit_param = bind_idx + 2;
     let data_sql = format!(
-        r#"SELECT p.*
+        r#"SELECT p.*, {price_in_base} AS price_in_base{search_columns}{distance_column}
            FROM properties p
            WHERE {where_clause}
            ORDER BY {order_clause}
            OFFSET ${offset_param} LIMIT ${limit_param}"#
     );
 
-    // Execute count query
-    let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
-
-    if let Some(ref v) = binds.property_type {
-        count_query = count_query.bind(v);
-    }
-    if let Some(ref v) = binds.listing_type {
-        count_query = count_query.bind(v);
-    }
-    if let Some(v) = binds.min_price {
-        count_query = count_query.bind(v);
-    }
-    if let Some(v) = binds.max_price {
-        count_query = count_query.bind(v);
-    }
-    if let Some(v) = binds.bedrooms {
-        count_query = count_query.bind(v);
-    }
-    if let Some(v) = binds.bathrooms {
-        count_query = count_query.bind(v);
-    }
-    if let Some(ref v) = binds.area {
-        count_query = count_query.bind(v);
-    }
-    if let Some(ref v) = binds.search {
-        count_query = count_query.bind(v);
-        count_query = count_query.bind(v);
+    // The count, data and facet queries share the WHERE clause, so bind the
+    // filter values to each in the order their placeholders were assigned.
+    macro_rules! bind_filters {
+        ($query:expr) => {{
+            let mut query = $query;
+            if let Some(ref v) = binds.property_type {
+                query = query.bind(v);
+            }
+            if let Some(ref v) = binds.listing_type {
+                query = query.bind(v);
+            }
+            if let Some(v) = binds.min_price {
+                query = query.bind(v);
+            }
+            if let Some(v) = binds.max_price {
+                query = query.bind(v);
+            }
+            if let Some(v) = binds.bedrooms {
+                query = query.bind(v);
+            }
+            if let Some(v) = binds.bathrooms {
+                query = query.bind(v);
+            }
+            if let Some(ref v) = binds.area {
+                query = query.bind(v);
+            }
+            if let Some(ref v) = binds.search {
+                query = query.bind(v);
+            }
+            if let Some((lat, lng)) = binds.origin {
+                query = query.bind(lat).bind(lng);
+            }
+            if let Some(v) = binds.radius_km {
+                query = query.bind(v);
+            }
+            for b in &binds.bounds {
+                query = query
+                    .bind(b.min_lat)
+                    .bind(b.max_lat)
+                    .bind(b.min_lng)
+                    .bind(b.max_lng);
+            }
+            if let Some((check_in, check_out)) = binds.stay {
+                query = query.bind(check_in).bind(check_out);
+            }
+            if let Some(v) = binds.guests {
+                query = query.bind(v);
+            }
+            if let Some(ref v) = binds.amenities {
+                query = query.bind(v);
+            }
+            if let Some((ref key, id)) = binds.after {
+                query = query.bind(key).bind(id);
+            }
+            query
+        }};
     }
 
-    let total: i64 = count_query.fetch_one(&state.pool).await?;
-
-    // Execute data query
-    let mut data_query = sqlx::query_as::<_, PropertyResponse>(&data_sql);
+    // Execute data query, fetching one extra row to tell whether there is a
+    // next page
+    let mut data_query = bind_filters!(sqlx::query_as::<_, PropertyResponse>(&data_sql));
+    data_query = data_query.bind(offset).bind(per_page + 1);
+
+    let mut items: Vec<PropertyResponse> = data_query.fetch_all(&state.pool).await?;
+    let next_cursor = match sort_key {
+        Some(key) => try_next_cursor(&mut items, per_page, key.name(), |p| {
+            Ok::<_, AppError>((key.value(p)?, p.id))
+        })?,
+        None => {
+            items.truncate(per_page as usize);
+            None
+        }
+    };
 
-    if let Some(ref v) = binds.property_type {
-        data_query = data_query.bind(v);
-    }
-    if let Some(ref v) = binds.listing_type {
-        data_query = data_query.bind(v);
-    }
-    if let Some(v) = binds.min_price {
-        data_query = data_query.bind(v);
-    }
-    if let Some(v) = binds.max_price {
-        data_query = data_query.bind(v);
-    }
-    if let Some(v) = binds.bedrooms {
-        data_query = data_query.bind(v);
-    }
-    if let Some(v) = binds.bathrooms {
-        data_query = data_query.bind(v);
-    }
-    if let Some(ref v) = binds.area {
-        data_query = data_query.bind(v);
-    }
-    if let Some(ref v) = binds.search {
-        data_query = data_query.bind(v);
-        data_query = data_query.bind(v);
+    if let Some((check_in, check_out)) = binds.stay {
+        price_stays(&state.pool, &mut items, check_in, check_out).await?;
     }
 
-    data_query = data_query.bind(offset).bind(per_page);
-
-    let items: Vec<PropertyResponse> = data_query.fetch_all(&state.pool).await?;
+    if let Some(ref display) = display {
+        for item in items.iter_mut() {
+            item.show_prices_in(display);
+        }
+    }
 
-    let total_pages = if total == 0 {
-        0
+    // The total and facets describe the whole result set and are the same on
+    // every page, so cursor pages skip them.
+    let (total, facets) = if pagination.is_cursor() {
+        (None, None)
     } else {
-        (total + per_page - 1) / per_page
+        let count_query = bind_filters!(sqlx::query_scalar::<_, i64>(&count_sql));
+        let total: i64 = count_query.fetch_one(&state.pool).await?;
+
+        let facet_sql = format!(
+            r#"SELECT 'property_type' AS facet, p.property_type::text AS value, NULL AS label, COUNT(*) AS count
+               FROM properties p WHERE {where_clause} GROUP BY 2
+               UNION ALL
+               SELECT 'listing_type', p.listing_type::text, NULL, COUNT(*)
+               FROM properties p WHERE {where_clause} GROUP BY 2
+               UNION ALL
+               SELECT 'area', p.area, NULL, COUNT(*)
+               FROM properties p WHERE {where_clause} GROUP BY 2
+               UNION ALL
+               SELECT 'bedrooms', CASE WHEN p.bedrooms >= 5 THEN '5+' ELSE p.bedrooms::text END, NULL, COUNT(*)
+               FROM properties p WHERE {where_clause} AND p.bedrooms IS NOT NULL GROUP BY 2
+               UNION ALL
+               SELECT 'amenities', a.slug, a.name, COUNT(*)
+               FROM properties p
+               JOIN property_amenities pa ON pa.property_id = p.id
+               JOIN amenities a ON a.id = pa.amenity_id
+               WHERE {where_clause} GROUP BY a.slug, a.name"#
+        );
+        let facet_rows: Vec<(String, String, Option<String>, i64)> =
+            bind_filters!(sqlx::query_as(&facet_sql))
+                .fetch_all(&state.pool)
+                .await?;
+
+        (Some(total), Some(SearchFacets::from_rows(facet_rows)))
     };
 
     Ok(Json(ApiResponse::success(PropertyListResponse {
         items,
         total,
-        page,
+        page: total.map(|_| page),
         per_page,
-        total_pages,
+        total_pages: total.map(|total| (total + per_page - 1) / per_page),
+        next_cursor,
+        facets,
     })))
 }
 
+/// Fill in `stay_total` for each listing, priced like the quote endpoint.
+/// Listings whose tiers do not cover a stay of this length get none.
+async fn price_stays(
+    pool: &PgPool,
+    items: &mut [PropertyResponse],
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+) -> Result<(), AppError> {
+    let ids: Vec<Uuid> = items.iter().map(|p| p.id).collect();
+    let tiers: Vec<PricingTier> = sqlx::query_as(
+        "SELECT * FROM pricing_tiers WHERE property_id = ANY($1) AND is_active = true",
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut tiers_by_property: HashMap<Uuid, Vec<PricingTier>> = HashMap::new();
+    for tier in tiers {
+        tiers_by_property
+            .entry(tier.property_id)
+            .or_default()
+            .push(tier);
+    }
+
+    for item in items.iter_mut() {
+        let listing = ListingPrice {
+            price: item.price,
+            price_period: item.price_period.as_ref(),
+            currency: &item.currency,
+        };
+        let tiers = tiers_by_property
+            .get(&item.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        item.stay_total = quote_stay(tiers, listing, check_in, check_out, None)
+            .ok()
+            .map(|quote| quote.total_price);
+    }
+
+    Ok(())
+}
+
 /// GET /api/v1/properties/featured
 pub async fn get_featured(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<DisplayCurrencyQuery>,
 ) -> Result<Json<ApiResponse<Vec<PropertyResponse>>>, AppError> {
-    let properties: Vec<PropertyResponse> = sqlx::query_as(
+    let display =
+        DisplayCurrency::from_param(&state.pool, query.display_currency.as_deref()).await?;
+
+    let mut properties: Vec<PropertyResponse> = sqlx::query_as(
         r#"SELECT * FROM properties
            WHERE is_featured = true AND is_active = true
            ORDER BY created_at DESC
@@ -207,6 +615,12 @@ pub async fn get_featured(
     .fetch_all(&state.pool)
     .await?;
 
+    if let Some(ref display) = display {
+        for property in properties.iter_mut() {
+            property.show_prices_in(display);
+        }
+    }
+
     Ok(Json(ApiResponse::success(properties)))
 }
 
@@ -231,8 +645,12 @@ pub async fn get_areas(
 pub async fn get_property(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
+    Query(query): Query<DisplayCurrencyQuery>,
 ) -> Result<Json<ApiResponse<PropertyResponse>>, AppError> {
-    let property: PropertyResponse = sqlx::query_as(
+    let display =
+        DisplayCurrency::from_param(&state.pool, query.display_currency.as_deref()).await?;
+
+    let mut property: PropertyResponse = sqlx::query_as(
         r#"UPDATE properties
            SET view_count = view_count + 1
            WHERE slug = $1 AND is_active = true
@@ -243,6 +661,10 @@ pub async fn get_property(
     .await?
     .ok_or_else(|| AppError::NotFound(format!("Property with slug '{slug}' not found")))?;
 
+    if let Some(ref display) = display {
+        property.show_prices_in(display);
+    }
+
     Ok(Json(ApiResponse::success(property)))
 }
 
@@ -270,6 +692,7 @@ pub async fn create_inquiry(
     let user_id: Option<Uuid> = claims.as_ref().and_then(|c| c.sub.parse::<Uuid>().ok());
 
     let inquiry_id = Uuid::new_v4();
+    let mut tx = state.pool.begin().await?;
 
     sqlx::query(
         r#"INSERT INTO inquiries (id, property_id, user_id, name, email, phone, message, status, created_at)
@@ -282,19 +705,36 @@ pub async fn create_inquiry(
     .bind(&payload.email)
     .bind(&payload.phone)
     .bind(&payload.message)
-    .execute(&state.pool)
+    .execute(&mut *tx)
     .await?;
 
+    notifications::inquiry_received(&mut tx, inquiry_id).await?;
+
+    tx.commit().await?;
+
     Ok(Json(ApiResponse::success(serde_json::json!({
         "id": inquiry_id,
         "message": "Inquiry submitted successfully"
     }))))
 }
 
+/// The status a listing takes when the caller submits it: live straight away
+/// for roles allowed to publish without review, otherwise queued for
+/// moderation. Unknown roles hold no permissions.
+pub(crate) fn submitted_status(claims: &Claims) -> ListingStatus {
+    if claims.has_permission(Permission::PublishWithoutReview) {
+        ListingStatus::Approved
+    } else {
+        ListingStatus::PendingReview
+    }
+}
+
 /// POST /api/v1/properties
 ///
 /// Create a new property listing. Requires authentication.
-/// Agents get their listings auto-activated; regular users need admin review.
+/// Agents' and admins' listings go live straight away; regular users'
+/// listings wait in the moderation queue. With `draft: true` the listing is
+/// saved without being submitted.
 pub async fn create_property(
     State(state): State<Arc<AppState>>,
     RequireAuth(claims): RequireAuth,
@@ -309,24 +749,28 @@ pub async fn create_property(
         .parse()
         .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))?;
 
-    // Agents get auto-activated; regular users need admin review
-    let is_active = claims.role == "agent"
-        || claims.role == "Agent"
-        || claims.role == "admin"
-        || claims.role == "Admin";
+    ensure_email_verified(&state.pool, owner_id).await?;
+
+    let status = if payload.draft {
+        ListingStatus::Draft
+    } else {
+        submitted_status(&claims)
+    };
 
     let id = Uuid::new_v4();
     let slug = format!("{}-{}", slugify(&payload.title), &id.to_string()[..8]);
     let features = payload.features.unwrap_or(serde_json::json!([]));
     let images = payload.images.unwrap_or(serde_json::json!([]));
-    let currency = payload.currency.unwrap_or_else(|| "USD".to_string());
+    let currency = listing_currency(&state.pool, payload.currency.as_deref()).await?;
+
+    let mut tx = state.pool.begin().await?;
 
     let property: PropertyResponse = sqlx::query_as(
         r#"INSERT INTO properties (
             id, owner_id, title, slug, description, property_type, listing_type,
             price, price_period, currency, area, address, latitude, longitude,
             bedrooms, bathrooms, land_size_sqm, building_size_sqm, year_built,
-            features, images, thumbnail_url, is_featured, is_active, view_count
+            features, images, thumbnail_url, is_featured, listing_status, view_count
         )
         VALUES (
             $1, $2, $3, $4, $5, $6, $7,
@@ -358,9 +802,17 @@ pub async fn create_property(
     .bind(&features)
     .bind(&images)
     .bind(&payload.thumbnail_url)
-    .bind(is_active)
-    .fetch_one(&state.pool)
+    .bind(status)
+    .fetch_one(&mut *tx)
     .await?;
 
+    listings::record_status(&mut tx, id, None, status, Some(owner_id), None).await?;
+
+    if let Some(ref slugs) = payload.amenity_slugs {
+        set_property_amenities(&mut tx, id, slugs).await?;
+    }
+
+    tx.commit().await?;
+
     Ok(Json(ApiResponse::success(property)))
 }
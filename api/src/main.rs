@@ -3,8 +3,15 @@ mod middleware;
 mod models;
 mod routes;
 
+use shared::google::{GoogleVerifier, HttpKeySource};
+use shared::mailer::Mailer;
+use shared::notifications::OutboxWorker;
+use shared::payment_provider::PaymentProvider;
 use sqlx::PgPool;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -13,7 +20,17 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 pub struct AppState {
     pub pool: PgPool,
     pub jwt_secret: String,
-    pub google_client_id: String,
+    /// Verifies Google sign-in tokens against Google's cached signing keys.
+    pub google: Arc<GoogleVerifier>,
+    /// Fan-out channel feeding the live conversation streams.
+    pub chat_events: broadcast::Sender<models::ChatEvent>,
+    /// Delivers verification and password reset emails directly; other
+    /// notifications go through the outbox worker.
+    pub mailer: Arc<dyn Mailer>,
+    /// Base URL of the public site, for links in emails.
+    pub app_url: String,
+    /// Takes booking payments and issues refunds.
+    pub payments: Arc<dyn PaymentProvider>,
 }
 
 #[tokio::main]
@@ -35,8 +52,14 @@ async fn main() {
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let jwt_secret =
         std::env::var("JWT_SECRET").unwrap_or_else(|_| "dev-secret-change-me".to_string());
-    let google_client_id =
-        std::env::var("GOOGLE_CLIENT_ID").unwrap_or_default();
+    let google = Arc::new(GoogleVerifier::new(
+        std::env::var("GOOGLE_CLIENT_ID").unwrap_or_default(),
+        Arc::new(HttpKeySource::google()),
+    ));
+    let app_url = std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let mailer = shared::mailer::from_env().expect("Invalid mailer configuration");
+    let payments =
+        shared::payment_provider::from_env().expect("Invalid payment provider configuration");
 
     // Create the database connection pool.
     let pool = shared::db::create_pool(&database_url)
@@ -45,8 +68,52 @@ async fn main() {
 
     tracing::info!("Database connection pool created");
 
+    // Deliver queued notifications in the background. Workers on several
+    // instances share the outbox safely; set NOTIFICATION_WORKER=false to run
+    // an instance without one.
+    if std::env::var("NOTIFICATION_WORKER").map_or(true, |v| v != "false") {
+        let worker = OutboxWorker::new(pool.clone(), mailer.clone(), app_url.clone());
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = worker.drain().await {
+                    tracing::error!("Notification delivery failed: {e}");
+                }
+            }
+        });
+        tracing::info!("Notification worker started");
+    }
+
+    // Keep Google's signing keys cached so sign-ins are verified without a
+    // round trip to Google. Keys are refetched when Google says they expire.
+    if google.is_configured() {
+        let google = google.clone();
+        tokio::spawn(async move {
+            loop {
+                let wait = match google.refresh().await {
+                    Ok(ttl) => ttl,
+                    Err(e) => {
+                        tracing::warn!("Google signing key refresh failed: {e}");
+                        Duration::from_secs(60)
+                    }
+                };
+                tokio::time::sleep(wait).await;
+            }
+        });
+    }
+
     // Build shared application state.
-    let state = Arc::new(AppState { pool, jwt_secret, google_client_id });
+    let (chat_events, _) = broadcast::channel(256);
+    let state = Arc::new(AppState {
+        pool,
+        jwt_secret,
+        google,
+        chat_events,
+        mailer,
+        app_url,
+        payments,
+    });
 
     // CORS: allow all origins during development.
     let cors = CorsLayer::new()
@@ -66,5 +133,11 @@ async fn main() {
 
     tracing::info!("MyBaliVilla API listening on 0.0.0.0:8080");
 
-    axum::serve(listener, app).await.expect("Server error");
+    // Connection info gives handlers the client address for login rate limiting.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Server error");
 }
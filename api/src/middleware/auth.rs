@@ -4,31 +4,44 @@ use axum::{
     middleware::Next,
     response::Response,
 };
-use shared::auth::{verify_token, Claims};
+use shared::auth::{authenticate, Audience, Claims, TokenAuthority};
 use shared::errors::AppError;
+use sqlx::PgPool;
 use std::sync::Arc;
 
 use crate::AppState;
 
+/// Lets `auth_middleware` verify bearer tokens with
+/// `shared::auth::authenticate`, and handlers take `shared::auth::Require<P>`
+/// to admit only roles holding a permission.
+impl TokenAuthority for AppState {
+    fn jwt_secret(&self) -> &str {
+        &self.jwt_secret
+    }
+
+    fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    fn audience(&self) -> Audience {
+        Audience::Api
+    }
+}
+
 /// Middleware that extracts a Bearer token from the Authorization header,
 /// verifies it, and injects the resulting `Claims` into request extensions.
 ///
 /// If no Authorization header is present, the request proceeds without claims.
-/// If the header is present but invalid, a 401 is returned.
+/// If the header is present but invalid, or the token has been revoked, a 401
+/// is returned.
 pub async fn auth_middleware(
     state: axum::extract::State<Arc<AppState>>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, AppError> {
-    if let Some(auth_header) = request.headers().get(AUTHORIZATION) {
-        let auth_str = auth_header
-            .to_str()
-            .map_err(|_| AppError::Unauthorized("Invalid authorization header".to_string()))?;
-
-        if let Some(token) = auth_str.strip_prefix("Bearer ") {
-            let claims = verify_token(token, &state.jwt_secret)?;
-            request.extensions_mut().insert(claims);
-        }
+    if request.headers().contains_key(AUTHORIZATION) {
+        let claims = authenticate(request.headers(), state.as_ref()).await?;
+        request.extensions_mut().insert(claims);
     }
 
     Ok(next.run(request).await)
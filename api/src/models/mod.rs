@@ -1,7 +1,13 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use shared::models::{ListingType, PricePeriod, PropertyType, UserRole};
+use shared::currency::DisplayCurrency;
+use shared::models::{
+    ListingStatus, ListingType, Payment, PaymentKind, PricePeriod, PropertyType, UserRole,
+};
+use shared::pricing::PriceQuote;
+use shared::refresh_tokens::TokenPair;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use validator::Validate;
 
@@ -60,10 +66,42 @@ pub struct GoogleLoginRequest {
 
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
-    pub token: String,
+    #[serde(flatten)]
+    pub tokens: TokenPair,
     pub user: UserResponse,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email address"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: String,
+    /// End every session of the account, not just this one.
+    #[serde(default)]
+    pub all_devices: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponse {
     pub id: Uuid,
@@ -72,6 +110,7 @@ pub struct UserResponse {
     pub phone: Option<String>,
     pub avatar_url: Option<String>,
     pub role: UserRole,
+    pub email_verified: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -84,6 +123,7 @@ impl From<shared::models::User> for UserResponse {
             phone: u.phone,
             avatar_url: u.avatar_url,
             role: u.role,
+            email_verified: u.email_verified,
             created_at: u.created_at,
         }
     }
@@ -94,10 +134,76 @@ impl From<shared::models::User> for UserResponse {
 #[derive(Debug, Serialize)]
 pub struct PropertyListResponse {
     pub items: Vec<PropertyResponse>,
-    pub total: i64,
-    pub page: i64,
+    /// `total`, `page`, `total_pages` and `facets` are left out of cursor
+    /// pages, which skip counting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,
     pub per_page: i64,
-    pub total_pages: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<i64>,
+    /// Pass as `cursor` to fetch the next page; `null` on the last page and
+    /// when sorting by relevance or distance.
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<SearchFacets>,
+}
+
+/// Number of matching properties for each value of a filter, under the
+/// current filter set.
+#[derive(Debug, Default, Serialize)]
+pub struct SearchFacets {
+    pub amenities: Vec<FacetCount>,
+    pub property_type: Vec<FacetCount>,
+    pub listing_type: Vec<FacetCount>,
+    pub area: Vec<FacetCount>,
+    /// Bedroom counts `0` to `4`, then `5+`.
+    pub bedrooms: Vec<FacetCount>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    /// Display name, for facets whose values are slugs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub count: i64,
+}
+
+impl SearchFacets {
+    /// Build from `(facet, value, label, count)` rows, most common first.
+    pub fn from_rows(rows: Vec<(String, String, Option<String>, i64)>) -> Self {
+        let mut facets = Self::default();
+        for (facet, value, label, count) in rows {
+            let bucket = match facet.as_str() {
+                "amenities" => &mut facets.amenities,
+                "property_type" => &mut facets.property_type,
+                "listing_type" => &mut facets.listing_type,
+                "area" => &mut facets.area,
+                "bedrooms" => &mut facets.bedrooms,
+                _ => continue,
+            };
+            bucket.push(FacetCount {
+                value,
+                label,
+                count,
+            });
+        }
+
+        for bucket in [
+            &mut facets.amenities,
+            &mut facets.property_type,
+            &mut facets.listing_type,
+            &mut facets.area,
+        ] {
+            bucket.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        }
+        // Bedroom buckets read best in order ("5+" sorts last as text).
+        facets.bedrooms.sort_by(|a, b| a.value.cmp(&b.value));
+
+        facets
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -131,21 +237,108 @@ pub struct PropertyResponse {
     pub review_count: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub listing_status: ListingStatus,
+    /// Why an admin last rejected the listing; cleared once it is approved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_reason: Option<String>,
+    /// Search relevance score; only present in search results.
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<f32>,
+    /// Description excerpt with matched terms wrapped in `<mark>`; only
+    /// present in search results.
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Distance in kilometres from the `lat`/`lng` search point; only
+    /// present when one is given.
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance_km: Option<f64>,
+    /// Total price of the requested stay, fees included; only present when
+    /// `check_in`/`check_out` are given and the listing's pricing covers a
+    /// stay of that length.
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stay_total: Option<Decimal>,
+    /// Price converted to the base currency, used for price sorting.
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub price_in_base: Option<Decimal>,
+    /// Prices in the requested `display_currency`; only present when one is
+    /// given.
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<PropertyDisplayPrice>,
+}
+
+impl PropertyResponse {
+    pub fn show_prices_in(&mut self, display: &DisplayCurrency) {
+        self.display =
+            display
+                .convert(self.price, &self.currency)
+                .map(|price| PropertyDisplayPrice {
+                    currency: display.currency.clone(),
+                    price,
+                    stay_total: self
+                        .stay_total
+                        .and_then(|total| display.convert(total, &self.currency)),
+                });
+    }
+}
+
+/// Listing prices converted for display. Bookings are still charged in the
+/// listing's own currency.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PropertyDisplayPrice {
+    pub currency: String,
+    pub price: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stay_total: Option<Decimal>,
+}
+
+/// Query for endpoints that can show prices in another currency.
+#[derive(Debug, Deserialize)]
+pub struct DisplayCurrencyQuery {
+    /// ISO 4217 code to convert prices to, e.g. `EUR`.
+    pub display_currency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PropertyFilters {
     pub property_type: Option<String>,
     pub listing_type: Option<String>,
+    /// Price bounds in `display_currency`, or USD when none is given. Listings
+    /// priced in other currencies are converted before comparing.
     pub min_price: Option<f64>,
     pub max_price: Option<f64>,
     pub bedrooms: Option<i32>,
     pub bathrooms: Option<i32>,
     pub area: Option<String>,
+    /// Free-text search over title, area, features and description.
     pub search: Option<String>,
+    /// Search point for `radius_km` and `sort_by=distance`.
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+    pub radius_km: Option<f64>,
+    /// Map viewport as `min_lng,min_lat,max_lng,max_lat`.
+    pub bbox: Option<String>,
+    /// Only rentals free for the whole stay (check-out day excluded).
+    pub check_in: Option<chrono::NaiveDate>,
+    pub check_out: Option<chrono::NaiveDate>,
+    /// Only properties whose house rules allow this many guests.
+    pub guests: Option<i32>,
+    /// Comma-separated amenity slugs, e.g. `pool,wifi`; all must be present.
+    pub amenities: Option<String>,
+    /// `newest` (default), `oldest`, `price_asc`, `price_desc`, `views`,
+    /// `relevance` (the default when `search` is given) or `distance`.
     pub sort_by: Option<String>,
     pub page: Option<i64>,
     pub per_page: Option<i64>,
+    /// `next_cursor` from the previous page; takes precedence over `page`.
+    pub cursor: Option<String>,
+    /// ISO 4217 code to show prices in, e.g. `EUR`.
+    pub display_currency: Option<String>,
 }
 
 // ── Create Property DTO ──────────────────────────────────────────────────
@@ -173,6 +366,84 @@ pub struct CreatePropertyRequest {
     pub features: Option<serde_json::Value>,
     pub images: Option<serde_json::Value>,
     pub thumbnail_url: Option<String>,
+    /// Amenities to attach, by slug.
+    pub amenity_slugs: Option<Vec<String>>,
+    /// Save the listing as a draft instead of submitting it for review.
+    #[serde(default)]
+    pub draft: bool,
+}
+
+// ── Owner Dashboard DTOs ────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct MyPropertyFilters {
+    /// `true` for live listings only, `false` for all others.
+    pub is_active: Option<bool>,
+    pub listing_status: Option<ListingStatus>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    /// `next_cursor` from the previous page; takes precedence over `page`.
+    pub cursor: Option<String>,
+}
+
+/// Changes to one of the owner's listings; omitted fields are left as they
+/// are.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePropertyRequest {
+    #[validate(length(min = 1, message = "Title is required"))]
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub property_type: Option<PropertyType>,
+    pub listing_type: Option<ListingType>,
+    pub price: Option<Decimal>,
+    pub currency: Option<String>,
+    pub price_period: Option<PricePeriod>,
+    pub bedrooms: Option<i32>,
+    pub bathrooms: Option<i32>,
+    pub land_size_sqm: Option<Decimal>,
+    pub building_size_sqm: Option<Decimal>,
+    #[validate(length(min = 1, message = "Area is required"))]
+    pub area: Option<String>,
+    pub address: Option<String>,
+    pub latitude: Option<Decimal>,
+    pub longitude: Option<Decimal>,
+    pub year_built: Option<i32>,
+    pub features: Option<serde_json::Value>,
+    pub images: Option<serde_json::Value>,
+    pub thumbnail_url: Option<String>,
+    /// Replaces the property's amenities when given.
+    pub amenity_slugs: Option<Vec<String>>,
+}
+
+/// Paging for the inquiries and bookings on one of the owner's listings.
+#[derive(Debug, Deserialize)]
+pub struct OwnerListFilters {
+    pub status: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    /// `X-Next-Cursor` from the previous page; takes precedence over `page`.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PropertyStats {
+    pub property_id: Uuid,
+    pub is_active: bool,
+    pub listing_status: ListingStatus,
+    pub view_count: i32,
+    /// Users who have saved the listing.
+    pub saved_count: i64,
+    pub inquiry_count: i64,
+    /// Inquiries still in status `new`.
+    pub new_inquiry_count: i64,
+    /// Bookings per status, e.g. `{"confirmed": 3, "cancelled": 1}`.
+    pub bookings_by_status: BTreeMap<String, i64>,
+    /// Confirmed or checked-in bookings that have not ended yet.
+    pub upcoming_bookings: i64,
+    /// Total price of confirmed, checked-in and checked-out bookings, in the
+    /// listing's currency.
+    pub booked_revenue: Decimal,
+    pub currency: String,
 }
 
 // ── Inquiry DTOs ─────────────────────────────────────────────────────────
@@ -217,6 +488,23 @@ pub struct AmenityResponse {
     pub category: String,
 }
 
+impl From<shared::models::Amenity> for AmenityResponse {
+    fn from(amenity: shared::models::Amenity) -> Self {
+        Self {
+            id: amenity.id,
+            slug: amenity.slug,
+            name: amenity.name,
+            icon: amenity.icon,
+            category: amenity.category,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetPropertyAmenitiesRequest {
+    pub amenity_slugs: Vec<String>,
+}
+
 // ── Booking DTOs ────────────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize, Validate)]
@@ -227,7 +515,15 @@ pub struct CreateBookingRequest {
     #[validate(range(min = 1, message = "At least 1 guest required"))]
     pub num_guests: i32,
     pub special_requests: Option<String>,
-    pub duration_type: shared::models::RentalDurationType,
+    /// Restrict pricing to tiers of this duration type. When omitted the
+    /// pricing engine picks the best tier for the stay length.
+    #[serde(default)]
+    pub duration_type: Option<shared::models::RentalDurationType>,
+    /// Whether the guest is bringing pets.
+    #[serde(default)]
+    pub has_pets: bool,
+    /// When the guest expects to arrive on the check-in date.
+    pub expected_arrival_time: Option<chrono::NaiveTime>,
 }
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
@@ -235,6 +531,7 @@ pub struct BookingResponse {
     pub id: Uuid,
     pub property_id: Uuid,
     pub guest_id: Uuid,
+    pub pricing_tier_id: Option<Uuid>,
     pub check_in: chrono::NaiveDate,
     pub check_out: chrono::NaiveDate,
     pub num_guests: i32,
@@ -247,8 +544,51 @@ pub struct BookingResponse {
     pub duration_type: shared::models::RentalDurationType,
     pub duration_count: i32,
     pub status: shared::models::BookingStatus,
+    pub has_pets: bool,
+    pub expected_arrival_time: Option<chrono::NaiveTime>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub refund_amount: Option<Decimal>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Amounts in the requested `display_currency`; only present when one is
+    /// given.
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<BookingDisplayPrice>,
+}
+
+impl BookingResponse {
+    pub fn show_prices_in(&mut self, display: &DisplayCurrency) {
+        self.display = self.display_price(display);
+    }
+
+    fn display_price(&self, display: &DisplayCurrency) -> Option<BookingDisplayPrice> {
+        let convert = |amount: Decimal| display.convert(amount, &self.currency);
+        let convert_opt = |amount: Option<Decimal>| match amount {
+            Some(amount) => convert(amount).map(Some),
+            None => Some(None),
+        };
+        Some(BookingDisplayPrice {
+            currency: display.currency.clone(),
+            base_price: convert(self.base_price)?,
+            cleaning_fee: convert_opt(self.cleaning_fee)?,
+            service_fee: convert_opt(self.service_fee)?,
+            total_price: convert(self.total_price)?,
+            refund_amount: convert_opt(self.refund_amount)?,
+        })
+    }
+}
+
+/// Booking amounts converted for display; the booking is charged in its own
+/// `currency`.
+#[derive(Debug, Serialize)]
+pub struct BookingDisplayPrice {
+    pub currency: String,
+    pub base_price: Decimal,
+    pub cleaning_fee: Option<Decimal>,
+    pub service_fee: Option<Decimal>,
+    pub total_price: Decimal,
+    pub refund_amount: Option<Decimal>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -256,6 +596,26 @@ pub struct BookingFilters {
     pub status: Option<String>,
     pub page: Option<i64>,
     pub per_page: Option<i64>,
+    /// `X-Next-Cursor` from the previous page; takes precedence over `page`.
+    pub cursor: Option<String>,
+    /// ISO 4217 code to show amounts in, e.g. `EUR`.
+    pub display_currency: Option<String>,
+}
+
+// ── Payment DTOs ────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct StartPaymentRequest {
+    pub kind: PaymentKind,
+}
+
+/// A payment the guest is about to make, with the client secret their
+/// browser passes to the payment provider to complete it.
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentResponse {
+    #[serde(flatten)]
+    pub payment: Payment,
+    pub client_secret: Option<String>,
 }
 
 // ── Review DTOs ─────────────────────────────────────────────────────────
@@ -345,6 +705,55 @@ pub struct PricingTierResponse {
     pub is_active: bool,
 }
 
+// ── Quote DTOs ──────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct QuoteQuery {
+    pub check_in: chrono::NaiveDate,
+    pub check_out: chrono::NaiveDate,
+    pub guests: Option<i32>,
+    pub duration_type: Option<shared::models::RentalDurationType>,
+    /// ISO 4217 code to show the quote in, e.g. `EUR`.
+    pub display_currency: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuoteResponse {
+    #[serde(flatten)]
+    pub quote: PriceQuote,
+    /// The quote in the requested `display_currency`; only present when one
+    /// is given. The stay is charged in the quote's own `currency`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<QuoteDisplayPrice>,
+}
+
+impl QuoteResponse {
+    pub fn new(quote: PriceQuote, display: Option<&DisplayCurrency>) -> Self {
+        let display = display.and_then(|display| {
+            let convert = |amount: Decimal| display.convert(amount, &quote.currency);
+            Some(QuoteDisplayPrice {
+                currency: display.currency.clone(),
+                unit_price: convert(quote.unit_price)?,
+                base_price: convert(quote.base_price)?,
+                cleaning_fee: convert(quote.cleaning_fee)?,
+                service_fee: convert(quote.service_fee)?,
+                total_price: convert(quote.total_price)?,
+            })
+        });
+        Self { quote, display }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuoteDisplayPrice {
+    pub currency: String,
+    pub unit_price: Decimal,
+    pub base_price: Decimal,
+    pub cleaning_fee: Decimal,
+    pub service_fee: Decimal,
+    pub total_price: Decimal,
+}
+
 // ── Availability DTOs ───────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize)]
@@ -358,3 +767,85 @@ pub struct BlockedDateRange {
     pub start_date: chrono::NaiveDate,
     pub end_date: chrono::NaiveDate,
 }
+
+/// Body for creating or editing a blocked date range. Both dates are
+/// blocked (the range is inclusive).
+#[derive(Debug, Deserialize, Validate)]
+pub struct BlockedDateRequest {
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    #[validate(length(max = 255, message = "Reason must be at most 255 characters"))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CalendarImportRequest {
+    /// `http(s)://` or `webcal://` link to an iCalendar feed.
+    #[validate(url(message = "Invalid calendar URL"))]
+    pub url: String,
+    /// Name for the feed (e.g. "airbnb"); defaults to the URL.
+    #[validate(length(min = 1, max = 255, message = "Source must be 1-255 characters"))]
+    pub source: Option<String>,
+}
+
+// ── Conversation DTOs ───────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateConversationRequest {
+    pub property_id: Uuid,
+    /// Optional opening message sent together with the new conversation.
+    #[validate(length(min = 1, max = 5000, message = "Message must be 1-5000 characters"))]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ConversationSummary {
+    pub id: Uuid,
+    pub property_id: Option<Uuid>,
+    pub property_title: Option<String>,
+    pub property_slug: Option<String>,
+    pub other_participant_id: Uuid,
+    pub other_participant_name: String,
+    pub other_participant_avatar: Option<String>,
+    pub last_message: Option<String>,
+    pub last_message_at: Option<DateTime<Utc>>,
+    pub unread_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SendMessageRequest {
+    #[validate(length(min = 1, max = 5000, message = "Message must be 1-5000 characters"))]
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessageFilters {
+    /// Only return messages created strictly before this timestamp.
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// Event pushed to open chat streams.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatEvent {
+    Message {
+        message: shared::models::Message,
+    },
+    Read {
+        conversation_id: Uuid,
+        reader_id: Uuid,
+    },
+}
+
+impl ChatEvent {
+    pub fn conversation_id(&self) -> Uuid {
+        match self {
+            ChatEvent::Message { message } => message.conversation_id,
+            ChatEvent::Read {
+                conversation_id, ..
+            } => *conversation_id,
+        }
+    }
+}
@@ -10,4 +10,10 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/register", post(auth::register))
         .route("/login", post(auth::login))
         .route("/google", post(auth::google_login))
+        .route("/refresh", post(auth::refresh))
+        .route("/logout", post(auth::logout))
+        .route("/verify-email", post(auth::verify_email))
+        .route("/resend-verification", post(auth::resend_verification))
+        .route("/forgot-password", post(auth::forgot_password))
+        .route("/reset-password", post(auth::reset_password))
 }
@@ -2,7 +2,7 @@ use axum::routing::{get, post, put};
 use axum::Router;
 use std::sync::Arc;
 
-use crate::handlers::bookings;
+use crate::handlers::{bookings, payments};
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
@@ -11,4 +11,12 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/", get(bookings::list_my_bookings))
         .route("/{id}", get(bookings::get_booking))
         .route("/{id}/cancel", put(bookings::cancel_booking))
+        .route(
+            "/{id}/cancellation-preview",
+            get(bookings::preview_cancellation),
+        )
+        .route(
+            "/{id}/payments",
+            get(payments::list_payments).post(payments::start_payment),
+        )
 }
@@ -0,0 +1,20 @@
+use axum::routing::{get, put};
+use axum::Router;
+use std::sync::Arc;
+
+use crate::handlers::conversations;
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/",
+            get(conversations::list_conversations).post(conversations::create_conversation),
+        )
+        .route(
+            "/{id}/messages",
+            get(conversations::list_messages).post(conversations::send_message),
+        )
+        .route("/{id}/read", put(conversations::mark_read))
+        .route("/{id}/stream", get(conversations::stream_conversation))
+}
@@ -0,0 +1,39 @@
+use axum::routing::{get, post, put};
+use axum::Router;
+use std::sync::Arc;
+
+use crate::handlers::my_properties;
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/properties", get(my_properties::list_my_properties))
+        .route(
+            "/properties/{id}",
+            get(my_properties::get_my_property).put(my_properties::update_my_property),
+        )
+        .route(
+            "/properties/{id}/amenities",
+            put(my_properties::set_my_property_amenities),
+        )
+        .route(
+            "/properties/{id}/toggle-active",
+            put(my_properties::toggle_my_property_active),
+        )
+        .route(
+            "/properties/{id}/submit",
+            post(my_properties::submit_my_property),
+        )
+        .route(
+            "/properties/{id}/stats",
+            get(my_properties::get_my_property_stats),
+        )
+        .route(
+            "/properties/{id}/inquiries",
+            get(my_properties::list_my_property_inquiries),
+        )
+        .route(
+            "/properties/{id}/bookings",
+            get(my_properties::list_my_property_bookings),
+        )
+}
@@ -1,5 +1,8 @@
 pub mod auth;
 pub mod bookings;
+pub mod conversations;
+pub mod me;
+pub mod payments;
 pub mod properties;
 pub mod uploads;
 pub mod users;
@@ -24,7 +27,10 @@ pub fn create_router(state: Arc<AppState>) -> Router {
                 .nest("/auth", auth::routes())
                 .nest("/properties", properties::routes())
                 .nest("/users", users::routes())
+                .nest("/me", me::routes())
                 .nest("/bookings", bookings::routes())
+                .nest("/conversations", conversations::routes())
+                .nest("/payments", payments::routes())
                 .nest("/uploads", uploads::routes()),
         )
         // Serve uploaded files at /uploads/
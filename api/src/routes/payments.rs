@@ -0,0 +1,10 @@
+use axum::routing::post;
+use axum::Router;
+use std::sync::Arc;
+
+use crate::handlers::payments;
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/webhook", post(payments::webhook))
+}
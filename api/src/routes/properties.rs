@@ -1,8 +1,8 @@
-use axum::routing::{get, post};
+use axum::routing::{get, post, put};
 use axum::Router;
 use std::sync::Arc;
 
-use crate::handlers::{amenities, availability, properties, reviews};
+use crate::handlers::{amenities, availability, blocked_dates, calendar, properties, reviews};
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
@@ -22,4 +22,19 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/{slug}/availability", get(availability::get_availability))
         .route("/{slug}/rules", get(availability::get_property_rules))
         .route("/{slug}/pricing", get(availability::get_property_pricing))
+        .route("/{slug}/quote", get(availability::get_quote))
+        .route("/{slug}/calendar.ics", get(calendar::export_calendar))
+        .route("/{id}/calendar/import", post(calendar::import_calendar_url))
+        .route(
+            "/{id}/calendar/import/upload",
+            post(calendar::import_calendar_file),
+        )
+        .route(
+            "/{id}/blocked-dates",
+            get(blocked_dates::list_blocked_dates).post(blocked_dates::create_blocked_dates),
+        )
+        .route(
+            "/{id}/blocked-dates/{block_id}",
+            put(blocked_dates::update_blocked_dates).delete(blocked_dates::delete_blocked_dates),
+        )
 }
@@ -0,0 +1,9 @@
+-- =============================================================================
+-- Migration 007: Guest-declared booking details
+-- Stores what the guest declared at booking time so it can be checked against
+-- property_rules and shown to the host.
+-- =============================================================================
+
+ALTER TABLE bookings
+    ADD COLUMN has_pets BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN expected_arrival_time TIME;
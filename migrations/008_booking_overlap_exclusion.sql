@@ -0,0 +1,15 @@
+-- =============================================================================
+-- Migration 008: Database-level protection against double bookings
+-- Two active bookings for the same property may not share a night. Ranges are
+-- half-open ([check_in, check_out)), so back-to-back stays are allowed.
+-- =============================================================================
+
+CREATE EXTENSION IF NOT EXISTS btree_gist;
+
+ALTER TABLE bookings
+    ADD CONSTRAINT bookings_no_overlap
+    EXCLUDE USING gist (
+        property_id WITH =,
+        daterange(check_in, check_out) WITH &&
+    )
+    WHERE (status NOT IN ('cancelled', 'refunded'));
@@ -0,0 +1,9 @@
+-- =============================================================================
+-- Migration 009: Refund amount on cancelled bookings
+-- Records what the guest is owed under the property's cancellation policy at
+-- the moment the booking was cancelled.
+-- =============================================================================
+
+ALTER TABLE bookings
+    ADD COLUMN refund_amount DECIMAL(15, 2),
+    ADD CONSTRAINT bookings_refund_positive CHECK (refund_amount IS NULL OR refund_amount >= 0);
@@ -0,0 +1,22 @@
+-- =============================================================================
+-- Migration 010: Booking status history
+-- One row per status change, including the initial 'pending' status when a
+-- booking is created (from_status is NULL for that row).
+-- =============================================================================
+
+CREATE TABLE booking_status_history (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    from_status booking_status,
+    to_status booking_status NOT NULL,
+    changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_booking_status_history_booking ON booking_status_history (booking_id, created_at);
+
+-- Seed the history of existing bookings with their current status
+INSERT INTO booking_status_history (booking_id, from_status, to_status, reason, created_at)
+SELECT id, NULL, status, 'Recorded when status history was introduced', updated_at
+FROM bookings;
@@ -0,0 +1,13 @@
+-- =============================================================================
+-- Migration 011: Calendar import source on blocked dates
+-- Blocks imported from an external iCalendar feed record the feed they came
+-- from and the event UID, so re-importing a feed replaces exactly its own
+-- blocks. Blocks created by hand have a NULL source.
+-- =============================================================================
+
+ALTER TABLE blocked_dates
+    ADD COLUMN source VARCHAR(255),
+    ADD COLUMN external_uid VARCHAR(255);
+
+CREATE INDEX idx_blocked_dates_source ON blocked_dates (property_id, source)
+    WHERE source IS NOT NULL;
@@ -0,0 +1,17 @@
+-- =============================================================================
+-- Migration 012: Full-text property search
+-- A weighted tsvector over title (A), area (B), features (C) and description
+-- (D), kept up to date by Postgres, plus a trigram index on area so typo-
+-- tolerant matching on title and area can use an index.
+-- =============================================================================
+
+ALTER TABLE properties
+    ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('english', coalesce(area, '')), 'B') ||
+        setweight(jsonb_to_tsvector('english', coalesce(features, '[]'::jsonb), '["string"]'), 'C') ||
+        setweight(to_tsvector('english', coalesce(description, '')), 'D')
+    ) STORED;
+
+CREATE INDEX idx_properties_search_vector ON properties USING gin (search_vector);
+CREATE INDEX idx_properties_area_trgm ON properties USING gin (area gin_trgm_ops);
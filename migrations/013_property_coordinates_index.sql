@@ -0,0 +1,8 @@
+-- =============================================================================
+-- Migration 013: Property coordinates index
+-- Supports radius and map-viewport searches, which first narrow candidates to
+-- a latitude/longitude box before checking the exact distance.
+-- =============================================================================
+
+CREATE INDEX idx_properties_lat_lng ON properties (latitude, longitude)
+    WHERE latitude IS NOT NULL AND longitude IS NOT NULL;
@@ -0,0 +1,24 @@
+-- =============================================================================
+-- Migration 014: Refresh tokens and token revocation
+-- Access tokens carry the user's token_version; bumping it (deactivation, role
+-- or password change, "log out everywhere") invalidates every access token
+-- issued before. Refresh tokens are stored as SHA-256 hashes and rotated on
+-- every use; tokens descending from the same login share a family_id so a
+-- replayed token can revoke the whole chain.
+-- =============================================================================
+
+ALTER TABLE users ADD COLUMN token_version INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE refresh_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    family_id UUID NOT NULL,
+    token_hash TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT refresh_tokens_hash_unique UNIQUE (token_hash)
+);
+
+CREATE INDEX idx_refresh_tokens_user ON refresh_tokens (user_id) WHERE revoked_at IS NULL;
+CREATE INDEX idx_refresh_tokens_family ON refresh_tokens (family_id);
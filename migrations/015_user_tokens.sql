@@ -0,0 +1,23 @@
+-- =============================================================================
+-- Migration 015: Email verification and password reset tokens
+-- Single-use tokens sent by email. Only a SHA-256 hash of each token is
+-- stored; used_at is set when the token is redeemed.
+-- =============================================================================
+
+CREATE TABLE user_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR(32) NOT NULL CHECK (purpose IN ('email_verification', 'password_reset')),
+    token_hash TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT user_tokens_hash_unique UNIQUE (token_hash)
+);
+
+CREATE INDEX idx_user_tokens_user ON user_tokens (user_id, purpose) WHERE used_at IS NULL;
+
+-- Bookings and listings now require a verified email. Accounts created before
+-- verification existed never received a verification email, so they are
+-- treated as verified rather than locked out.
+UPDATE users SET email_verified = true WHERE email_verified = false;
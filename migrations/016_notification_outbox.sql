@@ -0,0 +1,23 @@
+-- =============================================================================
+-- Migration 016: Notification outbox
+-- Emails are written here in the same transaction as the change they report
+-- (a booking, an inquiry, a published review) and delivered by a background
+-- worker, so a notification is never lost to a crash or an SMTP outage and is
+-- never sent for a change that was rolled back.
+-- =============================================================================
+
+CREATE TABLE notification_outbox (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    kind VARCHAR(64) NOT NULL,
+    recipient VARCHAR(255) NOT NULL,
+    context JSONB NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sent', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notification_outbox_due ON notification_outbox (next_attempt_at)
+    WHERE status = 'pending';
@@ -0,0 +1,18 @@
+-- =============================================================================
+-- Migration 017: Login attempts
+-- Every password login on the public and admin APIs is logged here. Recent
+-- failures per email and per client IP decide whether further attempts are
+-- locked out, with the lockout doubling on each failure past the limit.
+-- =============================================================================
+
+CREATE TABLE login_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    email VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45),
+    succeeded BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_attempts_email ON login_attempts (email, created_at DESC);
+CREATE INDEX idx_login_attempts_ip ON login_attempts (ip_address, created_at DESC)
+    WHERE NOT succeeded;
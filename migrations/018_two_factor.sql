@@ -0,0 +1,34 @@
+-- =============================================================================
+-- Migration 018: TOTP two-factor authentication
+-- Admin-portal users can enrol an authenticator app (RFC 6238). totp_secret
+-- holds the base32 secret, pending until totp_enabled is set by confirming a
+-- first code; totp_last_used_step stops a code being replayed. Recovery codes
+-- and login challenges store only SHA-256 hashes.
+-- =============================================================================
+
+ALTER TABLE users
+    ADD COLUMN totp_secret TEXT,
+    ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN totp_last_used_step BIGINT;
+
+CREATE TABLE recovery_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_recovery_codes_user ON recovery_codes (user_id) WHERE used_at IS NULL;
+
+-- Issued after a correct password when a second step is needed: entering a
+-- code, or enrolling when two-factor authentication is mandatory.
+CREATE TABLE two_factor_challenges (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT two_factor_challenges_hash_unique UNIQUE (token_hash)
+);
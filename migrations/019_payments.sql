@@ -0,0 +1,75 @@
+-- =============================================================================
+-- Migration 019: Payments and refunds
+-- A booking is paid either in full or as a deposit followed by the balance.
+-- Each payment is a payment intent at the provider (Stripe); the provider's
+-- webhook reports when it succeeds, which confirms a pending booking. Refunds
+-- due on cancellation are issued against the booking's successful payments.
+-- =============================================================================
+
+CREATE TYPE payment_kind AS ENUM (
+    'deposit',
+    'balance',
+    'full'
+);
+
+CREATE TYPE payment_status AS ENUM (
+    'requires_payment',
+    'succeeded',
+    'failed'
+);
+
+CREATE TYPE refund_status AS ENUM (
+    'pending',
+    'succeeded',
+    'failed'
+);
+
+CREATE TABLE payments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    kind payment_kind NOT NULL,
+    amount DECIMAL(15, 2) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    provider VARCHAR(32) NOT NULL,
+    -- Set once the provider has created the payment intent.
+    provider_payment_id VARCHAR(255),
+    client_secret TEXT,
+    status payment_status NOT NULL DEFAULT 'requires_payment',
+    failure_reason TEXT,
+    captured_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT payments_amount_positive CHECK (amount > 0),
+    CONSTRAINT payments_provider_payment_unique UNIQUE (provider, provider_payment_id)
+);
+
+CREATE INDEX idx_payments_booking ON payments (booking_id, created_at);
+
+CREATE TRIGGER trigger_payments_updated_at
+    BEFORE UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TABLE refunds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    payment_id UUID NOT NULL REFERENCES payments(id) ON DELETE CASCADE,
+    amount DECIMAL(15, 2) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    provider VARCHAR(32) NOT NULL,
+    provider_refund_id VARCHAR(255),
+    status refund_status NOT NULL DEFAULT 'pending',
+    failure_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT refunds_amount_positive CHECK (amount > 0),
+    CONSTRAINT refunds_provider_refund_unique UNIQUE (provider, provider_refund_id)
+);
+
+CREATE INDEX idx_refunds_booking ON refunds (booking_id, created_at);
+CREATE INDEX idx_refunds_payment ON refunds (payment_id);
+
+CREATE TRIGGER trigger_refunds_updated_at
+    BEFORE UPDATE ON refunds
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
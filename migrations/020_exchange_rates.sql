@@ -0,0 +1,91 @@
+-- =============================================================================
+-- Migration 020: Exchange rates
+-- Listings are priced in their own currency (many Bali rentals in IDR). To
+-- filter and sort by price across currencies, and to show prices in the
+-- visitor's currency, every listing currency needs a rate against the base
+-- currency (USD). Admins keep the rates current by hand or by file import.
+-- =============================================================================
+
+-- Listing currencies were free text; store them as upper-case codes. Each
+-- one needs a rate (see the end of this migration) so every listing can be
+-- converted.
+UPDATE properties SET currency = UPPER(TRIM(currency)) WHERE currency <> UPPER(TRIM(currency));
+
+-- A code that is not three letters cannot have a rate. Stop with the
+-- offending values, before anything else changes, rather than with a bare
+-- foreign key error at the end.
+DO $$
+DECLARE
+    invalid TEXT;
+BEGIN
+    SELECT string_agg(DISTINCT quote_literal(currency), ', ') INTO invalid
+    FROM properties
+    WHERE currency !~ '^[A-Z]{3}$';
+
+    IF invalid IS NOT NULL THEN
+        RAISE EXCEPTION 'Listings use currencies that are not ISO 4217 codes: %', invalid
+            USING HINT = 'Correct properties.currency for these listings, then apply this migration again.';
+    END IF;
+END $$;
+
+CREATE TABLE exchange_rates (
+    -- ISO 4217 code.
+    currency VARCHAR(3) PRIMARY KEY,
+    -- Units of this currency per one unit of the base currency.
+    rate DECIMAL(20, 8) NOT NULL,
+    -- Where the rate came from: 'seed', 'placeholder' (see below), 'manual' or
+    -- 'import'.
+    source VARCHAR(16) NOT NULL DEFAULT 'manual',
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT exchange_rates_currency_format CHECK (currency ~ '^[A-Z]{3}$'),
+    CONSTRAINT exchange_rates_rate_positive CHECK (rate > 0),
+    CONSTRAINT exchange_rates_base_is_one CHECK (currency <> 'USD' OR rate = 1)
+);
+
+CREATE TRIGGER trigger_exchange_rates_updated_at
+    BEFORE UPDATE ON exchange_rates
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Starting values only; admins are expected to replace them.
+INSERT INTO exchange_rates (currency, rate, source) VALUES
+    ('USD', 1, 'seed'),
+    ('IDR', 16250, 'seed'),
+    ('EUR', 0.92, 'seed'),
+    ('GBP', 0.79, 'seed'),
+    ('AUD', 1.52, 'seed'),
+    ('NZD', 1.66, 'seed'),
+    ('SGD', 1.34, 'seed'),
+    ('MYR', 4.45, 'seed'),
+    ('JPY', 150, 'seed'),
+    ('CNY', 7.20, 'seed'),
+    ('CAD', 1.37, 'seed'),
+    ('CHF', 0.88, 'seed'),
+    ('HKD', 7.80, 'seed');
+
+-- Other listing currencies get a placeholder rate of 1 so existing listings
+-- stay valid. Admins must replace it; until then these listings are
+-- compared as if priced in USD.
+INSERT INTO exchange_rates (currency, rate, source)
+SELECT DISTINCT p.currency, 1, 'placeholder'
+FROM properties p
+WHERE NOT EXISTS (SELECT 1 FROM exchange_rates er WHERE er.currency = p.currency);
+
+DO $$
+DECLARE
+    placeholders TEXT;
+BEGIN
+    SELECT string_agg(currency, ', ' ORDER BY currency) INTO placeholders
+    FROM exchange_rates
+    WHERE source = 'placeholder';
+
+    IF placeholders IS NOT NULL THEN
+        RAISE WARNING 'Added placeholder exchange rates of 1 for %; set real rates in the admin portal', placeholders;
+    END IF;
+END $$;
+
+ALTER TABLE properties
+    ADD CONSTRAINT properties_currency_fkey
+    FOREIGN KEY (currency) REFERENCES exchange_rates(currency);
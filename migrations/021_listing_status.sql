@@ -0,0 +1,59 @@
+-- =============================================================================
+-- Migration 021: Listing moderation
+-- Listings move through explicit review states instead of a bare is_active
+-- flag: owners keep drafts, submit them for review, and an admin approves or
+-- rejects them with a reason. Only approved listings are public, so is_active
+-- becomes a read-only column derived from the status.
+-- =============================================================================
+
+CREATE TYPE listing_status AS ENUM (
+    'draft',
+    'pending_review',
+    'approved',
+    'rejected',
+    'archived'
+);
+
+ALTER TABLE properties
+    ADD COLUMN listing_status listing_status NOT NULL DEFAULT 'pending_review',
+    -- The latest moderation decision, shown to the owner.
+    ADD COLUMN review_reason TEXT,
+    ADD COLUMN reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN reviewed_at TIMESTAMPTZ;
+
+-- Live listings were approved in all but name. Inactive ones were either
+-- waiting for a review that never came or taken down by an admin; queue them
+-- so an admin makes the call.
+UPDATE properties
+SET listing_status = CASE WHEN is_active THEN 'approved' ELSE 'pending_review' END::listing_status;
+
+-- Dropping the column drops the indexes on it; they are recreated below.
+ALTER TABLE properties DROP COLUMN is_active;
+ALTER TABLE properties
+    ADD COLUMN is_active BOOLEAN NOT NULL
+    GENERATED ALWAYS AS (listing_status = 'approved') STORED;
+
+CREATE INDEX idx_properties_is_active ON properties (is_active);
+CREATE INDEX idx_properties_active_type ON properties (is_active, property_type);
+CREATE INDEX idx_properties_active_listing ON properties (is_active, listing_type);
+CREATE INDEX idx_properties_active_area ON properties (is_active, area);
+CREATE INDEX idx_properties_active_featured ON properties (is_active, is_featured) WHERE is_featured = true;
+CREATE INDEX idx_properties_listing_status ON properties (listing_status);
+
+-- One row per status change, like booking_status_history. from_status is
+-- NULL for the status a listing was created with.
+CREATE TABLE listing_status_history (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    property_id UUID NOT NULL REFERENCES properties(id) ON DELETE CASCADE,
+    from_status listing_status,
+    to_status listing_status NOT NULL,
+    changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_listing_status_history_property ON listing_status_history (property_id, created_at);
+
+INSERT INTO listing_status_history (property_id, from_status, to_status, reason, created_at)
+SELECT id, NULL, listing_status, 'Recorded when listing moderation was introduced', updated_at
+FROM properties;
@@ -0,0 +1,20 @@
+-- =============================================================================
+-- Migration 022: Session audiences
+-- The public API and the admin portal sign tokens with the same secret and
+-- share this table, so a session from the public API's password-only login
+-- could be refreshed by the admin portal without its second factor. Each
+-- refresh token now records the service that issued it ('api' or 'admin'),
+-- and only that service will rotate it.
+-- =============================================================================
+
+ALTER TABLE refresh_tokens
+    ADD COLUMN audience VARCHAR(10) NOT NULL DEFAULT 'api'
+        CHECK (audience IN ('api', 'admin'));
+
+-- Existing sessions cannot be told apart, so admin-portal users sign in again.
+UPDATE refresh_tokens
+SET revoked_at = NOW()
+WHERE revoked_at IS NULL
+  AND user_id IN (
+      SELECT id FROM users WHERE role IN ('super_admin', 'admin', 'operational')
+  );
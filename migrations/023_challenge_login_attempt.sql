@@ -0,0 +1,10 @@
+-- =============================================================================
+-- Migration 023: Login attempt behind each two-factor challenge
+-- The password step's login attempt is only marked successful once the
+-- second step completes. Without a link from the challenge back to it, every
+-- two-factor login left a failed attempt behind that counted towards the
+-- client IP's lockout.
+-- =============================================================================
+
+ALTER TABLE two_factor_challenges
+    ADD COLUMN login_attempt_id UUID REFERENCES login_attempts(id) ON DELETE SET NULL;
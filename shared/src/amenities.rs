@@ -0,0 +1,92 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::Amenity;
+
+/// Whether `slug` is lowercase letters, digits and single hyphens, like the
+/// seeded amenities (`private-pool`, `ac`).
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.split('-').all(|part| {
+            !part.is_empty()
+                && part
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        })
+}
+
+/// Replace a property's amenities with the ones named by `slugs`.
+///
+/// Duplicates are ignored and an empty list clears the property's amenities.
+/// Unknown slugs are rejected as a whole, so a typo never silently drops an
+/// amenity. Run it inside the caller's transaction when the property is
+/// written in the same request. Returns the property's amenities in catalogue
+/// order.
+pub async fn set_property_amenities(
+    conn: &mut PgConnection,
+    property_id: Uuid,
+    slugs: &[String],
+) -> Result<Vec<Amenity>, AppError> {
+    let mut slugs: Vec<&str> = slugs.iter().map(|s| s.trim()).collect();
+    slugs.sort_unstable();
+    slugs.dedup();
+
+    let amenities: Vec<Amenity> =
+        sqlx::query_as("SELECT * FROM amenities WHERE slug = ANY($1) ORDER BY sort_order, name")
+            .bind(&slugs)
+            .fetch_all(&mut *conn)
+            .await?;
+
+    let unknown: Vec<&str> = slugs
+        .iter()
+        .copied()
+        .filter(|slug| !amenities.iter().any(|a| a.slug == *slug))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(AppError::BadRequest(format!(
+            "Unknown amenities: {}",
+            unknown.join(", ")
+        )));
+    }
+
+    sqlx::query("DELETE FROM property_amenities WHERE property_id = $1")
+        .bind(property_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let ids: Vec<Uuid> = amenities.iter().map(|a| a.id).collect();
+    sqlx::query(
+        r#"INSERT INTO property_amenities (property_id, amenity_id)
+           SELECT $1, UNNEST($2::uuid[])"#,
+    )
+    .bind(property_id)
+    .bind(&ids)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(amenities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_validation() {
+        for slug in ["pool", "private-pool", "24h-security", "ac"] {
+            assert!(is_valid_slug(slug), "{slug}");
+        }
+        for slug in [
+            "",
+            "Pool",
+            "private pool",
+            "-pool",
+            "pool-",
+            "private--pool",
+            "wi_fi",
+        ] {
+            assert!(!is_valid_slug(slug), "{slug}");
+        }
+    }
+}
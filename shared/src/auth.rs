@@ -1,10 +1,23 @@
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
     Argon2,
 };
+use axum::extract::FromRequestParts;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use chrono::Utc;
+use http::header::AUTHORIZATION;
+use http::request::Parts;
+use http::HeaderMap;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::marker::PhantomData;
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::errors::AppError;
@@ -21,17 +34,66 @@ pub struct Claims {
     pub role: String,
     /// Expiration timestamp (seconds since UNIX epoch).
     pub exp: usize,
+    /// The user's `token_version` when the token was issued. Tokens from an
+    /// older version have been revoked.
+    #[serde(default)]
+    pub ver: i32,
+    /// The service the token was issued by and is only valid for.
+    pub aud: String,
 }
 
-/// Create a signed JWT that expires in 24 hours.
+impl Claims {
+    /// The role the token was issued for, if it is one this build knows.
+    pub fn user_role(&self) -> Option<UserRole> {
+        self.role.parse().ok()
+    }
+
+    /// Whether the token's role holds `permission`. Unknown roles hold none.
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.user_role()
+            .is_some_and(|role| role.has_permission(permission))
+    }
+}
+
+/// Lifetime of an access token. Sessions outlive it through refresh tokens.
+/// Kept at the original 24 hours while the web clients still sign in again
+/// instead of refreshing; revocation does not wait for it to run out, as
+/// every request checks [`ensure_token_current`].
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 24 * 60;
+
+/// The service a session belongs to. Both services sign with the same
+/// secret, so tokens name their audience: a session from the public API's
+/// password-only login must not open the admin portal, which asks for a
+/// second factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// The public API (`/api/v1`).
+    Api,
+    /// The admin portal API (`/api/admin`).
+    Admin,
+}
+
+impl Audience {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Audience::Api => "api",
+            Audience::Admin => "admin",
+        }
+    }
+}
+
+/// Create a signed JWT for `audience` that expires in
+/// [`ACCESS_TOKEN_TTL_MINUTES`].
 pub fn create_token(
     user_id: Uuid,
     email: &str,
     role: &UserRole,
+    token_version: i32,
+    audience: Audience,
     secret: &str,
 ) -> Result<String, AppError> {
     let expiration = Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
+        .checked_add_signed(chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES))
         .expect("valid timestamp")
         .timestamp() as usize;
 
@@ -43,6 +105,8 @@ pub fn create_token(
             .and_then(|v| v.as_str().map(String::from))
             .unwrap_or_else(|| format!("{:?}", role)),
         exp: expiration,
+        ver: token_version,
+        aud: audience.as_str().to_string(),
     };
 
     encode(
@@ -53,18 +117,245 @@ pub fn create_token(
     .map_err(|e| AppError::Internal(format!("Failed to create token: {e}")))
 }
 
-/// Verify a JWT and return the embedded claims.
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims, AppError> {
+/// Verify a JWT issued for `audience` and return the embedded claims.
+pub fn verify_token(token: &str, secret: &str, audience: Audience) -> Result<Claims, AppError> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[audience.as_str()]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+
     let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
+        &validation,
     )
     .map_err(|e| AppError::Unauthorized(format!("Invalid token: {e}")))?;
 
     Ok(token_data.claims)
 }
 
+/// Reject claims whose user has been deactivated or deleted, or whose
+/// `token_version` has since been bumped. A valid signature alone is not
+/// enough: this is what makes revocation take effect before a token expires.
+pub async fn ensure_token_current(pool: &PgPool, claims: &Claims) -> Result<(), AppError> {
+    let user_id: Uuid = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Unauthorized("Invalid token subject".to_string()))?;
+
+    let row: Option<(bool, i32)> =
+        sqlx::query_as("SELECT is_active, token_version FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    match row {
+        Some((true, version)) if version == claims.ver => Ok(()),
+        Some((false, _)) => Err(AppError::Unauthorized("Account is deactivated".to_string())),
+        _ => Err(AppError::Unauthorized("Token has been revoked".to_string())),
+    }
+}
+
+/// Reject users who have not confirmed their email address yet.
+pub async fn ensure_email_verified(pool: &PgPool, user_id: Uuid) -> Result<(), AppError> {
+    let verified: bool = sqlx::query_scalar("SELECT email_verified FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(false);
+
+    if !verified {
+        return Err(AppError::Forbidden(
+            "Please verify your email address first".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Permissions
+// ---------------------------------------------------------------------------
+
+/// Something a role may be allowed to do. Handlers ask for a permission, not
+/// a role; which roles hold it is decided in [`Permission::roles`] alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Sign in to the admin portal and use its everyday endpoints.
+    AdminPortal,
+    /// Approve and reject listings.
+    ModerateListings,
+    /// List, create, edit and deactivate users.
+    ManageUsers,
+    /// Delete (archive) properties.
+    DeleteProperties,
+    /// Delete amenities, removing them from every property.
+    DeleteAmenities,
+    /// Add, change, delete and import exchange rates.
+    ManageExchangeRates,
+    /// Remove another user's two-factor authentication.
+    ResetTwoFactor,
+    /// Publish listings without admin review.
+    PublishWithoutReview,
+}
+
+impl Permission {
+    /// The roles holding this permission. This is the whole access policy:
+    /// adding a role means adding it to the lists it belongs in here.
+    pub fn roles(self) -> &'static [UserRole] {
+        use UserRole::*;
+        match self {
+            Permission::AdminPortal => &[SuperAdmin, Admin, Operational],
+            Permission::ModerateListings => &[SuperAdmin, Admin, Operational],
+            Permission::ManageUsers => &[SuperAdmin, Admin],
+            Permission::DeleteProperties => &[SuperAdmin, Admin],
+            Permission::DeleteAmenities => &[SuperAdmin, Admin],
+            Permission::ManageExchangeRates => &[SuperAdmin, Admin],
+            Permission::ResetTwoFactor => &[SuperAdmin],
+            Permission::PublishWithoutReview => &[SuperAdmin, Admin, Agent],
+        }
+    }
+
+    fn denied_message(self) -> &'static str {
+        match self {
+            Permission::AdminPortal => "Admin portal access required",
+            Permission::ModerateListings => "You are not allowed to moderate listings",
+            Permission::ManageUsers => "You are not allowed to manage users",
+            Permission::DeleteProperties => "You are not allowed to delete properties",
+            Permission::DeleteAmenities => "You are not allowed to delete amenities",
+            Permission::ManageExchangeRates => "You are not allowed to manage exchange rates",
+            Permission::ResetTwoFactor => "You are not allowed to reset two-factor authentication",
+            Permission::PublishWithoutReview => "Your listings need an admin's review",
+        }
+    }
+}
+
+/// Names a [`Permission`] at the type level, for [`Require`].
+pub trait RequiredPermission: Send + Sync + 'static {
+    const PERMISSION: Permission;
+}
+
+macro_rules! permission_markers {
+    ($($name:ident),* $(,)?) => {
+        /// One marker type per [`Permission`], e.g. `Require<perm::ManageUsers>`.
+        pub mod perm {
+            $(
+                #[derive(Debug, Clone, Copy)]
+                pub struct $name;
+
+                impl super::RequiredPermission for $name {
+                    const PERMISSION: super::Permission = super::Permission::$name;
+                }
+            )*
+        }
+    };
+}
+
+permission_markers!(
+    AdminPortal,
+    ModerateListings,
+    ManageUsers,
+    DeleteProperties,
+    DeleteAmenities,
+    ManageExchangeRates,
+    ResetTwoFactor,
+    PublishWithoutReview,
+);
+
+/// Application state [`Require`] can check bearer tokens against.
+pub trait TokenAuthority: Send + Sync {
+    fn jwt_secret(&self) -> &str;
+    fn pool(&self) -> &PgPool;
+    /// The audience tokens must have been issued for.
+    fn audience(&self) -> Audience;
+}
+
+/// Verify the bearer token in `headers` and check it has not been revoked.
+pub async fn authenticate(
+    headers: &HeaderMap,
+    authority: &impl TokenAuthority,
+) -> Result<Claims, AppError> {
+    let auth_header = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| AppError::Unauthorized("Missing Authorization header".to_string()))?;
+
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| AppError::Unauthorized("Invalid Authorization header format".to_string()))?;
+
+    let claims = verify_token(token, authority.jwt_secret(), authority.audience())?;
+    ensure_token_current(authority.pool(), &claims).await?;
+
+    Ok(claims)
+}
+
+/// Extractor that admits callers whose role holds the permission `P`.
+///
+/// Claims already verified by an auth middleware (placed in the request
+/// extensions) are used as they are; otherwise the bearer token is verified
+/// here. A missing or invalid token is a 401; a valid token whose role lacks
+/// the permission is a 403.
+#[derive(Debug, Clone)]
+pub struct Require<P: RequiredPermission> {
+    pub claims: Claims,
+    pub role: UserRole,
+    _permission: PhantomData<P>,
+}
+
+impl<P: RequiredPermission> Require<P> {
+    /// Check `claims` against `P`.
+    pub fn check(claims: Claims) -> Result<Self, AppError> {
+        let role = claims
+            .user_role()
+            .ok_or_else(|| AppError::Unauthorized(format!("Unknown role: {}", claims.role)))?;
+
+        if !role.has_permission(P::PERMISSION) {
+            return Err(AppError::Forbidden(
+                P::PERMISSION.denied_message().to_string(),
+            ));
+        }
+
+        Ok(Require {
+            claims,
+            role,
+            _permission: PhantomData,
+        })
+    }
+}
+
+impl<P, S> FromRequestParts<Arc<S>> for Require<P>
+where
+    P: RequiredPermission,
+    S: TokenAuthority,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<S>,
+    ) -> Result<Self, Self::Rejection> {
+        let claims = match parts.extensions.get::<Claims>() {
+            Some(claims) => claims.clone(),
+            None => authenticate(&parts.headers, state.as_ref()).await?,
+        };
+
+        Self::check(claims)
+    }
+}
+
+/// A random 256-bit token, URL-safe, for refresh tokens and emailed links.
+pub fn generate_secret_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The form a [`generate_secret_token`] token is stored in. The tokens are
+/// high-entropy random values, so a plain SHA-256 is enough to keep a
+/// database leak from handing out live credentials.
+pub fn hash_secret_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
 /// Hash a plaintext password using Argon2id.
 pub fn hash_password(password: &str) -> Result<String, AppError> {
     let salt = SaltString::generate(&mut OsRng);
@@ -105,16 +396,172 @@ mod tests {
         let email = "test@example.com";
         let role = UserRole::Agent;
 
-        let token = create_token(user_id, email, &role, secret).unwrap();
-        let claims = verify_token(&token, secret).unwrap();
+        let token = create_token(user_id, email, &role, 3, Audience::Api, secret).unwrap();
+        let claims = verify_token(&token, secret, Audience::Api).unwrap();
 
         assert_eq!(claims.sub, user_id.to_string());
         assert_eq!(claims.email, email);
+        assert_eq!(claims.ver, 3);
+        assert_eq!(claims.aud, "api");
+    }
+
+    #[test]
+    fn test_token_is_only_valid_for_its_audience() {
+        let secret = "test-jwt-secret-key";
+        let role = UserRole::SuperAdmin;
+
+        let token = create_token(Uuid::new_v4(), "a@b.c", &role, 0, Audience::Api, secret).unwrap();
+        assert!(matches!(
+            verify_token(&token, secret, Audience::Admin),
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // Tokens from before audiences were introduced have none.
+        let legacy = encode(
+            &Header::default(),
+            &serde_json::json!({
+                "sub": Uuid::new_v4().to_string(),
+                "email": "a@b.c",
+                "role": "super_admin",
+                "exp": usize::MAX,
+            }),
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap();
+        assert!(verify_token(&legacy, secret, Audience::Admin).is_err());
+    }
+
+    #[test]
+    fn test_secret_tokens_are_unique_and_hashed() {
+        let a = generate_secret_token();
+        let b = generate_secret_token();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 43);
+
+        assert_eq!(hash_secret_token(&a), hash_secret_token(&a));
+        assert_ne!(hash_secret_token(&a), hash_secret_token(&b));
+        assert_eq!(hash_secret_token(&a).len(), 64);
     }
 
     #[test]
     fn test_invalid_token() {
-        let result = verify_token("invalid.token.here", "secret");
+        let result = verify_token("invalid.token.here", "secret", Audience::Api);
         assert!(result.is_err());
     }
+
+    fn claims(role: &str) -> Claims {
+        Claims {
+            sub: Uuid::new_v4().to_string(),
+            email: "test@example.com".to_string(),
+            role: role.to_string(),
+            exp: usize::MAX,
+            ver: 0,
+            aud: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_policy_table() {
+        use UserRole::*;
+        let holders = |permission: Permission| {
+            [SuperAdmin, Admin, Operational, Agent, User]
+                .into_iter()
+                .filter(|role| role.has_permission(permission))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            holders(Permission::AdminPortal),
+            [SuperAdmin, Admin, Operational]
+        );
+        assert_eq!(holders(Permission::ManageUsers), [SuperAdmin, Admin]);
+        assert_eq!(holders(Permission::ResetTwoFactor), [SuperAdmin]);
+        assert_eq!(
+            holders(Permission::PublishWithoutReview),
+            [SuperAdmin, Admin, Agent]
+        );
+        assert!(holders(Permission::DeleteProperties)
+            .iter()
+            .all(|role| role.can_delete_properties()));
+    }
+
+    #[test]
+    fn test_require_checks_permission() {
+        let admin = Require::<perm::ManageUsers>::check(claims("admin")).unwrap();
+        assert_eq!(admin.role, UserRole::Admin);
+
+        assert!(matches!(
+            Require::<perm::ManageUsers>::check(claims("operational")),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            Require::<perm::AdminPortal>::check(claims("guest")),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(claims("Agent").has_permission(Permission::PublishWithoutReview));
+        assert!(!claims("user").has_permission(Permission::PublishWithoutReview));
+    }
+
+    struct TestAuthority(PgPool);
+
+    impl TokenAuthority for TestAuthority {
+        fn jwt_secret(&self) -> &str {
+            "test-jwt-secret-key"
+        }
+
+        fn pool(&self) -> &PgPool {
+            &self.0
+        }
+
+        fn audience(&self) -> Audience {
+            Audience::Admin
+        }
+    }
+
+    #[tokio::test]
+    async fn test_require_extractor() {
+        // Never connects: every request below is settled before a query.
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let state = Arc::new(TestAuthority(pool));
+
+        let (mut parts, _) = http::Request::new(()).into_parts();
+        assert!(matches!(
+            Require::<perm::AdminPortal>::from_request_parts(&mut parts, &state).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // Claims left by an auth middleware are trusted.
+        parts.extensions.insert(claims("super_admin"));
+        let require = Require::<perm::ResetTwoFactor>::from_request_parts(&mut parts, &state)
+            .await
+            .unwrap();
+        assert_eq!(require.role, UserRole::SuperAdmin);
+
+        parts.extensions.insert(claims("agent"));
+        assert!(matches!(
+            Require::<perm::AdminPortal>::from_request_parts(&mut parts, &state).await,
+            Err(AppError::Forbidden(_))
+        ));
+
+        // A super admin's token from the public API's password-only login
+        // does not open the admin portal.
+        let token = create_token(
+            Uuid::new_v4(),
+            "admin@example.com",
+            &UserRole::SuperAdmin,
+            0,
+            Audience::Api,
+            state.jwt_secret(),
+        )
+        .unwrap();
+        let request = http::Request::builder()
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(())
+            .unwrap();
+        let (mut parts, _) = request.into_parts();
+        assert!(matches!(
+            Require::<perm::AdminPortal>::from_request_parts(&mut parts, &state).await,
+            Err(AppError::Unauthorized(_))
+        ));
+    }
 }
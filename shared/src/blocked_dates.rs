@@ -0,0 +1,324 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::calendar::CalendarEvent;
+use crate::errors::AppError;
+use crate::models::BlockedDate;
+
+/// The result of merging a new range into a property's existing blocks.
+#[derive(Debug, PartialEq)]
+pub struct MergePlan {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Existing blocks that overlap or touch the new range and are replaced
+    /// by the merged one.
+    pub absorbed: Vec<Uuid>,
+}
+
+/// Whether two inclusive date ranges overlap or sit next to each other
+/// (e.g. 1-5 and 6-9).
+fn touches(a: (NaiveDate, NaiveDate), b: (NaiveDate, NaiveDate)) -> bool {
+    a.0 <= b.1 + chrono::Days::new(1) && b.0 <= a.1 + chrono::Days::new(1)
+}
+
+/// Merge `start..=end` with every existing block it overlaps or touches,
+/// repeating until the merged range touches nothing else.
+pub fn plan_merge(existing: &[BlockedDate], start: NaiveDate, end: NaiveDate) -> MergePlan {
+    let mut plan = MergePlan {
+        start_date: start,
+        end_date: end,
+        absorbed: Vec::new(),
+    };
+
+    loop {
+        let mut grew = false;
+        for block in existing {
+            if plan.absorbed.contains(&block.id)
+                || !touches(
+                    (plan.start_date, plan.end_date),
+                    (block.start_date, block.end_date),
+                )
+            {
+                continue;
+            }
+            plan.start_date = plan.start_date.min(block.start_date);
+            plan.end_date = plan.end_date.max(block.end_date);
+            plan.absorbed.push(block.id);
+            grew = true;
+        }
+        if !grew {
+            return plan;
+        }
+    }
+}
+
+/// Block `start..=end` on a property, merging it with any overlapping or
+/// adjacent blocks created by hand.
+///
+/// When `replacing` is given, that block is removed first, which is how an
+/// existing block is edited. Ranges that collide with a confirmed or
+/// checked-in booking are rejected with [`AppError::Conflict`].
+pub async fn save_blocked_range(
+    pool: &PgPool,
+    property_id: Uuid,
+    start: NaiveDate,
+    end: NaiveDate,
+    reason: Option<&str>,
+    replacing: Option<Uuid>,
+) -> Result<BlockedDate, AppError> {
+    if end < start {
+        return Err(AppError::BadRequest(
+            "End date must not be before start date".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Serialise calendar edits per property so concurrent requests cannot
+    // both miss each other's blocks when merging.
+    sqlx::query("SELECT id FROM properties WHERE id = $1 FOR NO KEY UPDATE")
+        .bind(property_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    // Imported blocks belong to their feed and are replaced on the next
+    // import, so only blocks created by hand can be edited or merged.
+    if let Some(block_id) = replacing {
+        sqlx::query(
+            "DELETE FROM blocked_dates WHERE id = $1 AND property_id = $2 AND source IS NULL RETURNING id",
+        )
+            .bind(block_id)
+            .bind(property_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Blocked date range not found".to_string()))?;
+    }
+
+    let booked: Option<(NaiveDate, NaiveDate)> = sqlx::query_as(
+        r#"SELECT check_in, check_out FROM bookings
+           WHERE property_id = $1
+           AND status IN ('confirmed', 'checked_in')
+           AND check_in <= $3 AND check_out > $2
+           ORDER BY check_in
+           LIMIT 1"#,
+    )
+    .bind(property_id)
+    .bind(start)
+    .bind(end)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some((check_in, check_out)) = booked {
+        return Err(AppError::Conflict(format!(
+            "These dates collide with a confirmed booking from {check_in} to {check_out}"
+        )));
+    }
+
+    let existing: Vec<BlockedDate> =
+        sqlx::query_as("SELECT * FROM blocked_dates WHERE property_id = $1 AND source IS NULL")
+            .bind(property_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+    let plan = plan_merge(&existing, start, end);
+
+    // Keep the reason of an absorbed block unless a new one was given.
+    let reason = reason.map(str::to_string).or_else(|| {
+        existing
+            .iter()
+            .filter(|b| plan.absorbed.contains(&b.id))
+            .find_map(|b| b.reason.clone())
+    });
+
+    sqlx::query("DELETE FROM blocked_dates WHERE id = ANY($1)")
+        .bind(&plan.absorbed)
+        .execute(&mut *tx)
+        .await?;
+
+    let block: BlockedDate = sqlx::query_as(
+        r#"INSERT INTO blocked_dates (id, property_id, start_date, end_date, reason, created_at)
+           VALUES ($1, $2, $3, $4, $5, NOW())
+           RETURNING *"#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(property_id)
+    .bind(plan.start_date)
+    .bind(plan.end_date)
+    .bind(reason)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(block)
+}
+
+/// Outcome of importing a calendar feed.
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub source: String,
+    /// Blocks created from the feed.
+    pub imported: usize,
+    /// Blocks from the previous import of the same feed that were replaced.
+    pub removed: u64,
+    /// Events left out because they collide with a confirmed booking here.
+    pub skipped: Vec<SkippedEvent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedEvent {
+    pub uid: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub reason: String,
+}
+
+/// Replace every block previously imported from `source` with `events`.
+///
+/// Importing the same feed twice leaves the calendar unchanged, and events
+/// removed from the feed disappear. Imported blocks are stored as they are,
+/// without merging, so each can be traced back to its event. Events that
+/// collide with a confirmed or checked-in booking are skipped and reported,
+/// since they point at a double booking the owner has to resolve.
+pub async fn replace_imported_blocks(
+    pool: &PgPool,
+    property_id: Uuid,
+    source: &str,
+    events: &[CalendarEvent],
+) -> Result<ImportSummary, AppError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT id FROM properties WHERE id = $1 FOR NO KEY UPDATE")
+        .bind(property_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Property not found".to_string()))?;
+
+    let removed = sqlx::query("DELETE FROM blocked_dates WHERE property_id = $1 AND source = $2")
+        .bind(property_id)
+        .bind(source)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let booked: Vec<(NaiveDate, NaiveDate)> = sqlx::query_as(
+        r#"SELECT check_in, check_out FROM bookings
+           WHERE property_id = $1 AND status IN ('confirmed', 'checked_in')"#,
+    )
+    .bind(property_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut summary = ImportSummary {
+        source: source.to_string(),
+        imported: 0,
+        removed,
+        skipped: Vec::new(),
+    };
+
+    for event in events {
+        let collision = booked.iter().find(|(check_in, check_out)| {
+            *check_in <= event.end_date && *check_out > event.start_date
+        });
+
+        if let Some((check_in, check_out)) = collision {
+            summary.skipped.push(SkippedEvent {
+                uid: event.uid.clone(),
+                start_date: event.start_date,
+                end_date: event.end_date,
+                reason: format!("Collides with a confirmed booking from {check_in} to {check_out}"),
+            });
+            continue;
+        }
+
+        sqlx::query(
+            r#"INSERT INTO blocked_dates
+                   (id, property_id, start_date, end_date, reason, source, external_uid, created_at)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())"#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(property_id)
+        .bind(event.start_date)
+        .bind(event.end_date)
+        .bind(&event.summary)
+        .bind(source)
+        .bind(&event.uid)
+        .execute(&mut *tx)
+        .await?;
+
+        summary.imported += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 5, d).unwrap()
+    }
+
+    fn block(start: u32, end: u32) -> BlockedDate {
+        BlockedDate {
+            id: Uuid::new_v4(),
+            property_id: Uuid::nil(),
+            start_date: date(start),
+            end_date: date(end),
+            reason: None,
+            created_at: Utc::now(),
+            source: None,
+            external_uid: None,
+        }
+    }
+
+    #[test]
+    fn test_separate_range_is_not_merged() {
+        let existing = vec![block(1, 3)];
+        let plan = plan_merge(&existing, date(5), date(7));
+        assert_eq!((plan.start_date, plan.end_date), (date(5), date(7)));
+        assert!(plan.absorbed.is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_and_adjacent_ranges_merge() {
+        let existing = vec![block(1, 3), block(10, 12)];
+
+        let overlapping = plan_merge(&existing, date(2), date(6));
+        assert_eq!(
+            (overlapping.start_date, overlapping.end_date),
+            (date(1), date(6))
+        );
+        assert_eq!(overlapping.absorbed, vec![existing[0].id]);
+
+        let adjacent = plan_merge(&existing, date(13), date(15));
+        assert_eq!(
+            (adjacent.start_date, adjacent.end_date),
+            (date(10), date(15))
+        );
+    }
+
+    #[test]
+    fn test_bridging_range_absorbs_both_sides() {
+        let existing = vec![block(1, 3), block(8, 9), block(20, 21)];
+        let plan = plan_merge(&existing, date(4), date(7));
+        assert_eq!((plan.start_date, plan.end_date), (date(1), date(9)));
+        assert_eq!(plan.absorbed.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_cascades_through_existing_overlaps() {
+        // Legacy data may contain blocks that were never merged.
+        let existing = vec![block(10, 14), block(1, 5), block(6, 9)];
+        let plan = plan_merge(&existing, date(4), date(4));
+        assert_eq!((plan.start_date, plan.end_date), (date(1), date(14)));
+        assert_eq!(plan.absorbed.len(), 3);
+    }
+}
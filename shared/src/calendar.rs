@@ -0,0 +1,503 @@
+use std::io::BufReader;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use ical::parser::ical::component::IcalEvent;
+use ical::IcalParser;
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use reqwest::Url;
+
+use crate::errors::AppError;
+
+/// Largest feed we are willing to download or accept as an upload.
+pub const MAX_FEED_BYTES: usize = 5 * 1024 * 1024;
+
+const PRODID: &str = "-//MyBaliVilla//Availability//EN";
+
+/// A single unavailable period in a calendar feed.
+///
+/// Dates are nights, like `blocked_dates`: both `start_date` and `end_date`
+/// are unavailable. On the wire this becomes an all-day event whose
+/// (exclusive) `DTEND` is the day after `end_date`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub summary: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Export
+// ---------------------------------------------------------------------------
+
+/// Render events as an RFC 5545 `VCALENDAR`, with CRLF line endings and
+/// long lines folded.
+pub fn render_calendar(
+    name: &str,
+    events: &[CalendarEvent],
+    generated_at: DateTime<Utc>,
+) -> String {
+    let stamp = generated_at.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut out = String::new();
+
+    for line in [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{PRODID}"),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ] {
+        push_line(&mut out, &line);
+    }
+
+    for event in events {
+        let end = event.end_date.max(event.start_date) + Days::new(1);
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}", escape_text(&event.uid)));
+        push_line(&mut out, &format!("DTSTAMP:{stamp}"));
+        push_line(
+            &mut out,
+            &format!("DTSTART;VALUE=DATE:{}", event.start_date.format("%Y%m%d")),
+        );
+        push_line(
+            &mut out,
+            &format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+        );
+        if let Some(ref summary) = event.summary {
+            push_line(&mut out, &format!("SUMMARY:{}", escape_text(summary)));
+        }
+        push_line(&mut out, "TRANSP:OPAQUE");
+        push_line(&mut out, "END:VEVENT");
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Append a content line, folding it at 75 octets (RFC 5545 §3.1) without
+/// splitting a UTF-8 character.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// ---------------------------------------------------------------------------
+// Import
+// ---------------------------------------------------------------------------
+
+/// Parse an iCalendar feed into unavailable periods.
+///
+/// Cancelled events are skipped. Date-time values are reduced to their
+/// calendar date; a stay occupies the nights from `DTSTART` up to (but not
+/// including) `DTEND`, and an event without `DTEND` blocks a single night.
+/// Recurrence rules are not expanded, since channel-manager feeds list every
+/// booking as its own event.
+pub fn parse_calendar(input: &[u8]) -> Result<Vec<CalendarEvent>, AppError> {
+    let mut events = Vec::new();
+    let mut calendars = 0;
+
+    for calendar in IcalParser::new(BufReader::new(input)) {
+        let calendar =
+            calendar.map_err(|e| AppError::BadRequest(format!("Invalid iCalendar feed: {e}")))?;
+        calendars += 1;
+
+        for event in &calendar.events {
+            if let Some(event) = parse_event(event)? {
+                events.push(event);
+            }
+        }
+    }
+
+    if calendars == 0 {
+        return Err(AppError::BadRequest(
+            "Invalid iCalendar feed: no VCALENDAR found".to_string(),
+        ));
+    }
+
+    Ok(events)
+}
+
+fn parse_event(event: &IcalEvent) -> Result<Option<CalendarEvent>, AppError> {
+    let value = |name: &str| {
+        event
+            .properties
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .and_then(|p| p.value.as_deref())
+            .map(str::trim)
+    };
+
+    if value("STATUS").is_some_and(|s| s.eq_ignore_ascii_case("CANCELLED")) {
+        return Ok(None);
+    }
+
+    let Some(dtstart) = value("DTSTART") else {
+        return Ok(None);
+    };
+    let start_date = parse_date(dtstart)?;
+
+    let end_date = match value("DTEND") {
+        // DTEND is exclusive: the check-out day itself stays free.
+        Some(dtend) => (parse_date(dtend)? - Days::new(1)).max(start_date),
+        None => start_date,
+    };
+
+    let uid = value("UID")
+        .filter(|uid| !uid.is_empty())
+        .map(|uid| unescape_text(uid).chars().take(255).collect())
+        // Without a UID, fall back to something stable across re-imports.
+        .unwrap_or_else(|| {
+            format!(
+                "{}-{}",
+                start_date.format("%Y%m%d"),
+                end_date.format("%Y%m%d")
+            )
+        });
+
+    let summary = value("SUMMARY")
+        .filter(|s| !s.is_empty())
+        .map(|s| unescape_text(s).chars().take(255).collect());
+
+    Ok(Some(CalendarEvent {
+        uid,
+        start_date,
+        end_date,
+        summary,
+    }))
+}
+
+/// Read the date part of a `DATE` (`20260310`) or `DATE-TIME`
+/// (`20260310T140000Z`) value.
+fn parse_date(value: &str) -> Result<NaiveDate, AppError> {
+    value
+        .get(..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid iCalendar date: {value}")))
+}
+
+/// Redirects followed when downloading a feed. Each hop is checked like the
+/// first URL.
+const MAX_REDIRECTS: usize = 5;
+
+/// Download a feed from `url`. `webcal://` links, which most platforms hand
+/// out, are fetched over HTTPS.
+///
+/// The URL comes from a listing owner, so it and every redirect may only
+/// point at public internet addresses, never private, loopback, link-local
+/// or otherwise reserved ones.
+pub async fn fetch_calendar(url: &str) -> Result<Vec<u8>, AppError> {
+    fetch_feed(url, is_public_address).await
+}
+
+async fn fetch_feed(url: &str, allowed: fn(IpAddr) -> bool) -> Result<Vec<u8>, AppError> {
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{rest}"),
+        None => url.to_string(),
+    };
+    let mut url =
+        Url::parse(&url).map_err(|_| AppError::BadRequest("Invalid calendar URL".to_string()))?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let mut response = get_checked(&url, allowed).await?;
+
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| {
+                    AppError::BadRequest(
+                        "Could not fetch calendar: redirect without a location".to_string(),
+                    )
+                })?;
+            url = url.join(location).map_err(|_| {
+                AppError::BadRequest("Could not fetch calendar: invalid redirect".to_string())
+            })?;
+            continue;
+        }
+
+        if !response.status().is_success() {
+            return Err(AppError::BadRequest(format!(
+                "Could not fetch calendar: server responded with {}",
+                response.status()
+            )));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Could not fetch calendar: {e}")))?
+        {
+            if body.len() + chunk.len() > MAX_FEED_BYTES {
+                return Err(AppError::BadRequest(
+                    "Calendar feed is too large".to_string(),
+                ));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        return Ok(body);
+    }
+
+    Err(AppError::BadRequest(
+        "Could not fetch calendar: too many redirects".to_string(),
+    ))
+}
+
+/// Send a GET to `url` without following redirects, once its host resolves
+/// only to addresses `allowed` accepts. The connection is pinned to the
+/// checked addresses, so a second DNS answer cannot send it elsewhere.
+async fn get_checked(
+    url: &Url,
+    allowed: fn(IpAddr) -> bool,
+) -> Result<reqwest::Response, AppError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::BadRequest(
+            "Calendar URL must use http, https or webcal".to_string(),
+        ));
+    }
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return Err(AppError::BadRequest("Invalid calendar URL".to_string()));
+    };
+
+    // IPv6 literals keep their brackets in `host_str`.
+    let literal = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok();
+    let addrs: Vec<SocketAddr> = match literal {
+        Some(ip) => vec![SocketAddr::new(ip, port)],
+        None => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| {
+                AppError::BadRequest(format!("Could not fetch calendar: unknown host {host}"))
+            })?
+            .collect(),
+    };
+    if addrs.is_empty() || !addrs.iter().all(|addr| allowed(addr.ip())) {
+        return Err(AppError::BadRequest(
+            "Calendar URL must point to a public internet address".to_string(),
+        ));
+    }
+
+    let mut client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .redirect(Policy::none())
+        // A proxy would make its own DNS lookup.
+        .no_proxy();
+    if literal.is_none() {
+        client = client.resolve_to_addrs(host, &addrs);
+    }
+    let client = client
+        .build()
+        .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {e}")))?;
+
+    client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Could not fetch calendar: {e}")))
+}
+
+/// Whether `ip` is a public internet address. Loopback, private, link-local
+/// (including the cloud metadata service at 169.254.169.254), carrier-grade
+/// NAT and other special-purpose ranges are not.
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_address(IpAddr::V4(v4));
+            }
+            let segments = ip.segments();
+            // NAT64 addresses reach the IPv4 address they embed.
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_public_address(IpAddr::V4(Ipv4Addr::new(a, b, c, d)));
+            }
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || segments[0] & 0xfe00 == 0xfc00
+                || segments[0] & 0xffc0 == 0xfe80
+                || segments[..2] == [0x2001, 0x0db8])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_lines_are_folded_at_75_octets() {
+        let mut out = String::new();
+        push_line(&mut out, &format!("SUMMARY:{}", "é".repeat(60)));
+        for line in out.split("\r\n") {
+            assert!(line.len() <= 75, "{line:?} is {} octets", line.len());
+        }
+        assert_eq!(
+            out.replace("\r\n ", ""),
+            format!("SUMMARY:{}\r\n", "é".repeat(60))
+        );
+    }
+
+    #[test]
+    fn test_text_escaping_round_trips() {
+        let text = "Owner stay; family, friends\nand a \\ backslash";
+        assert_eq!(unescape_text(&escape_text(text)), text);
+    }
+    #[test]
+    fn test_only_public_addresses_are_fetched() {
+        for ip in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+        ] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_internal_and_non_http_urls() {
+        for url in [
+            "http://127.0.0.1/feed.ics",
+            "http://localhost:8080/feed.ics",
+            "http://169.254.169.254/latest/meta-data/",
+            "webcal://10.0.0.1/feed.ics",
+            "http://[::1]/feed.ics",
+            "http://[::ffff:192.168.0.1]/feed.ics",
+            "ftp://example.com/feed.ics",
+            "file:///etc/passwd",
+        ] {
+            match fetch_calendar(url).await {
+                Err(AppError::BadRequest(_)) => {}
+                other => panic!("{url}: unexpected {other:?}"),
+            }
+        }
+    }
+
+    /// Serve `/feed.ics`, and redirect `/moved` there and `/metadata` to
+    /// the cloud metadata service, on a loopback port.
+    async fn redirecting_server() -> SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 1024];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..n]);
+                let response = if request.starts_with("GET /feed.ics ") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nBEGIN:...".to_string()
+                } else if request.starts_with("GET /moved ") {
+                    "HTTP/1.1 302 Found\r\nLocation: /feed.ics\r\nContent-Length: 0\r\n\r\n"
+                        .to_string()
+                } else {
+                    "HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/latest/\r\n\
+                     Content-Length: 0\r\n\r\n"
+                        .to_string()
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_every_redirect_is_checked() {
+        let addr = redirecting_server().await;
+        // Let this test reach the loopback server, and nothing else.
+        let loopback_only = |ip: IpAddr| ip.is_loopback();
+
+        let body = fetch_feed(&format!("http://{addr}/moved"), loopback_only)
+            .await
+            .unwrap();
+        assert_eq!(body, b"BEGIN:...");
+
+        match fetch_feed(&format!("http://{addr}/metadata"), loopback_only).await {
+            Err(AppError::BadRequest(msg)) => assert!(msg.contains("public"), "{msg}"),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
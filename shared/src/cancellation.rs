@@ -0,0 +1,255 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::{Booking, CancellationPolicyType, PropertyRules};
+
+/// Check-in time used when a property has no `property_rules` row.
+const DEFAULT_CHECK_IN: (u32, u32) = (14, 0);
+
+/// Bali is on WITA (UTC+8) all year round; check-in times are local.
+const BALI_UTC_OFFSET_SECS: i32 = 8 * 3600;
+
+/// The amounts a booking was charged, as stored on `bookings`.
+#[derive(Debug, Clone)]
+pub struct BookingCharges {
+    pub base_price: Decimal,
+    pub cleaning_fee: Decimal,
+    pub service_fee: Decimal,
+    pub currency: String,
+}
+
+/// How much a guest gets back for cancelling, and why.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RefundQuote {
+    pub policy: CancellationPolicyType,
+    /// Whole hours between cancellation and check-in (negative once the stay
+    /// has started).
+    pub hours_before_check_in: i64,
+    /// Share of the base price refunded, 0-100.
+    pub refund_percent: Decimal,
+    pub base_refund: Decimal,
+    pub cleaning_fee_refund: Decimal,
+    pub service_fee_refund: Decimal,
+    pub refund_amount: Decimal,
+    pub currency: String,
+    /// Human-readable summary of the rule that applied.
+    pub explanation: String,
+}
+
+/// The moment a stay starts: the check-in date at the property's check-in
+/// time (default 14:00), Bali time.
+pub fn check_in_at(check_in: NaiveDate, check_in_time: Option<NaiveTime>) -> DateTime<Utc> {
+    let time = check_in_time.unwrap_or_else(|| {
+        NaiveTime::from_hms_opt(DEFAULT_CHECK_IN.0, DEFAULT_CHECK_IN.1, 0).expect("valid time")
+    });
+    FixedOffset::east_opt(BALI_UTC_OFFSET_SECS)
+        .expect("valid offset")
+        .from_local_datetime(&check_in.and_time(time))
+        .single()
+        .expect("fixed offsets are unambiguous")
+        .with_timezone(&Utc)
+}
+
+/// Refund thresholds per policy as `(minimum hours of notice, percent of base
+/// price refunded)`, most generous first. Less notice than every threshold
+/// refunds nothing.
+fn refund_schedule(policy: &CancellationPolicyType) -> &'static [(i64, u32)] {
+    match policy {
+        // Full refund up to 24 hours before check-in.
+        CancellationPolicyType::Flexible => &[(24, 100)],
+        // Full refund up to 5 days before, half after that.
+        CancellationPolicyType::Moderate => &[(5 * 24, 100), (i64::MIN, 50)],
+        // Full refund up to 14 days before, half up to 7 days before.
+        CancellationPolicyType::Strict => &[(14 * 24, 100), (7 * 24, 50)],
+        CancellationPolicyType::NonRefundable => &[],
+    }
+}
+
+/// Work out the refund for cancelling a booking at `cancelled_at`.
+///
+/// The base price is refunded according to the policy's schedule and the
+/// service fee in the same proportion. The cleaning fee is refunded in full
+/// as long as the stay has not started, except under a non-refundable policy.
+/// Once the stay has started, nothing is refunded under any policy.
+pub fn calculate_refund(
+    policy: &CancellationPolicyType,
+    charges: &BookingCharges,
+    check_in_at: DateTime<Utc>,
+    cancelled_at: DateTime<Utc>,
+) -> RefundQuote {
+    let hours_before_check_in = (check_in_at - cancelled_at).num_hours();
+    let started = cancelled_at >= check_in_at;
+
+    let percent = if started {
+        0
+    } else {
+        refund_schedule(policy)
+            .iter()
+            .find(|(min_hours, _)| hours_before_check_in >= *min_hours)
+            .map(|(_, percent)| *percent)
+            .unwrap_or(0)
+    };
+    let refund_percent = Decimal::from(percent);
+
+    let share = |amount: Decimal| (amount * refund_percent / Decimal::ONE_HUNDRED).round_dp(2);
+    let base_refund = share(charges.base_price);
+    let service_fee_refund = share(charges.service_fee);
+    let cleaning_fee_refund = if started || *policy == CancellationPolicyType::NonRefundable {
+        Decimal::ZERO
+    } else {
+        charges.cleaning_fee
+    };
+
+    let explanation = if started {
+        "The stay has already started, so no refund is due".to_string()
+    } else if *policy == CancellationPolicyType::NonRefundable {
+        "This booking is non-refundable".to_string()
+    } else {
+        format!(
+            "Cancelled {hours_before_check_in} hours before check-in: {percent}% of the stay is refunded under the {} policy",
+            policy_name(policy)
+        )
+    };
+
+    RefundQuote {
+        policy: policy.clone(),
+        hours_before_check_in,
+        refund_percent,
+        base_refund,
+        cleaning_fee_refund,
+        service_fee_refund,
+        refund_amount: base_refund + cleaning_fee_refund + service_fee_refund,
+        currency: charges.currency.clone(),
+        explanation,
+    }
+}
+
+/// Refund for cancelling `booking` at `cancelled_at` under the property's
+/// rules. Properties without a `property_rules` row use the table defaults:
+/// the moderate policy and a 14:00 check-in.
+pub fn refund_for_booking(
+    booking: &Booking,
+    rules: Option<&PropertyRules>,
+    cancelled_at: DateTime<Utc>,
+) -> RefundQuote {
+    let policy = rules
+        .map(|r| r.cancellation_policy.clone())
+        .unwrap_or(CancellationPolicyType::Moderate);
+    let check_in = check_in_at(booking.check_in, rules.and_then(|r| r.check_in_time));
+
+    calculate_refund(
+        &policy,
+        &BookingCharges::from(booking),
+        check_in,
+        cancelled_at,
+    )
+}
+
+impl From<&Booking> for BookingCharges {
+    fn from(booking: &Booking) -> Self {
+        BookingCharges {
+            base_price: booking.base_price,
+            cleaning_fee: booking.cleaning_fee.unwrap_or_default(),
+            service_fee: booking.service_fee.unwrap_or_default(),
+            currency: booking.currency.clone(),
+        }
+    }
+}
+
+fn policy_name(policy: &CancellationPolicyType) -> &'static str {
+    match policy {
+        CancellationPolicyType::Flexible => "flexible",
+        CancellationPolicyType::Moderate => "moderate",
+        CancellationPolicyType::Strict => "strict",
+        CancellationPolicyType::NonRefundable => "non-refundable",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn charges() -> BookingCharges {
+        BookingCharges {
+            base_price: Decimal::from(1000),
+            cleaning_fee: Decimal::from(50),
+            service_fee: Decimal::from(100),
+            currency: "USD".to_string(),
+        }
+    }
+
+    fn check_in() -> DateTime<Utc> {
+        check_in_at(NaiveDate::from_ymd_opt(2026, 3, 10).unwrap(), None)
+    }
+
+    fn refund(policy: CancellationPolicyType, notice: Duration) -> Decimal {
+        calculate_refund(&policy, &charges(), check_in(), check_in() - notice).refund_amount
+    }
+
+    #[test]
+    fn test_check_in_is_local_bali_time() {
+        assert_eq!(check_in().to_rfc3339(), "2026-03-10T06:00:00+00:00");
+    }
+
+    #[test]
+    fn test_flexible() {
+        assert_eq!(
+            refund(CancellationPolicyType::Flexible, Duration::hours(24)),
+            Decimal::from(1150)
+        );
+        assert_eq!(
+            refund(CancellationPolicyType::Flexible, Duration::hours(23)),
+            Decimal::from(50)
+        );
+    }
+
+    #[test]
+    fn test_moderate() {
+        assert_eq!(
+            refund(CancellationPolicyType::Moderate, Duration::days(5)),
+            Decimal::from(1150)
+        );
+        assert_eq!(
+            refund(CancellationPolicyType::Moderate, Duration::days(2)),
+            Decimal::from(600)
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        assert_eq!(
+            refund(CancellationPolicyType::Strict, Duration::days(14)),
+            Decimal::from(1150)
+        );
+        assert_eq!(
+            refund(CancellationPolicyType::Strict, Duration::days(8)),
+            Decimal::from(600)
+        );
+        assert_eq!(
+            refund(CancellationPolicyType::Strict, Duration::days(3)),
+            Decimal::from(50)
+        );
+    }
+
+    #[test]
+    fn test_non_refundable() {
+        let quote = calculate_refund(
+            &CancellationPolicyType::NonRefundable,
+            &charges(),
+            check_in(),
+            check_in() - Duration::days(60),
+        );
+        assert_eq!(quote.refund_amount, Decimal::ZERO);
+        assert_eq!(quote.explanation, "This booking is non-refundable");
+    }
+
+    #[test]
+    fn test_no_refund_after_stay_started() {
+        assert_eq!(
+            refund(CancellationPolicyType::Moderate, Duration::hours(-1)),
+            Decimal::ZERO
+        );
+    }
+}
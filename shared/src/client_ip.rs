@@ -0,0 +1,87 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::LazyLock;
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+
+/// Whether to take the client address from the `X-Real-IP` header set by the
+/// reverse proxy. Only enable this when the API is reachable solely through
+/// the proxy; otherwise any client can claim any address.
+static TRUST_PROXY_HEADERS: LazyLock<bool> =
+    LazyLock::new(|| std::env::var("TRUST_PROXY_HEADERS").is_ok_and(|v| v == "true"));
+
+/// Extractor for the address of the client making the request.
+///
+/// Uses `X-Real-IP` when `TRUST_PROXY_HEADERS=true`, otherwise the peer
+/// address of the connection, which requires the server to be started with
+/// `into_make_service_with_connect_info::<SocketAddr>()`. `None` when neither
+/// is available.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(client_ip(parts, *TRUST_PROXY_HEADERS)))
+    }
+}
+
+fn client_ip(parts: &Parts, trust_proxy_headers: bool) -> Option<IpAddr> {
+    if trust_proxy_headers {
+        let header = parts
+            .headers
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        if header.is_some() {
+            return header;
+        }
+    }
+
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    fn parts(real_ip: Option<&str>, peer: Option<&str>) -> Parts {
+        let mut builder = Request::builder();
+        if let Some(ip) = real_ip {
+            builder = builder.header("x-real-ip", ip);
+        }
+        let (mut parts, _) = builder.body(()).unwrap().into_parts();
+        if let Some(peer) = peer {
+            parts
+                .extensions
+                .insert(ConnectInfo::<SocketAddr>(peer.parse().unwrap()));
+        }
+        parts
+    }
+
+    #[test]
+    fn test_proxy_header_used_only_when_trusted() {
+        let parts = parts(Some("203.0.113.7"), Some("10.0.0.2:51000"));
+        assert_eq!(
+            client_ip(&parts, true),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(client_ip(&parts, false), Some("10.0.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_falls_back_to_peer_address() {
+        let parts = parts(Some("not an ip"), Some("[2001:db8::1]:443"));
+        assert_eq!(
+            client_ip(&parts, true),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(client_ip(&self::parts(None, None), true), None);
+    }
+}
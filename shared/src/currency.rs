@@ -0,0 +1,553 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::ExchangeRate;
+
+/// The currency exchange rates are quoted against. Price filters and sorting
+/// compare listings after converting their prices to it.
+pub const BASE_CURRENCY: &str = "USD";
+
+/// Largest exchange rate file we accept as an upload.
+pub const MAX_RATES_FILE_BYTES: usize = 1024 * 1024;
+
+/// Active ISO 4217 currencies and their minor units (decimal places).
+#[rustfmt::skip]
+const ISO_4217: &[(&str, u32)] = &[
+    ("AED", 2), ("AFN", 2), ("ALL", 2), ("AMD", 2), ("ANG", 2), ("AOA", 2), ("ARS", 2),
+    ("AUD", 2), ("AWG", 2), ("AZN", 2), ("BAM", 2), ("BBD", 2), ("BDT", 2), ("BGN", 2),
+    ("BHD", 3), ("BIF", 0), ("BMD", 2), ("BND", 2), ("BOB", 2), ("BRL", 2), ("BSD", 2),
+    ("BTN", 2), ("BWP", 2), ("BYN", 2), ("BZD", 2), ("CAD", 2), ("CDF", 2), ("CHF", 2),
+    ("CLP", 0), ("CNY", 2), ("COP", 2), ("CRC", 2), ("CUP", 2), ("CVE", 2), ("CZK", 2),
+    ("DJF", 0), ("DKK", 2), ("DOP", 2), ("DZD", 2), ("EGP", 2), ("ERN", 2), ("ETB", 2),
+    ("EUR", 2), ("FJD", 2), ("FKP", 2), ("GBP", 2), ("GEL", 2), ("GHS", 2), ("GIP", 2),
+    ("GMD", 2), ("GNF", 0), ("GTQ", 2), ("GYD", 2), ("HKD", 2), ("HNL", 2), ("HTG", 2),
+    ("HUF", 2), ("IDR", 2), ("ILS", 2), ("INR", 2), ("IQD", 3), ("IRR", 2), ("ISK", 0),
+    ("JMD", 2), ("JOD", 3), ("JPY", 0), ("KES", 2), ("KGS", 2), ("KHR", 2), ("KMF", 0),
+    ("KPW", 2), ("KRW", 0), ("KWD", 3), ("KYD", 2), ("KZT", 2), ("LAK", 2), ("LBP", 2),
+    ("LKR", 2), ("LRD", 2), ("LSL", 2), ("LYD", 3), ("MAD", 2), ("MDL", 2), ("MGA", 2),
+    ("MKD", 2), ("MMK", 2), ("MNT", 2), ("MOP", 2), ("MRU", 2), ("MUR", 2), ("MVR", 2),
+    ("MWK", 2), ("MXN", 2), ("MYR", 2), ("MZN", 2), ("NAD", 2), ("NGN", 2), ("NIO", 2),
+    ("NOK", 2), ("NPR", 2), ("NZD", 2), ("OMR", 3), ("PAB", 2), ("PEN", 2), ("PGK", 2),
+    ("PHP", 2), ("PKR", 2), ("PLN", 2), ("PYG", 0), ("QAR", 2), ("RON", 2), ("RSD", 2),
+    ("RUB", 2), ("RWF", 0), ("SAR", 2), ("SBD", 2), ("SCR", 2), ("SDG", 2), ("SEK", 2),
+    ("SGD", 2), ("SHP", 2), ("SLE", 2), ("SOS", 2), ("SRD", 2), ("SSP", 2), ("STN", 2),
+    ("SVC", 2), ("SYP", 2), ("SZL", 2), ("THB", 2), ("TJS", 2), ("TMT", 2), ("TND", 3),
+    ("TOP", 2), ("TRY", 2), ("TTD", 2), ("TWD", 2), ("TZS", 2), ("UAH", 2), ("UGX", 0),
+    ("USD", 2), ("UYU", 2), ("UZS", 2), ("VES", 2), ("VND", 0), ("VUV", 0), ("WST", 2),
+    ("XAF", 0), ("XCD", 2), ("XCG", 2), ("XOF", 0), ("XPF", 0), ("YER", 2), ("ZAR", 2),
+    ("ZMW", 2), ("ZWG", 2),
+];
+
+/// The number of decimal places `code` is quoted in, or `None` when it is not
+/// an active ISO 4217 currency code.
+pub fn minor_units(code: &str) -> Option<u32> {
+    ISO_4217
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|&(_, units)| units)
+}
+
+/// Normalise a currency code to upper case, checking it against ISO 4217.
+pub fn parse_currency(code: &str) -> Result<String, AppError> {
+    let code = code.trim().to_ascii_uppercase();
+    if minor_units(&code).is_none() {
+        return Err(AppError::BadRequest(format!(
+            "Unknown currency code '{code}'; expected an ISO 4217 code such as USD or IDR"
+        )));
+    }
+    Ok(code)
+}
+
+/// SQL for the `amount` column, priced in the `currency` column, converted to
+/// the base currency. Rounded so the value survives a trip through a cursor.
+pub fn base_amount_sql(amount: &str, currency: &str) -> String {
+    format!(
+        "ROUND({amount} / (SELECT er.rate FROM exchange_rates er \
+         WHERE er.currency = {currency}), 6)"
+    )
+}
+
+/// A snapshot of the `exchange_rates` table.
+#[derive(Debug, Clone)]
+pub struct ExchangeRates {
+    rates: HashMap<String, Decimal>,
+}
+
+impl ExchangeRates {
+    pub fn new(rates: impl IntoIterator<Item = (String, Decimal)>) -> Self {
+        let mut rates: HashMap<String, Decimal> = rates.into_iter().collect();
+        rates.insert(BASE_CURRENCY.to_string(), Decimal::ONE);
+        Self { rates }
+    }
+
+    pub async fn load(pool: &PgPool) -> Result<Self, AppError> {
+        let rows: Vec<(String, Decimal)> =
+            sqlx::query_as("SELECT currency, rate FROM exchange_rates")
+                .fetch_all(pool)
+                .await?;
+
+        Ok(Self::new(rows))
+    }
+
+    /// Units of `currency` per unit of the base currency.
+    pub fn rate(&self, currency: &str) -> Option<Decimal> {
+        self.rates.get(currency).copied()
+    }
+
+    /// Convert `amount` between currencies, rounded to the minor units of
+    /// `to`. `None` when either currency has no rate.
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str) -> Option<Decimal> {
+        if from == to {
+            return Some(amount);
+        }
+        let converted = amount
+            .checked_mul(self.rate(to)?)?
+            .checked_div(self.rate(from)?)?;
+        Some(converted.round_dp(minor_units(to).unwrap_or(2)))
+    }
+}
+
+/// The currency a visitor asked to see prices in (`display_currency`).
+#[derive(Debug, Clone)]
+pub struct DisplayCurrency {
+    pub currency: String,
+    rates: ExchangeRates,
+}
+
+impl DisplayCurrency {
+    pub fn new(code: &str, rates: ExchangeRates) -> Result<Self, AppError> {
+        let currency = parse_currency(code)?;
+        if rates.rate(&currency).is_none() {
+            return Err(AppError::BadRequest(format!(
+                "No exchange rate for {currency}"
+            )));
+        }
+        Ok(Self { currency, rates })
+    }
+
+    /// Resolve an optional `display_currency` parameter. The rates are only
+    /// loaded when one is given.
+    pub async fn from_param(pool: &PgPool, code: Option<&str>) -> Result<Option<Self>, AppError> {
+        match code.map(str::trim).filter(|c| !c.is_empty()) {
+            Some(code) => Ok(Some(Self::new(code, ExchangeRates::load(pool).await?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// `amount` in currency `from`, shown in the display currency.
+    pub fn convert(&self, amount: Decimal, from: &str) -> Option<Decimal> {
+        self.rates.convert(amount, from, &self.currency)
+    }
+
+    /// An amount entered in the display currency (such as a price filter),
+    /// in the base currency. Not rounded, so filters stay exact.
+    pub fn to_base(&self, amount: Decimal) -> Decimal {
+        let rate = self.rates.rate(&self.currency).unwrap_or(Decimal::ONE);
+        amount.checked_div(rate).unwrap_or(amount)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Managing rates
+// ---------------------------------------------------------------------------
+
+/// Check a rate is positive and fits `exchange_rates.rate` (8 decimal places).
+fn check_rate(currency: &str, rate: Decimal) -> Result<Decimal, AppError> {
+    let rate = rate.round_dp(8);
+    if currency == BASE_CURRENCY && rate != Decimal::ONE {
+        return Err(AppError::BadRequest(format!(
+            "The base currency {BASE_CURRENCY} always has rate 1"
+        )));
+    }
+    if rate <= Decimal::ZERO {
+        return Err(AppError::BadRequest(format!(
+            "Rate for {currency} must be positive"
+        )));
+    }
+    if rate >= Decimal::from(1_000_000_000_000_i64) {
+        return Err(AppError::BadRequest(format!(
+            "Rate for {currency} is too large"
+        )));
+    }
+    Ok(rate)
+}
+
+/// Insert or update exchange rates. Either every rate is applied or, if any
+/// is invalid, none is.
+pub async fn set_rates(
+    pool: &PgPool,
+    rates: &[(String, Decimal)],
+    source: &str,
+    updated_by: Option<Uuid>,
+) -> Result<Vec<ExchangeRate>, AppError> {
+    if rates.is_empty() {
+        return Err(AppError::BadRequest("No exchange rates given".to_string()));
+    }
+
+    let mut seen = HashSet::new();
+    let mut checked = Vec::with_capacity(rates.len());
+    for (code, rate) in rates {
+        let currency = parse_currency(code)?;
+        let rate = check_rate(&currency, *rate)?;
+        if !seen.insert(currency.clone()) {
+            return Err(AppError::BadRequest(format!(
+                "{currency} is listed more than once"
+            )));
+        }
+        checked.push((currency, rate));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut saved = Vec::with_capacity(checked.len());
+    for (currency, rate) in checked {
+        let row: ExchangeRate = sqlx::query_as(
+            r#"INSERT INTO exchange_rates (currency, rate, source, updated_by)
+               VALUES ($1, $2, $3, $4)
+               ON CONFLICT (currency) DO UPDATE
+               SET rate = EXCLUDED.rate, source = EXCLUDED.source, updated_by = EXCLUDED.updated_by
+               RETURNING *"#,
+        )
+        .bind(&currency)
+        .bind(rate)
+        .bind(source)
+        .bind(updated_by)
+        .fetch_one(&mut *tx)
+        .await?;
+        saved.push(row);
+    }
+    tx.commit().await?;
+
+    Ok(saved)
+}
+
+/// Remove a currency's rate. Not allowed for the base currency or while any
+/// listing is priced in the currency.
+pub async fn delete_rate(pool: &PgPool, code: &str) -> Result<(), AppError> {
+    let currency = parse_currency(code)?;
+    if currency == BASE_CURRENCY {
+        return Err(AppError::BadRequest(
+            "The base currency cannot be removed".to_string(),
+        ));
+    }
+
+    let listings: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM properties WHERE currency = $1")
+        .bind(&currency)
+        .fetch_one(pool)
+        .await?;
+    if listings > 0 {
+        return Err(AppError::Conflict(format!(
+            "{listings} listing(s) are priced in {currency}"
+        )));
+    }
+
+    let result = sqlx::query("DELETE FROM exchange_rates WHERE currency = $1")
+        .bind(&currency)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "No exchange rate for {currency}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// The currency for a new or edited listing: a valid ISO 4217 code with an
+/// exchange rate, so the listing can be compared with others. Defaults to
+/// the base currency.
+pub async fn listing_currency(pool: &PgPool, code: Option<&str>) -> Result<String, AppError> {
+    let currency = match code {
+        Some(code) => parse_currency(code)?,
+        None => return Ok(BASE_CURRENCY.to_string()),
+    };
+
+    let known: Option<(String,)> =
+        sqlx::query_as("SELECT currency FROM exchange_rates WHERE currency = $1")
+            .bind(&currency)
+            .fetch_optional(pool)
+            .await?;
+    if known.is_none() {
+        return Err(AppError::BadRequest(format!(
+            "No exchange rate for {currency}; ask an admin to add one"
+        )));
+    }
+
+    Ok(currency)
+}
+
+// ---------------------------------------------------------------------------
+// Rate files
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RatesFile {
+    /// `{"base": "USD", "rates": {"IDR": 16250, ...}}`, the shape most rate
+    /// feeds publish.
+    Table {
+        base: Option<String>,
+        rates: BTreeMap<String, serde_json::Value>,
+    },
+    /// `[{"currency": "IDR", "rate": 16250}, ...]`
+    List(Vec<RateEntry>),
+}
+
+#[derive(Deserialize)]
+struct RateEntry {
+    currency: String,
+    rate: serde_json::Value,
+}
+
+fn parse_decimal(text: &str) -> Option<Decimal> {
+    let text = text.trim();
+    Decimal::from_str(text)
+        .or_else(|_| Decimal::from_scientific(text))
+        .ok()
+}
+
+fn json_rate(currency: &str, value: &serde_json::Value) -> Result<Decimal, AppError> {
+    let rate = match value {
+        serde_json::Value::Number(n) => parse_decimal(&n.to_string()),
+        serde_json::Value::String(s) => parse_decimal(s),
+        _ => None,
+    };
+    rate.ok_or_else(|| AppError::BadRequest(format!("Rate for {currency} is not a number")))
+}
+
+/// Quote rates given against another currency against the base currency
+/// instead. The file must include the base currency's rate for this.
+fn rebase(base: &str, rates: Vec<(String, Decimal)>) -> Result<Vec<(String, Decimal)>, AppError> {
+    let base_rate = rates
+        .iter()
+        .find(|(c, _)| c == BASE_CURRENCY)
+        .map(|&(_, rate)| rate)
+        .filter(|rate| *rate > Decimal::ZERO)
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Rates quoted against {base} must include {BASE_CURRENCY}"
+            ))
+        })?;
+
+    let divide = |currency: &str, rate: Decimal| {
+        rate.checked_div(base_rate)
+            .ok_or_else(|| AppError::BadRequest(format!("Rate for {currency} is too large")))
+    };
+
+    let mut rebased = vec![(base.to_string(), divide(base, Decimal::ONE)?)];
+    for (currency, rate) in rates {
+        if currency != base {
+            let rate = divide(&currency, rate)?;
+            rebased.push((currency, rate));
+        }
+    }
+    Ok(rebased)
+}
+
+fn parse_rates_json(text: &str) -> Result<Vec<(String, Decimal)>, AppError> {
+    let file: RatesFile = serde_json::from_str(text).map_err(|_| {
+        AppError::BadRequest(
+            r#"Expected {"base": ..., "rates": {...}} or a list of {"currency": ..., "rate": ...}"#
+                .to_string(),
+        )
+    })?;
+
+    match file {
+        RatesFile::Table { base, rates } => {
+            let rates = rates
+                .iter()
+                .map(|(code, value)| {
+                    let currency = parse_currency(code)?;
+                    let rate = json_rate(&currency, value)?;
+                    Ok((currency, rate))
+                })
+                .collect::<Result<Vec<_>, AppError>>()?;
+            match base.as_deref().map(parse_currency).transpose()? {
+                Some(base) if base != BASE_CURRENCY => rebase(&base, rates),
+                _ => Ok(rates),
+            }
+        }
+        RatesFile::List(entries) => entries
+            .iter()
+            .map(|entry| {
+                let currency = parse_currency(&entry.currency)?;
+                let rate = json_rate(&currency, &entry.rate)?;
+                Ok((currency, rate))
+            })
+            .collect(),
+    }
+}
+
+fn parse_rates_csv(text: &str) -> Result<Vec<(String, Decimal)>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| AppError::BadRequest(format!("Invalid CSV: {e}")))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (Some(currency_col), Some(rate_col)) = (column("currency"), column("rate")) else {
+        return Err(AppError::BadRequest(
+            "CSV must start with a header row naming the currency and rate columns".to_string(),
+        ));
+    };
+
+    let mut rates = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::BadRequest(format!("Invalid CSV: {e}")))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |col: usize| record.get(col).unwrap_or_default();
+
+        let currency = parse_currency(field(currency_col)).map_err(|e| match e {
+            AppError::BadRequest(msg) => AppError::BadRequest(format!("Line {line}: {msg}")),
+            other => other,
+        })?;
+        let rate = parse_decimal(field(rate_col)).ok_or_else(|| {
+            AppError::BadRequest(format!("Line {line}: rate for {currency} is not a number"))
+        })?;
+        rates.push((currency, rate));
+    }
+
+    Ok(rates)
+}
+
+/// Parse an uploaded exchange rate file, quoted against the base currency
+/// unless it says otherwise. Accepted formats:
+///
+/// - JSON `{"base": "USD", "rates": {"IDR": 16250, "EUR": 0.92}}`; rates
+///   against another `base` are converted, provided USD is among them
+/// - JSON `[{"currency": "IDR", "rate": 16250}]`
+/// - CSV with a header row naming `currency` and `rate` columns
+pub fn parse_rates_file(data: &[u8]) -> Result<Vec<(String, Decimal)>, AppError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| AppError::BadRequest("Exchange rate file must be UTF-8 text".to_string()))?;
+    // Spreadsheet exports often start with a byte order mark.
+    let text = text.trim_start_matches('\u{feff}').trim();
+
+    let rates = match text.chars().next() {
+        Some('{' | '[') => parse_rates_json(text)?,
+        Some(_) => parse_rates_csv(text)?,
+        None => Vec::new(),
+    };
+    if rates.is_empty() {
+        return Err(AppError::BadRequest(
+            "Exchange rate file contains no rates".to_string(),
+        ));
+    }
+
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn rates() -> ExchangeRates {
+        ExchangeRates::new([
+            ("IDR".to_string(), dec("16250")),
+            ("EUR".to_string(), dec("0.92")),
+            ("JPY".to_string(), dec("150")),
+        ])
+    }
+
+    #[test]
+    fn test_parse_currency() {
+        assert_eq!(parse_currency(" idr ").unwrap(), "IDR");
+        assert_eq!(parse_currency("USD").unwrap(), "USD");
+        for bad in ["", "US", "XYZ", "usdollar", "Rp"] {
+            assert!(parse_currency(bad).is_err(), "{bad} was accepted");
+        }
+    }
+
+    #[test]
+    fn test_convert_rounds_to_minor_units() {
+        let rates = rates();
+        // 8,125,000 IDR is 500 USD, or 460 EUR.
+        assert_eq!(
+            rates.convert(dec("8125000"), "IDR", "USD"),
+            Some(dec("500"))
+        );
+        assert_eq!(
+            rates.convert(dec("8125000"), "IDR", "EUR"),
+            Some(dec("460"))
+        );
+        assert_eq!(
+            rates.convert(dec("99.99"), "USD", "JPY"),
+            Some(dec("14998"))
+        );
+        assert_eq!(rates.convert(dec("10"), "EUR", "EUR"), Some(dec("10")));
+        assert_eq!(rates.convert(dec("10"), "GBP", "USD"), None);
+    }
+
+    #[test]
+    fn test_display_currency() {
+        assert!(DisplayCurrency::new("GBP", rates()).is_err());
+
+        let display = DisplayCurrency::new("eur", rates()).unwrap();
+        assert_eq!(display.currency, "EUR");
+        assert_eq!(display.convert(dec("100"), "USD"), Some(dec("92")));
+        assert_eq!(display.to_base(dec("92")), dec("100"));
+    }
+
+    #[test]
+    fn test_check_rate() {
+        assert_eq!(
+            check_rate("IDR", dec("16250.123456789")).unwrap(),
+            dec("16250.12345679")
+        );
+        assert!(check_rate("IDR", dec("0")).is_err());
+        assert!(check_rate("IDR", dec("0.000000001")).is_err());
+        assert!(check_rate("USD", dec("1.1")).is_err());
+        assert!(check_rate("USD", dec("1")).is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_set_and_delete_rates() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        sqlx::query("DELETE FROM exchange_rates WHERE currency IN ('ISK', 'KES')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // One bad rate rejects the whole batch.
+        let batch = [
+            ("isk".to_string(), dec("138.5")),
+            ("KES".to_string(), dec("0")),
+        ];
+        assert!(set_rates(&pool, &batch, "import", None).await.is_err());
+        assert!(listing_currency(&pool, Some("ISK")).await.is_err());
+
+        let saved = set_rates(&pool, &batch[..1], "import", None).await.unwrap();
+        assert_eq!(saved[0].currency, "ISK");
+        assert_eq!(saved[0].rate, dec("138.5"));
+        assert_eq!(listing_currency(&pool, Some(" isk")).await.unwrap(), "ISK");
+        assert_eq!(listing_currency(&pool, None).await.unwrap(), "USD");
+
+        let rates = ExchangeRates::load(&pool).await.unwrap();
+        assert_eq!(rates.convert(dec("100"), "USD", "ISK"), Some(dec("13850")));
+
+        delete_rate(&pool, "ISK").await.unwrap();
+        assert!(matches!(
+            delete_rate(&pool, "ISK").await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            delete_rate(&pool, "USD").await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
@@ -18,6 +18,8 @@ pub enum AppError {
     Forbidden(String),
     /// Resource conflict, e.g. duplicate email (409).
     Conflict(String),
+    /// Rate limit or lockout in effect, e.g. repeated failed logins (429).
+    TooManyRequests(String),
 }
 
 impl fmt::Display for AppError {
@@ -29,6 +31,7 @@ impl fmt::Display for AppError {
             AppError::Internal(msg) => write!(f, "Internal error: {msg}"),
             AppError::Forbidden(msg) => write!(f, "Forbidden: {msg}"),
             AppError::Conflict(msg) => write!(f, "Conflict: {msg}"),
+            AppError::TooManyRequests(msg) => write!(f, "Too many requests: {msg}"),
         }
     }
 }
@@ -44,6 +47,7 @@ impl IntoResponse for AppError {
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone()),
         };
 
         let body = axum::Json(json!({
@@ -57,16 +61,39 @@ impl IntoResponse for AppError {
     }
 }
 
-/// Convenience conversion from `sqlx::Error`.
+/// Convenience conversion from `sqlx::Error`. Rows rejected by a unique or
+/// exclusion constraint are a generic 409; callers wanting a specific message
+/// for one constraint use [`AppError::on_constraint`].
 impl From<sqlx::Error> for AppError {
     fn from(err: sqlx::Error) -> Self {
         match err {
             sqlx::Error::RowNotFound => AppError::NotFound("Record not found".to_string()),
+            // unique_violation, exclusion_violation
+            sqlx::Error::Database(ref db)
+                if matches!(db.code().as_deref(), Some("23505") | Some("23P01")) =>
+            {
+                AppError::Conflict("Conflicts with an existing record".to_string())
+            }
             _ => AppError::Internal(format!("Database error: {err}")),
         }
     }
 }
 
+impl AppError {
+    /// For `map_err`: turn a violation of the database constraint
+    /// `constraint` into `error`, and any other error into the usual
+    /// conversion.
+    pub fn on_constraint(
+        constraint: &'static str,
+        error: AppError,
+    ) -> impl FnOnce(sqlx::Error) -> AppError {
+        move |err| match err {
+            sqlx::Error::Database(ref db) if db.constraint() == Some(constraint) => error,
+            err => err.into(),
+        }
+    }
+}
+
 /// Convenience conversion from `serde_json::Error`.
 impl From<serde_json::Error> for AppError {
     fn from(err: serde_json::Error) -> Self {
@@ -0,0 +1,219 @@
+use crate::errors::AppError;
+
+/// Mean Earth radius used for great-circle distances.
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// A latitude/longitude rectangle in degrees.
+///
+/// When `min_lng > max_lng` the box crosses the antimeridian and covers the
+/// longitudes from `min_lng` east to 180 and from -180 to `max_lng`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lng: f64,
+    pub max_lat: f64,
+    pub max_lng: f64,
+}
+
+impl BoundingBox {
+    /// Parse a `min_lng,min_lat,max_lng,max_lat` string, the order used by
+    /// GeoJSON and by map libraries such as Leaflet (`toBBoxString`).
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        let invalid = || {
+            AppError::BadRequest(
+                "Invalid bbox: expected min_lng,min_lat,max_lng,max_lat".to_string(),
+            )
+        };
+
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<f64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let [min_lng, min_lat, max_lng, max_lat] = parts[..] else {
+            return Err(invalid());
+        };
+
+        validate_point(min_lat, min_lng)?;
+        validate_point(max_lat, max_lng)?;
+        if min_lat > max_lat {
+            return Err(AppError::BadRequest(
+                "Invalid bbox: min_lat must not be greater than max_lat".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            min_lat,
+            min_lng,
+            max_lat,
+            max_lng,
+        })
+    }
+
+    /// The smallest box containing every point within `radius_km` of
+    /// `(lat, lng)`. Used to narrow a radius search with an index before the
+    /// exact distance check.
+    pub fn around(lat: f64, lng: f64, radius_km: f64) -> Self {
+        let angular = radius_km / EARTH_RADIUS_KM;
+        let lat_delta = angular.to_degrees();
+        let min_lat = lat - lat_delta;
+        let max_lat = lat + lat_delta;
+
+        // A circle reaching a pole covers every longitude.
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            return Self {
+                min_lat: min_lat.max(-90.0),
+                min_lng: -180.0,
+                max_lat: max_lat.min(90.0),
+                max_lng: 180.0,
+            };
+        }
+
+        let lng_delta = (angular.sin() / lat.to_radians().cos()).asin().to_degrees();
+        if lng_delta.is_nan() || lng_delta >= 180.0 {
+            return Self {
+                min_lat,
+                min_lng: -180.0,
+                max_lat,
+                max_lng: 180.0,
+            };
+        }
+
+        let wrap = |lng: f64| {
+            if lng < -180.0 {
+                lng + 360.0
+            } else if lng > 180.0 {
+                lng - 360.0
+            } else {
+                lng
+            }
+        };
+
+        Self {
+            min_lat,
+            min_lng: wrap(lng - lng_delta),
+            max_lat,
+            max_lng: wrap(lng + lng_delta),
+        }
+    }
+
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lng > self.max_lng
+    }
+
+    pub fn contains(&self, lat: f64, lng: f64) -> bool {
+        let lng_inside = if self.crosses_antimeridian() {
+            lng >= self.min_lng || lng <= self.max_lng
+        } else {
+            lng >= self.min_lng && lng <= self.max_lng
+        };
+        lat >= self.min_lat && lat <= self.max_lat && lng_inside
+    }
+}
+
+/// Reject coordinates outside the valid latitude and longitude ranges.
+pub fn validate_point(lat: f64, lng: f64) -> Result<(), AppError> {
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(AppError::BadRequest(
+            "Latitude must be between -90 and 90".to_string(),
+        ));
+    }
+    if !(-180.0..=180.0).contains(&lng) {
+        return Err(AppError::BadRequest(
+            "Longitude must be between -180 and 180".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Great-circle distance between two points, using the haversine formula.
+///
+/// [`haversine_sql`] computes the same value in Postgres.
+pub fn haversine_km(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lng = (lng2 - lng1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lng / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// SQL expression for the haversine distance in kilometres from the point
+/// bound at `lat_param`/`lng_param` (`float8`) to the `lat`/`lng` columns.
+/// Plain maths, so it needs neither PostGIS nor `earthdistance`.
+pub fn haversine_sql(lat: &str, lng: &str, lat_param: usize, lng_param: usize) -> String {
+    format!(
+        "(2 * {EARTH_RADIUS_KM} * asin(least(1, sqrt(\
+         power(sin(radians({lat}::float8 - ${lat_param}) / 2), 2) \
+         + cos(radians(${lat_param})) * cos(radians({lat}::float8)) \
+         * power(sin(radians({lng}::float8 - ${lng_param}) / 2), 2)))))"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Seminyak and Ubud, about 23 km apart.
+    const SEMINYAK: (f64, f64) = (-8.6913, 115.1682);
+    const UBUD: (f64, f64) = (-8.5069, 115.2625);
+
+    #[test]
+    fn test_haversine_distance() {
+        let d = haversine_km(SEMINYAK.0, SEMINYAK.1, UBUD.0, UBUD.1);
+        assert!((d - 22.9).abs() < 0.5, "got {d}");
+        assert_eq!(haversine_km(UBUD.0, UBUD.1, UBUD.0, UBUD.1), 0.0);
+    }
+
+    #[test]
+    fn test_parse_bbox() {
+        let bbox = BoundingBox::parse("115.1, -8.8,115.3,-8.5").unwrap();
+        assert_eq!(
+            bbox,
+            BoundingBox {
+                min_lat: -8.8,
+                min_lng: 115.1,
+                max_lat: -8.5,
+                max_lng: 115.3,
+            }
+        );
+        assert!(bbox.contains(SEMINYAK.0, SEMINYAK.1));
+
+        assert!(BoundingBox::parse("115.1,-8.8,115.3").is_err());
+        assert!(BoundingBox::parse("115.1,-8.8,115.3,-8.5,1").is_err());
+        assert!(BoundingBox::parse("115.1,-8.5,115.3,-8.8").is_err());
+        assert!(BoundingBox::parse("115.1,-91,115.3,-8.5").is_err());
+        assert!(BoundingBox::parse("a,b,c,d").is_err());
+    }
+
+    #[test]
+    fn test_box_around_point_contains_the_circle() {
+        let radius = 25.0;
+        let bbox = BoundingBox::around(SEMINYAK.0, SEMINYAK.1, radius);
+        assert!(bbox.contains(UBUD.0, UBUD.1));
+
+        // Points just inside the circle, every 10 degrees of bearing.
+        for bearing in (0..360).step_by(10) {
+            let b = f64::from(bearing).to_radians();
+            let (lat1, lng1) = (SEMINYAK.0.to_radians(), SEMINYAK.1.to_radians());
+            let d = (radius - 0.001) / EARTH_RADIUS_KM;
+            let lat2 = (lat1.sin() * d.cos() + lat1.cos() * d.sin() * b.cos()).asin();
+            let lng2 =
+                lng1 + (b.sin() * d.sin() * lat1.cos()).atan2(d.cos() - lat1.sin() * lat2.sin());
+            assert!(
+                bbox.contains(lat2.to_degrees(), lng2.to_degrees()),
+                "bearing {bearing}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_box_around_point_wraps_and_reaches_poles() {
+        let fiji = BoundingBox::around(-17.7, 179.9, 50.0);
+        assert!(fiji.crosses_antimeridian());
+        assert!(fiji.contains(-17.7, -179.9));
+        assert!(!fiji.contains(-17.7, 0.0));
+
+        let polar = BoundingBox::around(89.9, 10.0, 50.0);
+        assert_eq!((polar.min_lng, polar.max_lng), (-180.0, 180.0));
+        assert_eq!(polar.max_lat, 90.0);
+    }
+}
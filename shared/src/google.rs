@@ -1,7 +1,30 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::errors::ErrorKind;
+pub use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use serde::Deserialize;
+use tokio::sync::RwLock;
 
 use crate::errors::AppError;
 
+/// Google's signing keys for ID tokens, as a JWK set.
+pub const GOOGLE_CERTS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+
+/// Both forms of `iss` Google puts in ID tokens.
+const GOOGLE_ISSUERS: [&str; 2] = ["accounts.google.com", "https://accounts.google.com"];
+
+/// How long keys are cached when the key source gives no lifetime.
+const DEFAULT_KEY_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A token signed with a key we don't know triggers a refetch, in case Google
+/// rotated its keys early, but no more often than this.
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
 /// User information extracted from a verified Google ID token.
 pub struct GoogleUserInfo {
     pub google_id: String,
@@ -10,88 +33,280 @@ pub struct GoogleUserInfo {
     pub picture: Option<String>,
 }
 
-/// Response from Google's tokeninfo endpoint.
+/// The claims we use from a Google ID token. `iss`, `aud` and `exp` are
+/// checked by [`Validation`].
 #[derive(Debug, Deserialize)]
-struct TokenInfoResponse {
+struct GoogleClaims {
     /// The user's unique Google ID.
-    sub: Option<String>,
-    /// The user's email address.
+    sub: String,
     email: Option<String>,
-    /// Whether the email is verified.
-    email_verified: Option<String>,
-    /// The user's full name.
+    #[serde(default)]
+    email_verified: bool,
     name: Option<String>,
-    /// URL to the user's profile picture.
     picture: Option<String>,
-    /// The audience (must match our client ID).
-    aud: Option<String>,
-}
-
-/// Verify a Google ID token by calling Google's tokeninfo endpoint.
-///
-/// Returns the user's profile information if the token is valid and the
-/// audience matches the expected client ID.
-pub async fn verify_google_token(
-    id_token: &str,
-    expected_client_id: &str,
-) -> Result<GoogleUserInfo, AppError> {
-    let url = format!(
-        "https://oauth2.googleapis.com/tokeninfo?id_token={}",
-        id_token
-    );
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to verify Google token: {e}")))?;
-
-    if !response.status().is_success() {
-        return Err(AppError::Unauthorized(
-            "Invalid Google token".to_string(),
-        ));
+}
+
+// ---------------------------------------------------------------------------
+// Key sources
+// ---------------------------------------------------------------------------
+
+/// Signing keys and, if known, how long they may be cached.
+pub struct SigningKeys {
+    pub keys: JwkSet,
+    pub max_age: Option<Duration>,
+}
+
+pub type KeysFuture<'a> = Pin<Box<dyn Future<Output = Result<SigningKeys, AppError>> + Send + 'a>>;
+
+/// Supplies the keys ID tokens are checked against. [`GoogleVerifier`] holds
+/// an `Arc<dyn KeySource>`: Google's JWKS endpoint in production, fixed keys
+/// in tests.
+pub trait KeySource: Send + Sync {
+    fn fetch(&self) -> KeysFuture<'_>;
+}
+
+/// Fetches a JWK set over HTTP, honouring the response's `Cache-Control`
+/// max-age.
+pub struct HttpKeySource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpKeySource {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+        }
     }
 
-    let info: TokenInfoResponse = response
-        .json()
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to parse Google token info: {e}")))?;
-
-    // Verify the audience matches our client ID.
-    let aud = info
-        .aud
-        .as_deref()
-        .ok_or_else(|| AppError::Unauthorized("Google token missing audience".to_string()))?;
-
-    if aud != expected_client_id {
-        return Err(AppError::Unauthorized(
-            "Google token audience mismatch".to_string(),
-        ));
+    /// Google's published ID token signing keys.
+    pub fn google() -> Self {
+        Self::new(GOOGLE_CERTS_URL)
     }
+}
+
+impl KeySource for HttpKeySource {
+    fn fetch(&self) -> KeysFuture<'_> {
+        Box::pin(async move {
+            let fetch_error =
+                |e: reqwest::Error| AppError::Internal(format!("Failed to fetch Google keys: {e}"));
+
+            let response = self
+                .client
+                .get(&self.url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(fetch_error)?;
 
-    // Verify email is present and verified.
-    let email_verified = info.email_verified.as_deref() == Some("true");
-    if !email_verified {
-        return Err(AppError::Unauthorized(
-            "Google email not verified".to_string(),
-        ));
+            let max_age = response
+                .headers()
+                .get(reqwest::header::CACHE_CONTROL)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_max_age);
+            let keys = response.json().await.map_err(fetch_error)?;
+
+            Ok(SigningKeys { keys, max_age })
+        })
     }
+}
+
+/// The `max-age` directive of a `Cache-Control` header.
+fn parse_max_age(cache_control: &str) -> Option<Duration> {
+    cache_control
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|seconds| seconds.parse().ok())
+        .map(Duration::from_secs)
+}
 
-    let google_id = info
-        .sub
-        .ok_or_else(|| AppError::Unauthorized("Google token missing subject".to_string()))?;
+/// Always returns the same keys.
+pub struct StaticKeySource {
+    keys: JwkSet,
+}
 
-    let email = info
-        .email
-        .ok_or_else(|| AppError::Unauthorized("Google token missing email".to_string()))?;
+impl StaticKeySource {
+    pub fn new(keys: JwkSet) -> Self {
+        Self { keys }
+    }
+}
+
+impl KeySource for StaticKeySource {
+    fn fetch(&self) -> KeysFuture<'_> {
+        Box::pin(async move {
+            Ok(SigningKeys {
+                keys: self.keys.clone(),
+                max_age: None,
+            })
+        })
+    }
+}
 
-    let name = info.name.unwrap_or_else(|| email.clone());
+// ---------------------------------------------------------------------------
+// Verifier
+// ---------------------------------------------------------------------------
 
-    Ok(GoogleUserInfo {
-        google_id,
-        email,
-        name,
-        picture: info.picture,
-    })
+#[derive(Default)]
+struct KeyCache {
+    keys: HashMap<String, DecodingKey>,
+    fetched_at: Option<Instant>,
+    expires_at: Option<Instant>,
+}
+
+/// Verifies Google ID tokens locally: the RS256 signature against Google's
+/// cached keys, then `iss`, `aud`, `exp` and `email_verified`. Keys are
+/// fetched on first use and when they expire; call [`GoogleVerifier::refresh`]
+/// periodically to keep them warm so logins never wait on Google.
+pub struct GoogleVerifier {
+    client_id: String,
+    source: Arc<dyn KeySource>,
+    cache: RwLock<KeyCache>,
+}
+
+impl GoogleVerifier {
+    /// `client_id` is our OAuth client ID, the audience tokens must be
+    /// issued for. Google sign-in is disabled when it is empty.
+    pub fn new(client_id: impl Into<String>, source: Arc<dyn KeySource>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            source,
+            cache: RwLock::new(KeyCache::default()),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.client_id.is_empty()
+    }
+
+    /// Fetch the current keys, replacing the cached ones, and return how long
+    /// until they should be fetched again.
+    pub async fn refresh(&self) -> Result<Duration, AppError> {
+        let fetched = self.source.fetch().await?;
+        let keys = fetched
+            .keys
+            .keys
+            .iter()
+            .filter_map(|jwk| {
+                let kid = jwk.common.key_id.clone()?;
+                Some((kid, DecodingKey::from_jwk(jwk).ok()?))
+            })
+            .collect();
+        let ttl = fetched.max_age.unwrap_or(DEFAULT_KEY_TTL);
+
+        let now = Instant::now();
+        *self.cache.write().await = KeyCache {
+            keys,
+            fetched_at: Some(now),
+            expires_at: Some(now + ttl),
+        };
+
+        Ok(ttl)
+    }
+
+    /// The key with ID `kid`, refetching if the cache has expired or doesn't
+    /// know it. If the refetch fails, a stale key is still used, so an outage
+    /// at Google doesn't stop sign-ins with keys we already have.
+    async fn key(&self, kid: &str) -> Result<DecodingKey, AppError> {
+        let stale = {
+            let cache = self.cache.read().await;
+            let now = Instant::now();
+            let fresh = cache.expires_at.is_some_and(|at| at > now);
+            match cache.keys.get(kid) {
+                Some(key) if fresh => return Ok(key.clone()),
+                None if fresh
+                    && cache
+                        .fetched_at
+                        .is_some_and(|at| now - at < MIN_REFETCH_INTERVAL) =>
+                {
+                    return Err(invalid_token());
+                }
+                key => key.cloned(),
+            }
+        };
+
+        match self.refresh().await {
+            Ok(_) => self
+                .cache
+                .read()
+                .await
+                .keys
+                .get(kid)
+                .cloned()
+                .ok_or_else(invalid_token),
+            Err(e) => stale.ok_or(e),
+        }
+    }
+
+    /// Verify a Google ID token and return the user's profile.
+    pub async fn verify(&self, id_token: &str) -> Result<GoogleUserInfo, AppError> {
+        if !self.is_configured() {
+            return Err(AppError::Unauthorized(
+                "Google sign-in is not configured".to_string(),
+            ));
+        }
+
+        let header = decode_header(id_token).map_err(|_| invalid_token())?;
+        if header.alg != Algorithm::RS256 {
+            return Err(invalid_token());
+        }
+        let kid = header.kid.ok_or_else(invalid_token)?;
+        let key = self.key(&kid).await?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&GOOGLE_ISSUERS);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<GoogleClaims>(id_token, &key, &validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => {
+                    AppError::Unauthorized("Google token has expired".to_string())
+                }
+                ErrorKind::InvalidAudience => {
+                    AppError::Unauthorized("Google token audience mismatch".to_string())
+                }
+                ErrorKind::InvalidIssuer => {
+                    AppError::Unauthorized("Google token issuer mismatch".to_string())
+                }
+                _ => invalid_token(),
+            })?
+            .claims;
+
+        if !claims.email_verified {
+            return Err(AppError::Unauthorized(
+                "Google email not verified".to_string(),
+            ));
+        }
+        let email = claims
+            .email
+            .ok_or_else(|| AppError::Unauthorized("Google token missing email".to_string()))?;
+        let name = claims.name.unwrap_or_else(|| email.clone());
+
+        Ok(GoogleUserInfo {
+            google_id: claims.sub,
+            email,
+            name,
+            picture: claims.picture,
+        })
+    }
+}
+
+fn invalid_token() -> AppError {
+    AppError::Unauthorized("Invalid Google token".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_max_age() {
+        assert_eq!(
+            parse_max_age("public, max-age=19977, must-revalidate, no-transform"),
+            Some(Duration::from_secs(19977))
+        );
+        assert_eq!(parse_max_age("no-cache"), None);
+        assert_eq!(parse_max_age("max-age=soon"), None);
+    }
 }
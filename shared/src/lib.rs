@@ -1,6 +1,24 @@
+pub mod amenities;
 pub mod auth;
+pub mod blocked_dates;
+pub mod calendar;
+pub mod cancellation;
+pub mod client_ip;
+pub mod currency;
 pub mod db;
 pub mod errors;
+pub mod geo;
 pub mod google;
+pub mod listings;
+pub mod login_attempts;
+pub mod mailer;
 pub mod models;
+pub mod notifications;
+pub mod pagination;
+pub mod payment_provider;
+pub mod payments;
+pub mod pricing;
+pub mod refresh_tokens;
+pub mod two_factor;
+pub mod user_tokens;
 pub mod utils;
@@ -0,0 +1,272 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::{ListingStatus, Property};
+use crate::notifications;
+
+/// An admin's decision on a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewDecision {
+    Approve,
+    Reject,
+}
+
+impl ReviewDecision {
+    fn status(self) -> ListingStatus {
+        match self {
+            ReviewDecision::Approve => ListingStatus::Approved,
+            ReviewDecision::Reject => ListingStatus::Rejected,
+        }
+    }
+}
+
+/// Append a row to `listing_status_history`. `from` is `None` for the status
+/// a listing is created with.
+pub async fn record_status(
+    conn: &mut PgConnection,
+    property_id: Uuid,
+    from: Option<ListingStatus>,
+    to: ListingStatus,
+    changed_by: Option<Uuid>,
+    reason: Option<&str>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"INSERT INTO listing_status_history (property_id, from_status, to_status, changed_by, reason)
+           VALUES ($1, $2, $3, $4, $5)"#,
+    )
+    .bind(property_id)
+    .bind(from)
+    .bind(to)
+    .bind(changed_by)
+    .bind(reason)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn lock_property(conn: &mut PgConnection, property_id: Uuid) -> Result<Property, AppError> {
+    sqlx::query_as("SELECT * FROM properties WHERE id = $1 FOR UPDATE")
+        .bind(property_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Property {property_id} not found")))
+}
+
+/// Move a listing to `to`, checked against
+/// [`ListingStatus::allowed_transitions`] and recorded in the history. For
+/// changes made by the owner or on their behalf, such as archiving or
+/// resubmitting; admin decisions go through [`review`].
+pub async fn change_status(
+    conn: &mut PgConnection,
+    property_id: Uuid,
+    to: ListingStatus,
+    changed_by: Uuid,
+    reason: Option<&str>,
+) -> Result<Property, AppError> {
+    let current = lock_property(conn, property_id).await?;
+    current.listing_status.check_transition(&to)?;
+
+    let property: Property = sqlx::query_as(
+        r#"UPDATE properties
+           SET listing_status = $2, updated_at = NOW()
+           WHERE id = $1
+           RETURNING *"#,
+    )
+    .bind(property_id)
+    .bind(to)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    record_status(
+        conn,
+        property_id,
+        Some(current.listing_status),
+        to,
+        Some(changed_by),
+        reason,
+    )
+    .await?;
+
+    Ok(property)
+}
+
+/// Approve or reject a listing and tell the owner. Listings awaiting review
+/// can be either; a rejected listing can later be approved, and a live one
+/// taken down by rejecting it. Drafts have not been submitted, so they
+/// cannot be reviewed.
+pub async fn review(
+    conn: &mut PgConnection,
+    property_id: Uuid,
+    decision: ReviewDecision,
+    reviewer: Uuid,
+    reason: Option<&str>,
+) -> Result<Property, AppError> {
+    let current = lock_property(conn, property_id).await?;
+    let to = decision.status();
+
+    if current.listing_status == ListingStatus::Draft {
+        return Err(AppError::Conflict(
+            "This listing is a draft and has not been submitted for review".to_string(),
+        ));
+    }
+    current.listing_status.check_transition(&to)?;
+
+    let property: Property = sqlx::query_as(
+        r#"UPDATE properties
+           SET listing_status = $2,
+               review_reason = CASE WHEN $2 = 'rejected'::listing_status THEN $3 END,
+               reviewed_by = $4, reviewed_at = NOW(), updated_at = NOW()
+           WHERE id = $1
+           RETURNING *"#,
+    )
+    .bind(property_id)
+    .bind(to)
+    .bind(reason)
+    .bind(reviewer)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    record_status(
+        conn,
+        property_id,
+        Some(current.listing_status),
+        to,
+        Some(reviewer),
+        reason,
+    )
+    .await?;
+
+    notifications::listing_reviewed(conn, property_id, &to, reason).await?;
+
+    Ok(property)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_review_records_history_and_notifies_owner() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+
+        let owner_id = Uuid::new_v4();
+        let admin_id = Uuid::new_v4();
+        let property_id = Uuid::new_v4();
+        let owner_email = format!("owner-{owner_id}@example.com");
+        for (id, email) in [
+            (owner_id, owner_email.clone()),
+            (admin_id, format!("admin-{admin_id}@example.com")),
+        ] {
+            sqlx::query(
+                "INSERT INTO users (id, email, full_name, role, email_verified) VALUES ($1, $2, 'Test', 'user', true)",
+            )
+            .bind(id)
+            .bind(email)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            r#"INSERT INTO properties (id, owner_id, title, slug, property_type, listing_type,
+                                       price, area, listing_status)
+               VALUES ($1, $2, 'Review Villa', $3, 'villa', 'sale_freehold',
+                       1000, 'Canggu', 'draft')"#,
+        )
+        .bind(property_id)
+        .bind(owner_id)
+        .bind(format!("review-villa-{property_id}"))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+
+        // Drafts are the owner's business until submitted.
+        assert!(matches!(
+            review(
+                &mut tx,
+                property_id,
+                ReviewDecision::Approve,
+                admin_id,
+                None
+            )
+            .await,
+            Err(AppError::Conflict(_))
+        ));
+        let submitted = change_status(
+            &mut tx,
+            property_id,
+            ListingStatus::PendingReview,
+            owner_id,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(!submitted.is_active);
+
+        let reason = "Photos are missing";
+        let rejected = review(
+            &mut tx,
+            property_id,
+            ReviewDecision::Reject,
+            admin_id,
+            Some(reason),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rejected.listing_status, ListingStatus::Rejected);
+        assert_eq!(rejected.review_reason.as_deref(), Some(reason));
+        assert_eq!(rejected.reviewed_by, Some(admin_id));
+
+        let approved = review(
+            &mut tx,
+            property_id,
+            ReviewDecision::Approve,
+            admin_id,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(approved.is_active);
+        assert_eq!(approved.review_reason, None);
+
+        // Approving twice is not a transition.
+        assert!(matches!(
+            review(
+                &mut tx,
+                property_id,
+                ReviewDecision::Approve,
+                admin_id,
+                None
+            )
+            .await,
+            Err(AppError::Conflict(_))
+        ));
+
+        // Everything happened in one transaction, so NOW() cannot order it.
+        let history: Vec<(Option<ListingStatus>, ListingStatus)> = sqlx::query_as(
+            "SELECT from_status, to_status FROM listing_status_history WHERE property_id = $1",
+        )
+        .bind(property_id)
+        .fetch_all(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(history.len(), 3);
+        assert!(history.contains(&(Some(ListingStatus::Rejected), ListingStatus::Approved)));
+
+        let kinds: Vec<String> = sqlx::query_scalar(
+            "SELECT kind FROM notification_outbox WHERE recipient = $1 ORDER BY kind DESC",
+        )
+        .bind(&owner_email)
+        .fetch_all(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(kinds, ["listing_rejected", "listing_approved"]);
+
+        tx.rollback().await.unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+/// Consecutive failed logins for one email before it is locked out.
+pub const MAX_FAILURES_PER_EMAIL: i64 = 5;
+/// Failed logins from one IP address, across all emails, within an hour
+/// before the address is locked out.
+pub const MAX_FAILURES_PER_IP: i64 = 20;
+
+/// Failures older than this no longer count towards an email's lockout.
+const EMAIL_WINDOW_HOURS: i64 = 24;
+const IP_WINDOW_MINUTES: i64 = 60;
+const BASE_LOCKOUT_SECONDS: i64 = 60;
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+
+/// How long to lock out after `failures` recent failures against a `limit`:
+/// one minute on reaching it, doubling with every further failure, capped at
+/// an hour.
+pub fn lockout_duration(failures: i64, limit: i64) -> Option<Duration> {
+    if failures < limit {
+        return None;
+    }
+    let doublings = (failures - limit).min(16) as u32;
+    Some(Duration::seconds(
+        (BASE_LOCKOUT_SECONDS << doublings).min(MAX_LOCKOUT_SECONDS),
+    ))
+}
+
+/// A password login in progress. It is logged as a failure when it begins, so
+/// concurrent guesses count against the limits while their passwords are
+/// still being checked and any early return leaves it a failure. Call
+/// [`LoginAttempt::succeeded`] once the user is authenticated.
+#[must_use]
+pub struct LoginAttempt {
+    id: Uuid,
+}
+
+impl LoginAttempt {
+    pub(crate) fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Mark the attempt successful, which resets the email's failure count.
+    pub async fn succeeded(self, pool: &PgPool) -> Result<(), AppError> {
+        sqlx::query("UPDATE login_attempts SET succeeded = true WHERE id = $1")
+            .bind(self.id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Start a login for `email` from `ip`, or return 429 if either is locked out
+/// by recent failures. Attempts rejected here are not logged, so waiting out a
+/// lockout is never extended by the attempts made during it.
+pub async fn begin_login_attempt(
+    pool: &PgPool,
+    email: &str,
+    ip: Option<IpAddr>,
+) -> Result<LoginAttempt, AppError> {
+    let email = email.trim().to_lowercase();
+    let ip = ip.map(|ip| ip.to_string());
+    let now = Utc::now();
+
+    // Failures since the email's last successful login.
+    let (email_failures, email_last_failure): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+        r#"SELECT COUNT(*), MAX(created_at) FROM login_attempts
+           WHERE email = $1 AND NOT succeeded AND created_at > $2
+             AND created_at > COALESCE(
+                 (SELECT MAX(created_at) FROM login_attempts WHERE email = $1 AND succeeded),
+                 '-infinity')"#,
+    )
+    .bind(&email)
+    .bind(now - Duration::hours(EMAIL_WINDOW_HOURS))
+    .fetch_one(pool)
+    .await?;
+
+    let mut lock = locked_until(email_failures, email_last_failure, MAX_FAILURES_PER_EMAIL);
+
+    if let Some(ip) = &ip {
+        let (ip_failures, ip_last_failure): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"SELECT COUNT(*), MAX(created_at) FROM login_attempts
+               WHERE ip_address = $1 AND NOT succeeded AND created_at > $2"#,
+        )
+        .bind(ip)
+        .bind(now - Duration::minutes(IP_WINDOW_MINUTES))
+        .fetch_one(pool)
+        .await?;
+
+        lock = lock.max(locked_until(
+            ip_failures,
+            ip_last_failure,
+            MAX_FAILURES_PER_IP,
+        ));
+    }
+
+    if let Some(until) = lock.filter(|until| *until > now) {
+        return Err(lockout_error(until - now));
+    }
+
+    let id = sqlx::query_scalar(
+        "INSERT INTO login_attempts (email, ip_address) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(&email)
+    .bind(&ip)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(LoginAttempt { id })
+}
+
+fn locked_until(
+    failures: i64,
+    last_failure: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Option<DateTime<Utc>> {
+    Some(last_failure? + lockout_duration(failures, limit)?)
+}
+
+fn lockout_error(remaining: Duration) -> AppError {
+    let minutes = (remaining.num_seconds() + 59) / 60;
+    let unit = if minutes == 1 { "minute" } else { "minutes" };
+    AppError::TooManyRequests(format!(
+        "Too many failed login attempts. Try again in {minutes} {unit}."
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_doubles_from_the_limit_and_caps() {
+        assert_eq!(lockout_duration(4, 5), None);
+        assert_eq!(lockout_duration(5, 5), Some(Duration::minutes(1)));
+        assert_eq!(lockout_duration(6, 5), Some(Duration::minutes(2)));
+        assert_eq!(lockout_duration(8, 5), Some(Duration::minutes(8)));
+        assert_eq!(lockout_duration(11, 5), Some(Duration::minutes(60)));
+        assert_eq!(lockout_duration(500, 5), Some(Duration::minutes(60)));
+    }
+
+    #[test]
+    fn test_lockout_error_rounds_up_to_whole_minutes() {
+        let message = |seconds| match lockout_error(Duration::seconds(seconds)) {
+            AppError::TooManyRequests(msg) => msg,
+            other => panic!("unexpected error: {other}"),
+        };
+        assert_eq!(
+            message(45),
+            "Too many failed login attempts. Try again in 1 minute."
+        );
+        assert_eq!(
+            message(61),
+            "Too many failed login attempts. Try again in 2 minutes."
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_email_locks_after_repeated_failures() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        let email = format!("{}@example.com", Uuid::new_v4());
+        let ip = Some("198.51.100.23".parse().unwrap());
+
+        for _ in 0..MAX_FAILURES_PER_EMAIL {
+            let _failed = begin_login_attempt(&pool, &email, ip).await.unwrap();
+        }
+        let locked = begin_login_attempt(&pool, &email.to_uppercase(), None).await;
+        assert!(matches!(locked, Err(AppError::TooManyRequests(_))));
+
+        // Other accounts are unaffected.
+        let other = format!("{}@example.com", Uuid::new_v4());
+        begin_login_attempt(&pool, &other, None)
+            .await
+            .unwrap()
+            .succeeded(&pool)
+            .await
+            .unwrap();
+
+        sqlx::query("DELETE FROM login_attempts WHERE email = ANY($1)")
+            .bind(vec![email, other])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::errors::AppError;
+
+/// An outgoing email. `body` is the plain-text version; when `html` is set
+/// the message is sent as multipart/alternative with both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub html: Option<String>,
+}
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<(), AppError>> + Send + 'a>>;
+
+/// Delivers emails. Handlers hold an `Arc<dyn Mailer>` so the transport is
+/// chosen at startup: SMTP in production, files or memory in development and
+/// tests.
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a>;
+}
+
+/// Pick a mailer from the environment: [`SmtpMailer`] when `SMTP_HOST` is set,
+/// or a [`FileMailer`] writing to `MAIL_DIR` for development. With neither,
+/// nobody would ever receive their verification or reset emails, so this is
+/// a configuration error rather than a silent fallback.
+pub fn from_env() -> Result<Arc<dyn Mailer>, AppError> {
+    let env = |key: &str| std::env::var(key).ok().filter(|value| !value.is_empty());
+
+    if env("SMTP_HOST").is_some() {
+        Ok(Arc::new(SmtpMailer::from_env()?))
+    } else if let Some(dir) = env("MAIL_DIR") {
+        Ok(Arc::new(FileMailer::new(dir)))
+    } else {
+        Err(AppError::Internal(
+            "No mailer configured: set SMTP_HOST, or MAIL_DIR to write emails to files in development"
+                .to_string(),
+        ))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SMTP
+// ---------------------------------------------------------------------------
+
+/// Sends through an SMTP relay.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Configure from `SMTP_HOST`, `SMTP_PORT`, `SMTP_USER`, `SMTP_PASSWORD`,
+    /// `SMTP_FROM` (e.g. `MyBaliVilla <noreply@...>`) and `SMTP_TLS`:
+    /// `starttls` (default, port 587), `tls` (port 465) or `none` (port 25),
+    /// the last for local sinks such as MailHog.
+    pub fn from_env() -> Result<Self, AppError> {
+        let env = |key: &str| std::env::var(key).unwrap_or_default();
+        let config_error = |msg: String| AppError::Internal(format!("SMTP configuration: {msg}"));
+
+        let host = env("SMTP_HOST");
+        let (mut builder, default_port) = match env("SMTP_TLS").as_str() {
+            "" | "starttls" => (
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+                    .map_err(|e| config_error(e.to_string()))?,
+                587,
+            ),
+            "tls" => (
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+                    .map_err(|e| config_error(e.to_string()))?,
+                465,
+            ),
+            "none" => (
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+                25,
+            ),
+            other => return Err(config_error(format!("invalid SMTP_TLS '{other}'"))),
+        };
+
+        let port = match env("SMTP_PORT").as_str() {
+            "" => default_port,
+            port => port
+                .parse()
+                .map_err(|_| config_error(format!("invalid SMTP_PORT '{port}'")))?,
+        };
+        let from = env("SMTP_FROM")
+            .parse()
+            .map_err(|e| config_error(format!("invalid SMTP_FROM: {e}")))?;
+
+        builder = builder.port(port);
+        if !env("SMTP_USER").is_empty() {
+            builder = builder.credentials(Credentials::new(env("SMTP_USER"), env("SMTP_PASSWORD")));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a> {
+        Box::pin(async move {
+            let to: Mailbox = email
+                .to
+                .parse()
+                .map_err(|e| AppError::BadRequest(format!("Invalid recipient address: {e}")))?;
+            let builder = Message::builder()
+                .from(self.from.clone())
+                .to(to)
+                .subject(&email.subject);
+            let message = match &email.html {
+                Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+                    email.body.clone(),
+                    html.clone(),
+                )),
+                None => builder.body(email.body.clone()),
+            }
+            .map_err(|e| AppError::Internal(format!("Failed to build email: {e}")))?;
+
+            self.transport
+                .send(message)
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to send email: {e}")))?;
+            Ok(())
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// File
+// ---------------------------------------------------------------------------
+
+/// Writes each email to its own file in a directory instead of sending it,
+/// so links in development emails can be followed without an SMTP server.
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a> {
+        Box::pin(async move {
+            let write_error = |e: std::io::Error| {
+                AppError::Internal(format!(
+                    "Failed to write email to {}: {e}",
+                    self.dir.display()
+                ))
+            };
+
+            tokio::fs::create_dir_all(&self.dir)
+                .await
+                .map_err(write_error)?;
+            let path = self.dir.join(format!(
+                "{}-{}.txt",
+                Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+                uuid::Uuid::new_v4()
+            ));
+            let mut contents = format!(
+                "To: {}\nSubject: {}\n\n{}\n",
+                email.to, email.subject, email.body
+            );
+            if let Some(html) = &email.html {
+                contents.push_str("\n----- HTML -----\n\n");
+                contents.push_str(html);
+            }
+            tokio::fs::write(path, contents).await.map_err(write_error)
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Memory
+// ---------------------------------------------------------------------------
+
+/// Keeps sent emails in memory, for tests to inspect.
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every email sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> SendFuture<'a> {
+        self.sent
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(email.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email {
+            to: "guest@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Welcome to MyBaliVilla".to_string(),
+            html: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_mailer_records_emails() {
+        let mailer = MemoryMailer::new();
+        mailer.send(&email()).await.unwrap();
+        assert_eq!(mailer.sent(), vec![email()]);
+    }
+
+    #[tokio::test]
+    async fn test_file_mailer_writes_one_file_per_email() {
+        let dir = std::env::temp_dir().join(format!("mailer-test-{}", uuid::Uuid::new_v4()));
+        let mailer: Arc<dyn Mailer> = Arc::new(FileMailer::new(&dir));
+        mailer.send(&email()).await.unwrap();
+        mailer.send(&email()).await.unwrap();
+
+        let mut entries = std::fs::read_dir(&dir).unwrap();
+        let first = entries.next().unwrap().unwrap().path();
+        let contents = std::fs::read_to_string(first).unwrap();
+        assert!(contents.starts_with("To: guest@example.com\nSubject: Hello\n"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
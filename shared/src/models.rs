@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::auth::Permission;
+use crate::errors::AppError;
+
 // ---------------------------------------------------------------------------
 // Enums
 // ---------------------------------------------------------------------------
@@ -39,7 +42,7 @@ pub enum PricePeriod {
     PerYear,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "user_role", rename_all = "snake_case")]
 #[serde(rename_all = "snake_case")]
 pub enum UserRole {
@@ -87,28 +90,32 @@ impl UserRole {
         }
     }
 
+    /// Whether this role holds `permission`, per [`Permission::roles`].
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        permission.roles().contains(self)
+    }
+
     /// Whether this role can access the admin portal.
     pub fn is_admin_portal_role(&self) -> bool {
-        matches!(self, UserRole::SuperAdmin | UserRole::Admin | UserRole::Operational)
+        self.has_permission(Permission::AdminPortal)
     }
 
     /// Whether this role can manage users (view, create, edit, deactivate).
     pub fn can_manage_users(&self) -> bool {
-        matches!(self, UserRole::SuperAdmin | UserRole::Admin)
+        self.has_permission(Permission::ManageUsers)
     }
 
     /// Whether `self` can assign the given target role to another user.
+    /// Super admins can assign any role; other user managers only roles
+    /// below their own.
     pub fn can_assign_role(&self, target: &UserRole) -> bool {
-        match self {
-            UserRole::SuperAdmin => target.is_admin_portal_role() || *target == UserRole::Agent || *target == UserRole::User,
-            UserRole::Admin => matches!(target, UserRole::Operational | UserRole::Agent | UserRole::User),
-            _ => false,
-        }
+        self.can_manage_users()
+            && (*self == UserRole::SuperAdmin || target.privilege_level() < self.privilege_level())
     }
 
     /// Whether this role can delete properties.
     pub fn can_delete_properties(&self) -> bool {
-        matches!(self, UserRole::SuperAdmin | UserRole::Admin)
+        self.has_permission(Permission::DeleteProperties)
     }
 }
 
@@ -150,11 +157,17 @@ pub struct Property {
     pub features: serde_json::Value,
     pub images: serde_json::Value,
     pub thumbnail_url: Option<String>,
+    /// Derived from `listing_status`: only approved listings are active.
     pub is_active: bool,
     pub is_featured: bool,
     pub view_count: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub listing_status: ListingStatus,
+    /// Why the listing was last rejected; cleared when it is approved.
+    pub review_reason: Option<String>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
 }
 
 impl Property {
@@ -169,6 +182,69 @@ impl Property {
     }
 }
 
+/// Where a listing is in moderation. Only `approved` listings are public.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "listing_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ListingStatus {
+    /// Being prepared by the owner; not yet submitted.
+    Draft,
+    /// Submitted and waiting for an admin.
+    PendingReview,
+    Approved,
+    /// Turned down by an admin; the owner can edit and resubmit it.
+    Rejected,
+    /// Taken off the market by the owner or an admin.
+    Archived,
+}
+
+impl ListingStatus {
+    /// The database/JSON spelling of the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListingStatus::Draft => "draft",
+            ListingStatus::PendingReview => "pending_review",
+            ListingStatus::Approved => "approved",
+            ListingStatus::Rejected => "rejected",
+            ListingStatus::Archived => "archived",
+        }
+    }
+
+    /// Statuses a listing in this status may move to next.
+    ///
+    /// Listings are submitted for review and then approved or rejected.
+    /// Owners whose listings skip review go straight to approved. Editing a
+    /// live listing can send it back for review, and an admin can reject a
+    /// live listing. Any listing can be archived and brought back later.
+    pub fn allowed_transitions(&self) -> &'static [ListingStatus] {
+        use ListingStatus::*;
+        match self {
+            Draft => &[PendingReview, Approved, Archived],
+            PendingReview => &[Approved, Rejected, Archived],
+            Approved => &[PendingReview, Rejected, Archived],
+            Rejected => &[PendingReview, Approved, Archived],
+            Archived => &[PendingReview, Approved],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: &ListingStatus) -> bool {
+        self.allowed_transitions().contains(next)
+    }
+
+    /// Check a status change against the transition table.
+    pub fn check_transition(&self, next: &ListingStatus) -> Result<(), AppError> {
+        if self.can_transition_to(next) {
+            Ok(())
+        } else {
+            Err(AppError::Conflict(format!(
+                "Cannot change listing status from {} to {}",
+                self.as_str(),
+                next.as_str()
+            )))
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // User
 // ---------------------------------------------------------------------------
@@ -189,6 +265,14 @@ pub struct User {
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing, default)]
+    pub token_version: i32,
+    #[serde(skip_serializing, default)]
+    pub totp_secret: Option<String>,
+    #[serde(skip_serializing, default)]
+    pub totp_enabled: bool,
+    #[serde(skip_serializing, default)]
+    pub totp_last_used_step: Option<i64>,
 }
 
 // ---------------------------------------------------------------------------
@@ -236,6 +320,53 @@ pub enum BookingStatus {
     Refunded,
 }
 
+impl BookingStatus {
+    /// The database/JSON spelling of the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookingStatus::Pending => "pending",
+            BookingStatus::Confirmed => "confirmed",
+            BookingStatus::CheckedIn => "checked_in",
+            BookingStatus::CheckedOut => "checked_out",
+            BookingStatus::Cancelled => "cancelled",
+            BookingStatus::Refunded => "refunded",
+        }
+    }
+
+    /// Statuses a booking in this status may move to next.
+    ///
+    /// A booking is confirmed, checked in and checked out in that order, and
+    /// can be cancelled until the guest checks in. Only a cancelled booking
+    /// can be refunded. Checked-out and refunded bookings are final.
+    pub fn allowed_transitions(&self) -> &'static [BookingStatus] {
+        match self {
+            BookingStatus::Pending => &[BookingStatus::Confirmed, BookingStatus::Cancelled],
+            BookingStatus::Confirmed => &[BookingStatus::CheckedIn, BookingStatus::Cancelled],
+            BookingStatus::CheckedIn => &[BookingStatus::CheckedOut],
+            BookingStatus::CheckedOut => &[],
+            BookingStatus::Cancelled => &[BookingStatus::Refunded],
+            BookingStatus::Refunded => &[],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: &BookingStatus) -> bool {
+        self.allowed_transitions().contains(next)
+    }
+
+    /// Check a status change against the transition table.
+    pub fn check_transition(&self, next: &BookingStatus) -> Result<(), AppError> {
+        if self.can_transition_to(next) {
+            Ok(())
+        } else {
+            Err(AppError::Conflict(format!(
+                "Cannot change booking status from {} to {}",
+                self.as_str(),
+                next.as_str()
+            )))
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "cancellation_policy_type", rename_all = "snake_case")]
 #[serde(rename_all = "snake_case")]
@@ -256,6 +387,29 @@ pub enum RentalDurationType {
     Yearly,
 }
 
+impl RentalDurationType {
+    /// Number of nights one unit of this duration covers.
+    pub fn nights(&self) -> i64 {
+        match self {
+            RentalDurationType::Nightly => 1,
+            RentalDurationType::Weekly => 7,
+            RentalDurationType::Monthly => 30,
+            RentalDurationType::Yearly => 365,
+        }
+    }
+}
+
+impl From<&PricePeriod> for RentalDurationType {
+    fn from(period: &PricePeriod) -> Self {
+        match period {
+            PricePeriod::PerNight => RentalDurationType::Nightly,
+            PricePeriod::PerWeek => RentalDurationType::Weekly,
+            PricePeriod::PerMonth => RentalDurationType::Monthly,
+            PricePeriod::PerYear => RentalDurationType::Yearly,
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Amenity
 // ---------------------------------------------------------------------------
@@ -296,6 +450,88 @@ pub struct Review {
     pub updated_at: DateTime<Utc>,
 }
 
+// ---------------------------------------------------------------------------
+// PropertyRules
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PropertyRules {
+    pub id: Uuid,
+    pub property_id: Uuid,
+    pub check_in_time: Option<chrono::NaiveTime>,
+    pub check_out_time: Option<chrono::NaiveTime>,
+    pub max_guests: Option<i32>,
+    pub pets_allowed: Option<bool>,
+    pub smoking_allowed: Option<bool>,
+    pub parties_allowed: Option<bool>,
+    pub quiet_hours_start: Option<chrono::NaiveTime>,
+    pub quiet_hours_end: Option<chrono::NaiveTime>,
+    pub custom_rules: Option<String>,
+    pub cancellation_policy: CancellationPolicyType,
+    pub cancellation_details: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PropertyRules {
+    /// Check a guest's declared stay against the house rules.
+    ///
+    /// Unset rules impose no limit, except `pets_allowed`, which defaults to
+    /// "no pets" as in the table definition.
+    pub fn check_booking(
+        &self,
+        num_guests: i32,
+        has_pets: bool,
+        arrival_time: Option<chrono::NaiveTime>,
+    ) -> Result<(), AppError> {
+        if let Some(max) = self.max_guests {
+            if num_guests > max {
+                return Err(AppError::BadRequest(format!(
+                    "This property allows at most {max} guests"
+                )));
+            }
+        }
+
+        if has_pets && self.pets_allowed != Some(true) {
+            return Err(AppError::BadRequest(
+                "Pets are not allowed at this property".to_string(),
+            ));
+        }
+
+        if let (Some(arrival), Some(check_in)) = (arrival_time, self.check_in_time) {
+            if arrival < check_in {
+                return Err(AppError::BadRequest(format!(
+                    "Check-in is from {}; arrival at {} is too early",
+                    check_in.format("%H:%M"),
+                    arrival.format("%H:%M")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PricingTier
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PricingTier {
+    pub id: Uuid,
+    pub property_id: Uuid,
+    pub duration_type: RentalDurationType,
+    pub price: Decimal,
+    pub currency: String,
+    pub min_duration: Option<i32>,
+    pub max_duration: Option<i32>,
+    pub cleaning_fee: Option<Decimal>,
+    pub service_fee_percent: Option<Decimal>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // ---------------------------------------------------------------------------
 // Booking
 // ---------------------------------------------------------------------------
@@ -322,4 +558,291 @@ pub struct Booking {
     pub cancellation_reason: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub has_pets: bool,
+    pub expected_arrival_time: Option<chrono::NaiveTime>,
+    pub refund_amount: Option<Decimal>,
+}
+
+// ---------------------------------------------------------------------------
+// Payments (migration 019)
+// ---------------------------------------------------------------------------
+
+/// What a payment covers: the whole booking up front, or a deposit now and
+/// the balance later.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "payment_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentKind {
+    Deposit,
+    Balance,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "payment_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+    /// Created at the provider, waiting for the guest to pay.
+    RequiresPayment,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "refund_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RefundStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Payment {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    pub kind: PaymentKind,
+    pub amount: Decimal,
+    pub currency: String,
+    pub provider: String,
+    pub provider_payment_id: Option<String>,
+    /// Lets the guest's browser complete the payment; only returned to the
+    /// guest when the payment is started.
+    #[serde(skip_serializing, default)]
+    pub client_secret: Option<String>,
+    pub status: PaymentStatus,
+    pub failure_reason: Option<String>,
+    pub captured_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Refund {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    pub payment_id: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
+    pub provider: String,
+    pub provider_refund_id: Option<String>,
+    pub status: RefundStatus,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ---------------------------------------------------------------------------
+// Exchange rates (migration 020)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// Units of `currency` per one unit of the base currency (USD).
+    pub rate: Decimal,
+    /// `seed`, `placeholder`, `manual` or `import`. Placeholder rates were
+    /// added by migration 020 for listing currencies it had no rate for.
+    pub source: String,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ---------------------------------------------------------------------------
+// Blocked dates
+// ---------------------------------------------------------------------------
+
+/// A range of dates an owner has taken off the market, by hand or through a
+/// calendar import. Both `start_date` and `end_date` are blocked (the range
+/// is inclusive).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BlockedDate {
+    pub id: Uuid,
+    pub property_id: Uuid,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// The calendar feed this block was imported from, or `None` when it was
+    /// created by hand.
+    pub source: Option<String>,
+    /// `UID` of the imported calendar event.
+    pub external_uid: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Conversation & Message
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Conversation {
+    pub id: Uuid,
+    pub property_id: Option<Uuid>,
+    pub participant_1: Uuid,
+    pub participant_2: Uuid,
+    pub last_message_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Conversation {
+    /// Whether `user_id` is one of the two participants.
+    pub fn has_participant(&self, user_id: Uuid) -> bool {
+        self.participant_1 == user_id || self.participant_2 == user_id
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Message {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub sender_id: Uuid,
+    pub content: String,
+    pub is_read: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+
+    fn rules() -> PropertyRules {
+        PropertyRules {
+            id: Uuid::new_v4(),
+            property_id: Uuid::new_v4(),
+            check_in_time: NaiveTime::from_hms_opt(14, 0, 0),
+            check_out_time: NaiveTime::from_hms_opt(11, 0, 0),
+            max_guests: Some(2),
+            pets_allowed: Some(false),
+            smoking_allowed: None,
+            parties_allowed: None,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            custom_rules: None,
+            cancellation_policy: CancellationPolicyType::Moderate,
+            cancellation_details: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_booking_status_happy_path() {
+        use BookingStatus::*;
+        let path = [Pending, Confirmed, CheckedIn, CheckedOut];
+        for pair in path.windows(2) {
+            assert!(pair[0].check_transition(&pair[1]).is_ok());
+        }
+        assert!(Confirmed.check_transition(&Cancelled).is_ok());
+        assert!(Cancelled.check_transition(&Refunded).is_ok());
+    }
+
+    #[test]
+    fn test_booking_status_illegal_transitions() {
+        use BookingStatus::*;
+        for (from, to) in [
+            (Cancelled, CheckedIn),
+            (Pending, Refunded),
+            (CheckedIn, Cancelled),
+            (CheckedOut, Pending),
+            (Refunded, Cancelled),
+            (Confirmed, Confirmed),
+        ] {
+            match from.check_transition(&to) {
+                Err(AppError::Conflict(msg)) => assert_eq!(
+                    msg,
+                    format!(
+                        "Cannot change booking status from {} to {}",
+                        from.as_str(),
+                        to.as_str()
+                    )
+                ),
+                other => panic!("{from:?} -> {to:?}: unexpected {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_listing_status_review_path() {
+        use ListingStatus::*;
+        for path in [
+            [Draft, PendingReview, Approved],
+            [Approved, PendingReview, Rejected],
+            [Rejected, PendingReview, Approved],
+            [Approved, Archived, PendingReview],
+        ] {
+            for pair in path.windows(2) {
+                assert!(pair[0].check_transition(&pair[1]).is_ok(), "{pair:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_listing_status_illegal_transitions() {
+        use ListingStatus::*;
+        for (from, to) in [
+            (Draft, Rejected),
+            (Rejected, Draft),
+            (Approved, Approved),
+            (Archived, Rejected),
+            (PendingReview, Draft),
+        ] {
+            match from.check_transition(&to) {
+                Err(AppError::Conflict(msg)) => assert_eq!(
+                    msg,
+                    format!(
+                        "Cannot change listing status from {} to {}",
+                        from.as_str(),
+                        to.as_str()
+                    )
+                ),
+                other => panic!("{from:?} -> {to:?}: unexpected {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_role_assignment() {
+        use UserRole::*;
+        let all = [SuperAdmin, Admin, Operational, Agent, User];
+        let assignable = |role: UserRole| {
+            all.into_iter()
+                .filter(|target| role.can_assign_role(target))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(assignable(SuperAdmin), all);
+        assert_eq!(assignable(Admin), [Operational, Agent, User]);
+        assert!(assignable(Operational).is_empty());
+        assert!(assignable(Agent).is_empty());
+        assert!(assignable(User).is_empty());
+    }
+
+    #[test]
+    fn test_booking_within_rules() {
+        let arrival = NaiveTime::from_hms_opt(15, 30, 0);
+        assert!(rules().check_booking(2, false, arrival).is_ok());
+        assert!(rules().check_booking(1, false, None).is_ok());
+    }
+
+    #[test]
+    fn test_booking_rule_violations() {
+        assert!(rules().check_booking(3, false, None).is_err());
+        assert!(rules().check_booking(2, true, None).is_err());
+        assert!(rules()
+            .check_booking(2, false, NaiveTime::from_hms_opt(9, 0, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_unset_rules_do_not_limit() {
+        let mut open = rules();
+        open.max_guests = None;
+        open.pets_allowed = Some(true);
+        open.check_in_time = None;
+        assert!(open
+            .check_booking(20, true, NaiveTime::from_hms_opt(6, 0, 0))
+            .is_ok());
+    }
 }
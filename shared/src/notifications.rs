@@ -0,0 +1,733 @@
+use std::sync::{Arc, LazyLock};
+
+use chrono::{Duration, NaiveDate};
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgConnection, PgPool};
+use tera::{Context, Tera};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::mailer::{Email, Mailer};
+use crate::models::{BookingStatus, ListingStatus};
+
+/// Deliveries given up after this many failed attempts are marked `failed`.
+pub const MAX_ATTEMPTS: i32 = 8;
+
+/// How long a claimed notification is reserved for the worker that claimed
+/// it. If that worker dies mid-send, another picks the row up afterwards.
+const CLAIM_LEASE_SECONDS: i32 = 300;
+
+/// The emails the platform sends. Each has `<name>.html` and `<name>.txt`
+/// templates in `shared/templates/notifications`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// To the guest, when they request a booking.
+    BookingCreatedGuest,
+    /// To the property owner, when a guest requests a booking.
+    BookingCreatedHost,
+    /// To the guest, when their booking is confirmed.
+    BookingConfirmed,
+    /// To both guest and owner, when a booking is cancelled.
+    BookingCancelled,
+    /// To the property owner, when someone sends an inquiry.
+    InquiryReceived,
+    /// To the property owner, when a review of the property is approved.
+    ReviewPublished,
+    /// To the property owner, when an admin approves their listing.
+    ListingApproved,
+    /// To the property owner, when an admin rejects their listing.
+    ListingRejected,
+}
+
+impl NotificationKind {
+    const ALL: [NotificationKind; 8] = [
+        NotificationKind::BookingCreatedGuest,
+        NotificationKind::BookingCreatedHost,
+        NotificationKind::BookingConfirmed,
+        NotificationKind::BookingCancelled,
+        NotificationKind::InquiryReceived,
+        NotificationKind::ReviewPublished,
+        NotificationKind::ListingApproved,
+        NotificationKind::ListingRejected,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::BookingCreatedGuest => "booking_created_guest",
+            NotificationKind::BookingCreatedHost => "booking_created_host",
+            NotificationKind::BookingConfirmed => "booking_confirmed",
+            NotificationKind::BookingCancelled => "booking_cancelled",
+            NotificationKind::InquiryReceived => "inquiry_received",
+            NotificationKind::ReviewPublished => "review_published",
+            NotificationKind::ListingApproved => "listing_approved",
+            NotificationKind::ListingRejected => "listing_rejected",
+        }
+    }
+
+    fn subject(self) -> &'static str {
+        match self {
+            NotificationKind::BookingCreatedGuest => {
+                "Your booking request for {{ property_title }}"
+            }
+            NotificationKind::BookingCreatedHost => "New booking request for {{ property_title }}",
+            NotificationKind::BookingConfirmed => "Your stay at {{ property_title }} is confirmed",
+            NotificationKind::BookingCancelled => "Booking cancelled: {{ property_title }}",
+            NotificationKind::InquiryReceived => "New inquiry about {{ property_title }}",
+            NotificationKind::ReviewPublished => "New review of {{ property_title }}",
+            NotificationKind::ListingApproved => "{{ property_title }} is now live",
+            NotificationKind::ListingRejected => "{{ property_title }} needs changes",
+        }
+    }
+}
+
+impl std::str::FromStr for NotificationKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| AppError::Internal(format!("Unknown notification kind '{s}'")))
+    }
+}
+
+macro_rules! template {
+    ($name:literal) => {
+        (
+            $name,
+            include_str!(concat!("../templates/notifications/", $name)),
+        )
+    };
+}
+
+/// Compiled once; the templates are embedded in the binary so deployments
+/// need no template directory.
+static TEMPLATES: LazyLock<Tera> = LazyLock::new(|| {
+    let mut tera = Tera::default();
+    tera.add_raw_templates([
+        template!("base.html"),
+        template!("booking_details.html"),
+        template!("booking_details.txt"),
+        template!("booking_created_guest.html"),
+        template!("booking_created_guest.txt"),
+        template!("booking_created_host.html"),
+        template!("booking_created_host.txt"),
+        template!("booking_confirmed.html"),
+        template!("booking_confirmed.txt"),
+        template!("booking_cancelled.html"),
+        template!("booking_cancelled.txt"),
+        template!("inquiry_received.html"),
+        template!("inquiry_received.txt"),
+        template!("review_published.html"),
+        template!("review_published.txt"),
+        template!("listing_approved.html"),
+        template!("listing_approved.txt"),
+        template!("listing_rejected.html"),
+        template!("listing_rejected.txt"),
+    ])
+    .expect("notification templates are valid");
+    for kind in NotificationKind::ALL {
+        tera.add_raw_template(&format!("{}.subject", kind.as_str()), kind.subject())
+            .expect("notification subjects are valid");
+    }
+    tera
+});
+
+/// Render a notification into an email. `context` is what was stored in the
+/// outbox; `app_url` is added for links.
+pub fn render(
+    kind: NotificationKind,
+    recipient: &str,
+    context: &Value,
+    app_url: &str,
+) -> Result<Email, AppError> {
+    let mut ctx = Context::from_value(context.clone())
+        .map_err(|e| AppError::Internal(format!("Invalid notification context: {e}")))?;
+    ctx.insert("app_url", app_url.trim_end_matches('/'));
+
+    let render = |suffix: &str| {
+        TEMPLATES
+            .render(&format!("{}.{suffix}", kind.as_str()), &ctx)
+            .map_err(|e| AppError::Internal(format!("Failed to render {}: {e:?}", kind.as_str())))
+    };
+
+    Ok(Email {
+        to: recipient.to_string(),
+        subject: render("subject")?,
+        body: render("txt")?,
+        html: Some(render("html")?),
+    })
+}
+
+/// Queue a notification. Call it on the caller's transaction so the email is
+/// only sent if the change it reports is committed.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    kind: NotificationKind,
+    recipient: &str,
+    context: Value,
+) -> Result<(), AppError> {
+    sqlx::query("INSERT INTO notification_outbox (kind, recipient, context) VALUES ($1, $2, $3)")
+        .bind(kind.as_str())
+        .bind(recipient)
+        .bind(context)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Events
+// ---------------------------------------------------------------------------
+
+#[derive(FromRow)]
+struct BookingNotice {
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    num_guests: i32,
+    total_price: Decimal,
+    currency: String,
+    special_requests: Option<String>,
+    cancellation_reason: Option<String>,
+    refund_amount: Option<Decimal>,
+    property_title: String,
+    property_slug: String,
+    guest_name: String,
+    guest_email: String,
+    host_name: String,
+    host_email: String,
+}
+
+impl BookingNotice {
+    async fn load(conn: &mut PgConnection, booking_id: Uuid) -> Result<Self, AppError> {
+        sqlx::query_as(
+            r#"SELECT b.check_in, b.check_out, b.num_guests, b.total_price, b.currency,
+                      b.special_requests, b.cancellation_reason, b.refund_amount,
+                      p.title AS property_title, p.slug AS property_slug,
+                      g.full_name AS guest_name, g.email AS guest_email,
+                      h.full_name AS host_name, h.email AS host_email
+               FROM bookings b
+               JOIN properties p ON p.id = b.property_id
+               JOIN users g ON g.id = b.guest_id
+               JOIN users h ON h.id = p.owner_id
+               WHERE b.id = $1"#,
+        )
+        .bind(booking_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Booking {booking_id} not found")))
+    }
+
+    fn context(&self, recipient_name: &str) -> Value {
+        json!({
+            "recipient_name": recipient_name,
+            "property_title": self.property_title,
+            "property_slug": self.property_slug,
+            "guest_name": self.guest_name,
+            "host_name": self.host_name,
+            "check_in": format_date(self.check_in),
+            "check_out": format_date(self.check_out),
+            "num_guests": self.num_guests,
+            "total_price": self.total_price.to_string(),
+            "currency": self.currency,
+            "special_requests": self.special_requests,
+            "cancellation_reason": self.cancellation_reason,
+            "refund_amount": self.refund_amount.map(|amount| amount.to_string()),
+        })
+    }
+}
+
+/// Tell the guest their request was received and the owner that it arrived.
+pub async fn booking_created(conn: &mut PgConnection, booking_id: Uuid) -> Result<(), AppError> {
+    let notice = BookingNotice::load(conn, booking_id).await?;
+
+    enqueue(
+        conn,
+        NotificationKind::BookingCreatedGuest,
+        &notice.guest_email,
+        notice.context(&notice.guest_name),
+    )
+    .await?;
+    enqueue(
+        conn,
+        NotificationKind::BookingCreatedHost,
+        &notice.host_email,
+        notice.context(&notice.host_name),
+    )
+    .await
+}
+
+/// Report a booking's move to `status`. Confirmations go to the guest and
+/// cancellations to both guest and owner; other changes send nothing.
+pub async fn booking_status_changed(
+    conn: &mut PgConnection,
+    booking_id: Uuid,
+    status: &BookingStatus,
+) -> Result<(), AppError> {
+    if !matches!(status, BookingStatus::Confirmed | BookingStatus::Cancelled) {
+        return Ok(());
+    }
+
+    let notice = BookingNotice::load(conn, booking_id).await?;
+
+    if *status == BookingStatus::Confirmed {
+        return enqueue(
+            conn,
+            NotificationKind::BookingConfirmed,
+            &notice.guest_email,
+            notice.context(&notice.guest_name),
+        )
+        .await;
+    }
+
+    for (email, name) in [
+        (&notice.guest_email, &notice.guest_name),
+        (&notice.host_email, &notice.host_name),
+    ] {
+        enqueue(
+            conn,
+            NotificationKind::BookingCancelled,
+            email,
+            notice.context(name),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Forward a new inquiry to the property owner.
+pub async fn inquiry_received(conn: &mut PgConnection, inquiry_id: Uuid) -> Result<(), AppError> {
+    let (name, email, phone, message, title, slug, owner_name, owner_email): (
+        String,
+        String,
+        Option<String>,
+        String,
+        String,
+        String,
+        String,
+        String,
+    ) = sqlx::query_as(
+        r#"SELECT i.name, i.email, i.phone, i.message, p.title, p.slug,
+                  o.full_name, o.email
+           FROM inquiries i
+           JOIN properties p ON p.id = i.property_id
+           JOIN users o ON o.id = p.owner_id
+           WHERE i.id = $1"#,
+    )
+    .bind(inquiry_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Inquiry {inquiry_id} not found")))?;
+
+    enqueue(
+        conn,
+        NotificationKind::InquiryReceived,
+        &owner_email,
+        json!({
+            "recipient_name": owner_name,
+            "property_title": title,
+            "property_slug": slug,
+            "inquirer_name": name,
+            "inquirer_email": email,
+            "inquirer_phone": phone,
+            "message": message,
+        }),
+    )
+    .await
+}
+
+/// Tell the property owner a review of their property has gone live.
+pub async fn review_published(conn: &mut PgConnection, review_id: Uuid) -> Result<(), AppError> {
+    let (rating, review_title, comment, reviewer_name, title, slug, owner_name, owner_email): (
+        i16,
+        Option<String>,
+        String,
+        String,
+        String,
+        String,
+        String,
+        String,
+    ) = sqlx::query_as(
+        r#"SELECT r.overall_rating, r.title, r.comment, u.full_name, p.title, p.slug,
+                  o.full_name, o.email
+           FROM reviews r
+           JOIN users u ON u.id = r.user_id
+           JOIN properties p ON p.id = r.property_id
+           JOIN users o ON o.id = p.owner_id
+           WHERE r.id = $1"#,
+    )
+    .bind(review_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Review {review_id} not found")))?;
+
+    enqueue(
+        conn,
+        NotificationKind::ReviewPublished,
+        &owner_email,
+        json!({
+            "recipient_name": owner_name,
+            "property_title": title,
+            "property_slug": slug,
+            "reviewer_name": reviewer_name,
+            "rating": rating,
+            "review_title": review_title,
+            "comment": comment,
+        }),
+    )
+    .await
+}
+
+/// Tell the property owner an admin has approved or rejected their listing,
+/// with the admin's reason. Other statuses send nothing.
+pub async fn listing_reviewed(
+    conn: &mut PgConnection,
+    property_id: Uuid,
+    status: &ListingStatus,
+    reason: Option<&str>,
+) -> Result<(), AppError> {
+    let kind = match status {
+        ListingStatus::Approved => NotificationKind::ListingApproved,
+        ListingStatus::Rejected => NotificationKind::ListingRejected,
+        _ => return Ok(()),
+    };
+
+    let (title, slug, owner_name, owner_email): (String, String, String, String) = sqlx::query_as(
+        r#"SELECT p.title, p.slug, o.full_name, o.email
+               FROM properties p
+               JOIN users o ON o.id = p.owner_id
+               WHERE p.id = $1"#,
+    )
+    .bind(property_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Property {property_id} not found")))?;
+
+    enqueue(
+        conn,
+        kind,
+        &owner_email,
+        json!({
+            "recipient_name": owner_name,
+            "property_title": title,
+            "property_slug": slug,
+            "reason": reason,
+        }),
+    )
+    .await
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%a %-d %b %Y").to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Delivery
+// ---------------------------------------------------------------------------
+
+#[derive(FromRow)]
+struct OutboxRow {
+    id: Uuid,
+    kind: String,
+    recipient: String,
+    context: Value,
+    attempts: i32,
+}
+
+/// Delivers queued notifications. Several workers may run at once (one per
+/// API instance); each row is claimed by exactly one of them.
+pub struct OutboxWorker {
+    pool: PgPool,
+    mailer: Arc<dyn Mailer>,
+    app_url: String,
+    batch_size: i64,
+}
+
+impl OutboxWorker {
+    pub fn new(pool: PgPool, mailer: Arc<dyn Mailer>, app_url: impl Into<String>) -> Self {
+        Self {
+            pool,
+            mailer,
+            app_url: app_url.into(),
+            batch_size: 20,
+        }
+    }
+
+    /// Deliver everything that is due, one batch at a time. Call it on a
+    /// timer. Returns how many notifications were attempted; failed sends
+    /// are recorded on their rows, so only database errors are returned.
+    pub async fn drain(&self) -> Result<usize, AppError> {
+        let mut total = 0;
+        loop {
+            let claimed = self.deliver_due().await?;
+            total += claimed;
+            if claimed < self.batch_size as usize {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Claim and send one batch of due notifications. Returns how many were
+    /// claimed. A failed send is rescheduled with exponential backoff, or
+    /// marked `failed` after [`MAX_ATTEMPTS`].
+    pub async fn deliver_due(&self) -> Result<usize, AppError> {
+        let rows: Vec<OutboxRow> = sqlx::query_as(
+            r#"UPDATE notification_outbox
+               SET attempts = attempts + 1,
+                   next_attempt_at = NOW() + make_interval(secs => $2)
+               WHERE id IN (
+                   SELECT id FROM notification_outbox
+                   WHERE status = 'pending' AND next_attempt_at <= NOW()
+                   ORDER BY next_attempt_at
+                   LIMIT $1
+                   FOR UPDATE SKIP LOCKED
+               )
+               RETURNING id, kind, recipient, context, attempts"#,
+        )
+        .bind(self.batch_size)
+        .bind(CLAIM_LEASE_SECONDS)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in &rows {
+            let result = match row.kind.parse() {
+                Ok(kind) => match render(kind, &row.recipient, &row.context, &self.app_url) {
+                    Ok(email) => self.mailer.send(&email).await,
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(()) => {
+                    sqlx::query(
+                        r#"UPDATE notification_outbox
+                           SET status = 'sent', sent_at = NOW(), last_error = NULL
+                           WHERE id = $1"#,
+                    )
+                    .bind(row.id)
+                    .execute(&self.pool)
+                    .await?;
+                }
+                Err(e) => {
+                    let failed = row.attempts >= MAX_ATTEMPTS;
+                    sqlx::query(
+                        r#"UPDATE notification_outbox
+                           SET status = CASE WHEN $2 THEN 'failed' ELSE 'pending' END,
+                               next_attempt_at = NOW() + make_interval(secs => $3),
+                               last_error = $4
+                           WHERE id = $1"#,
+                    )
+                    .bind(row.id)
+                    .bind(failed)
+                    .bind(retry_delay(row.attempts).num_seconds() as f64)
+                    .bind(e.to_string())
+                    .execute(&self.pool)
+                    .await?;
+                }
+            }
+        }
+
+        Ok(rows.len())
+    }
+}
+
+/// Wait before the next attempt after `attempts` failures: one minute,
+/// doubling each time, capped at six hours.
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 16) - 1;
+    Duration::minutes(1i64 << exponent).min(Duration::hours(6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn booking_context() -> Value {
+        json!({
+            "recipient_name": "Made",
+            "property_title": "Villa <Sunset>",
+            "property_slug": "villa-sunset",
+            "guest_name": "Ayu",
+            "host_name": "Made",
+            "check_in": "Sat 10 Jan 2029",
+            "check_out": "Mon 12 Jan 2029",
+            "num_guests": 2,
+            "total_price": "450.00",
+            "currency": "USD",
+            "special_requests": "Late arrival",
+            "cancellation_reason": null,
+            "refund_amount": "450.00",
+        })
+    }
+
+    #[test]
+    fn test_every_kind_renders() {
+        let review = json!({
+            "recipient_name": "Made",
+            "property_title": "Villa Sunset",
+            "property_slug": "villa-sunset",
+            "reviewer_name": "Ayu",
+            "rating": 5,
+            "review_title": null,
+            "comment": "Lovely",
+        });
+        let inquiry = json!({
+            "recipient_name": "Made",
+            "property_title": "Villa Sunset",
+            "property_slug": "villa-sunset",
+            "inquirer_name": "Ayu",
+            "inquirer_email": "ayu@example.com",
+            "inquirer_phone": null,
+            "message": "Is it available in May?",
+        });
+
+        let listing = json!({
+            "recipient_name": "Made",
+            "property_title": "Villa Sunset",
+            "property_slug": "villa-sunset",
+            "reason": "Please add photos of the bedrooms",
+        });
+
+        let booking = booking_context();
+        for kind in NotificationKind::ALL {
+            let context = match kind {
+                NotificationKind::InquiryReceived => &inquiry,
+                NotificationKind::ReviewPublished => &review,
+                NotificationKind::ListingApproved | NotificationKind::ListingRejected => &listing,
+                _ => &booking,
+            };
+            let email = render(kind, "made@example.com", context, "https://example.com/")
+                .unwrap_or_else(|e| panic!("{}: {e}", kind.as_str()));
+
+            assert!(!email.subject.is_empty());
+            assert!(email.body.starts_with("Hi Made,"), "{}", kind.as_str());
+            // Rejected listings are not public, so that email links to the
+            // owner's account instead.
+            let link = match kind {
+                NotificationKind::ListingRejected => "https://example.com/profile",
+                _ => "https://example.com/properties/villa-sunset",
+            };
+            assert!(email.html.unwrap().contains(link), "{}", kind.as_str());
+            assert_eq!(kind.as_str().parse::<NotificationKind>().unwrap(), kind);
+        }
+    }
+
+    #[test]
+    fn test_html_is_escaped_but_text_is_not() {
+        let email = render(
+            NotificationKind::BookingCreatedHost,
+            "made@example.com",
+            &booking_context(),
+            "https://example.com",
+        )
+        .unwrap();
+
+        assert_eq!(email.subject, "New booking request for Villa <Sunset>");
+        assert!(email.body.contains("Villa <Sunset>"));
+        assert!(email.body.contains("Late arrival"));
+        let html = email.html.unwrap();
+        assert!(html.contains("Villa &lt;Sunset&gt;"));
+        assert!(!html.contains("Villa <Sunset>"));
+    }
+
+    async fn queue_test_notification(pool: &PgPool) -> Uuid {
+        let recipient = format!("{}@example.com", Uuid::new_v4());
+        let mut conn = pool.acquire().await.unwrap();
+        enqueue(
+            &mut conn,
+            NotificationKind::BookingConfirmed,
+            &recipient,
+            booking_context(),
+        )
+        .await
+        .unwrap();
+
+        sqlx::query_scalar("SELECT id FROM notification_outbox WHERE recipient = $1")
+            .bind(&recipient)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Runs the worker against a real SMTP server. Start MailHog with
+    /// `docker compose up mailhog`, then run with `SMTP_HOST=localhost
+    /// SMTP_PORT=1025 SMTP_TLS=none SMTP_FROM=test@example.com` and
+    /// `DATABASE_URL` set; the email shows up at http://localhost:8025.
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL and an SMTP sink such as MailHog"]
+    async fn test_worker_delivers_through_smtp() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        let id = queue_test_notification(&pool).await;
+
+        let mailer = Arc::new(crate::mailer::SmtpMailer::from_env().unwrap());
+        OutboxWorker::new(pool.clone(), mailer, "http://localhost:3000")
+            .drain()
+            .await
+            .unwrap();
+
+        let (status, attempts): (String, i32) =
+            sqlx::query_as("SELECT status, attempts FROM notification_outbox WHERE id = $1")
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((status.as_str(), attempts), ("sent", 1));
+    }
+
+    struct FailingMailer;
+
+    impl Mailer for FailingMailer {
+        fn send<'a>(&'a self, _email: &'a Email) -> crate::mailer::SendFuture<'a> {
+            Box::pin(async { Err(AppError::Internal("SMTP server unavailable".to_string())) })
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_failed_delivery_is_retried_later() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        let id = queue_test_notification(&pool).await;
+
+        OutboxWorker::new(
+            pool.clone(),
+            Arc::new(FailingMailer),
+            "http://localhost:3000",
+        )
+        .drain()
+        .await
+        .unwrap();
+
+        let (status, attempts, last_error, retry_in): (String, i32, Option<String>, f64) =
+            sqlx::query_as(
+                r#"SELECT status, attempts, last_error,
+                          EXTRACT(EPOCH FROM next_attempt_at - NOW())::float8
+                   FROM notification_outbox WHERE id = $1"#,
+            )
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((status.as_str(), attempts), ("pending", 1));
+        assert!(last_error.unwrap().contains("SMTP server unavailable"));
+        assert!((50.0..=60.0).contains(&retry_in), "retry in {retry_in}s");
+
+        sqlx::query("DELETE FROM notification_outbox WHERE id = $1")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_and_caps() {
+        assert_eq!(retry_delay(1), Duration::minutes(1));
+        assert_eq!(retry_delay(2), Duration::minutes(2));
+        assert_eq!(retry_delay(4), Duration::minutes(8));
+        assert_eq!(retry_delay(MAX_ATTEMPTS + 10), Duration::hours(6));
+    }
+}
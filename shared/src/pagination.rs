@@ -0,0 +1,229 @@
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// Page selection for list endpoints.
+///
+/// Either numbered pages (`page`/`per_page`, counted with `OFFSET`) or, when
+/// `cursor` is given, the page after the one that returned that cursor.
+/// Cursor pages use keyset pagination on the sort key plus `id`, so they stay
+/// fast however deep the client scrolls and never repeat or skip rows when
+/// new ones are inserted.
+#[derive(Debug, Default, Deserialize)]
+pub struct PaginationParams {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    /// `next_cursor` from the previous page. Takes precedence over `page`.
+    pub cursor: Option<String>,
+}
+
+impl PaginationParams {
+    pub fn offset(&self) -> i64 {
+        if self.cursor.is_some() {
+            0
+        } else {
+            (self.current_page() - 1) * self.limit()
+        }
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    pub fn current_page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// Whether this is a cursor page. Cursor pages skip the total count.
+    pub fn is_cursor(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// The sort key and id to continue after, for a listing sorted by
+    /// `sort`. A cursor issued for a different sort order is rejected.
+    pub fn after<K: FromStr>(&self, sort: &str) -> Result<Option<(K, Uuid)>, AppError> {
+        let Some(ref encoded) = self.cursor else {
+            return Ok(None);
+        };
+
+        let cursor = Cursor::decode(encoded)?;
+        if cursor.sort != sort {
+            return Err(AppError::BadRequest(
+                "Cursor was issued for a different sort order".to_string(),
+            ));
+        }
+        let key = cursor.key.parse().map_err(|_| invalid_cursor())?;
+
+        Ok(Some((key, cursor.id)))
+    }
+}
+
+/// The position after the last item of a page. Clients only ever see it
+/// encoded, as an opaque string.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Cursor {
+    #[serde(rename = "s")]
+    sort: String,
+    #[serde(rename = "k")]
+    key: String,
+    #[serde(rename = "i")]
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        // Serialising a struct of strings and a UUID cannot fail.
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(encoded: &str) -> Result<Self, AppError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| invalid_cursor())?;
+        serde_json::from_slice(&bytes).map_err(|_| invalid_cursor())
+    }
+}
+
+fn invalid_cursor() -> AppError {
+    AppError::BadRequest("Invalid cursor".to_string())
+}
+
+/// Finish a page fetched with `LIMIT limit + 1`: drop the extra row and, if
+/// there was one, return the cursor for the next page. `key` gives an item's
+/// sort key (in a form `FromStr` reads back) and id.
+pub fn next_cursor<T>(
+    items: &mut Vec<T>,
+    limit: i64,
+    sort: &str,
+    key: impl Fn(&T) -> (String, Uuid),
+) -> Option<String> {
+    try_next_cursor(items, limit, sort, |item| {
+        Ok::<_, std::convert::Infallible>(key(item))
+    })
+    .unwrap_or_else(|never| match never {})
+}
+
+/// [`next_cursor`] for sort keys that may be missing from an item, such as a
+/// computed column. Fails rather than encode a cursor the next page's query
+/// would compare against the wrong value.
+pub fn try_next_cursor<T, E>(
+    items: &mut Vec<T>,
+    limit: i64,
+    sort: &str,
+    key: impl Fn(&T) -> Result<(String, Uuid), E>,
+) -> Result<Option<String>, E> {
+    let limit = usize::try_from(limit).unwrap_or(0);
+    if items.len() <= limit {
+        return Ok(None);
+    }
+    items.truncate(limit);
+
+    let Some(last) = items.last() else {
+        return Ok(None);
+    };
+    let (key, id) = key(last)?;
+    Ok(Some(
+        Cursor {
+            sort: sort.to_string(),
+            key,
+            id,
+        }
+        .encode(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn cursor_params(cursor: Option<String>) -> PaginationParams {
+        PaginationParams {
+            page: Some(3),
+            per_page: Some(2),
+            cursor,
+        }
+    }
+
+    #[test]
+    fn test_page_numbers_without_cursor() {
+        let params = cursor_params(None);
+        assert_eq!((params.offset(), params.limit()), (4, 2));
+        assert_eq!(params.after::<i32>("created_at").unwrap(), None);
+
+        let defaults = PaginationParams::default();
+        assert_eq!((defaults.offset(), defaults.limit()), (0, DEFAULT_PER_PAGE));
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let created_at = Utc.with_ymd_and_hms(2026, 3, 1, 9, 30, 15).unwrap();
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut items: Vec<(DateTime<Utc>, Uuid)> =
+            ids.iter().map(|id| (created_at, *id)).collect();
+
+        let next = next_cursor(&mut items, 2, "created_at", |(at, id)| {
+            (at.to_rfc3339(), *id)
+        });
+        assert_eq!(items.len(), 2);
+
+        let params = cursor_params(next);
+        assert_eq!(params.offset(), 0);
+        assert_eq!(
+            params.after::<DateTime<Utc>>("created_at").unwrap(),
+            Some((created_at, ids[1]))
+        );
+    }
+
+    #[test]
+    fn test_last_page_has_no_cursor() {
+        let mut items = vec![(1, Uuid::new_v4()), (2, Uuid::new_v4())];
+        assert_eq!(
+            next_cursor(&mut items, 2, "views", |(v, id)| (v.to_string(), *id)),
+            None
+        );
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_bad_or_mismatched_cursors() {
+        let mut items = vec![(1, Uuid::new_v4()), (2, Uuid::new_v4())];
+        let next = next_cursor(&mut items, 1, "views", |(v, id)| (v.to_string(), *id));
+
+        let params = cursor_params(next);
+        assert!(params.after::<i32>("price_asc").is_err());
+        assert!(params.after::<DateTime<Utc>>("views").is_err());
+
+        assert!(cursor_params(Some("not-a-cursor".to_string()))
+            .after::<i32>("views")
+            .is_err());
+    }
+
+    #[test]
+    fn test_missing_sort_key_fails() {
+        let mut items = vec![(Some(1), Uuid::new_v4()), (None, Uuid::new_v4())];
+        let key = |(v, id): &(Option<i32>, Uuid)| v.map(|v| (v.to_string(), *id)).ok_or("no key");
+
+        assert_eq!(
+            try_next_cursor(&mut items.clone(), 2, "views", key),
+            Ok(None)
+        );
+        assert_eq!(
+            try_next_cursor(&mut items, 1, "views", key).map(|c| c.is_some()),
+            Ok(true)
+        );
+
+        let mut items = vec![(None, Uuid::new_v4()), (Some(2), Uuid::new_v4())];
+        assert_eq!(try_next_cursor(&mut items, 1, "views", key), Err("no key"));
+    }
+}
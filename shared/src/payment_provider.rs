@@ -0,0 +1,620 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderValue};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::RefundStatus;
+
+/// Header carrying the webhook signature, `t=<unix time>,v1=<hex HMAC>`.
+pub const SIGNATURE_HEADER: &str = "stripe-signature";
+
+/// Webhooks signed longer ago than this are rejected as possible replays.
+const SIGNATURE_TOLERANCE_SECONDS: i64 = 5 * 60;
+
+const STRIPE_API_BASE: &str = "https://api.stripe.com/v1";
+
+/// Currencies Stripe takes in whole units rather than cents.
+const ZERO_DECIMAL_CURRENCIES: [&str; 16] = [
+    "BIF", "CLP", "DJF", "GNF", "JPY", "KMF", "KRW", "MGA", "PYG", "RWF", "UGX", "VND", "VUV",
+    "XAF", "XOF", "XPF",
+];
+
+/// A payment to collect from the guest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentRequest {
+    /// Our payment ID, also used as the idempotency key.
+    pub payment_id: Uuid,
+    pub booking_id: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
+    pub description: String,
+}
+
+/// A payment intent created at the provider.
+#[derive(Debug, Clone)]
+pub struct ProviderIntent {
+    pub id: String,
+    /// Handed to the guest's browser to complete the payment.
+    pub client_secret: Option<String>,
+}
+
+/// Money to return from a captured payment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefundRequest {
+    /// Our refund ID, also used as the idempotency key.
+    pub refund_id: Uuid,
+    pub provider_payment_id: String,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderRefund {
+    pub id: String,
+    pub status: RefundStatus,
+    pub failure_reason: Option<String>,
+}
+
+/// A verified webhook notification, reduced to what we act on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookEvent {
+    /// The payment was captured.
+    PaymentSucceeded { provider_payment_id: String },
+    PaymentFailed {
+        provider_payment_id: String,
+        reason: Option<String>,
+    },
+    RefundUpdated {
+        provider_refund_id: String,
+        status: RefundStatus,
+        failure_reason: Option<String>,
+    },
+    /// An event type we don't handle.
+    Ignored,
+}
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 'a>>;
+
+/// Takes payments and issues refunds. Handlers hold an
+/// `Arc<dyn PaymentProvider>` so the provider is chosen at startup: Stripe in
+/// production, [`FakePaymentProvider`] in development and tests.
+pub trait PaymentProvider: Send + Sync {
+    /// Recorded with each payment and refund.
+    fn name(&self) -> &'static str;
+
+    fn create_intent<'a>(
+        &'a self,
+        request: &'a IntentRequest,
+    ) -> ProviderFuture<'a, ProviderIntent>;
+
+    fn refund<'a>(&'a self, request: &'a RefundRequest) -> ProviderFuture<'a, ProviderRefund>;
+
+    /// Verify a webhook's signature and decode it.
+    fn parse_webhook(&self, headers: &HeaderMap, payload: &[u8]) -> Result<WebhookEvent, AppError>;
+}
+
+/// Pick a provider from the environment: [`StripeProvider`] when
+/// `STRIPE_SECRET_KEY` is set, or with `PAYMENT_PROVIDER=fake` a
+/// [`FakePaymentProvider`] whose webhooks are signed with
+/// `PAYMENT_WEBHOOK_SECRET` (rejected if unset). The fake moves no money, so
+/// a missing Stripe key is an error unless the fake was asked for.
+pub fn from_env() -> Result<Arc<dyn PaymentProvider>, AppError> {
+    let env = |key: &str| std::env::var(key).unwrap_or_default();
+
+    match env("PAYMENT_PROVIDER").as_str() {
+        "" | "stripe" if !env("STRIPE_SECRET_KEY").is_empty() => {
+            Ok(Arc::new(StripeProvider::from_env()?))
+        }
+        "" | "stripe" => Err(AppError::Internal(
+            "STRIPE_SECRET_KEY must be set; use PAYMENT_PROVIDER=fake in development".to_string(),
+        )),
+        "fake" => Ok(Arc::new(FakePaymentProvider::new(env(
+            "PAYMENT_WEBHOOK_SECRET",
+        )))),
+        other => Err(AppError::Internal(format!(
+            "Invalid PAYMENT_PROVIDER '{other}': expected 'stripe' or 'fake'"
+        ))),
+    }
+}
+
+/// `amount` in the currency's smallest unit, as Stripe expects it.
+pub fn to_minor_units(amount: Decimal, currency: &str) -> Result<i64, AppError> {
+    let currency = currency.to_uppercase();
+    let scaled = if ZERO_DECIMAL_CURRENCIES.contains(&currency.as_str()) {
+        amount.round()
+    } else {
+        (amount * Decimal::ONE_HUNDRED).round()
+    };
+    scaled
+        .to_i64()
+        .ok_or_else(|| AppError::BadRequest(format!("Amount {amount} {currency} is out of range")))
+}
+
+// ---------------------------------------------------------------------------
+// Webhook signatures and events
+// ---------------------------------------------------------------------------
+
+fn signature(secret: &str, timestamp: i64, payload: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    mac
+}
+
+/// The signature header value for `payload` sent at `timestamp`.
+pub fn sign_webhook(secret: &str, payload: &[u8], timestamp: i64) -> String {
+    let mac = signature(secret, timestamp, payload)
+        .finalize()
+        .into_bytes();
+    format!("t={timestamp},v1={}", hex::encode(mac))
+}
+
+/// Check a signature header made by [`sign_webhook`]. Several `v1` values are
+/// accepted while a secret is being rolled.
+fn verify_signature(
+    secret: &str,
+    header: Option<&str>,
+    payload: &[u8],
+    now: i64,
+) -> Result<(), AppError> {
+    if secret.is_empty() {
+        return Err(AppError::Internal(
+            "Payment webhooks are not configured".to_string(),
+        ));
+    }
+    let invalid = || AppError::BadRequest("Invalid webhook signature".to_string());
+
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.ok_or_else(invalid)?.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(invalid)?;
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECONDS {
+        return Err(invalid());
+    }
+
+    let expected = signature(secret, timestamp, payload);
+    if signatures
+        .iter()
+        .any(|sig| expected.clone().verify_slice(sig).is_ok())
+    {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+#[derive(Deserialize)]
+struct EventEnvelope {
+    #[serde(rename = "type")]
+    kind: String,
+    data: EventData,
+}
+
+#[derive(Deserialize)]
+struct EventData {
+    object: EventObject,
+}
+
+#[derive(Deserialize)]
+struct EventObject {
+    id: String,
+    status: Option<String>,
+    failure_reason: Option<String>,
+    last_payment_error: Option<PaymentError>,
+}
+
+#[derive(Deserialize)]
+struct PaymentError {
+    message: Option<String>,
+}
+
+fn refund_status(status: Option<&str>) -> RefundStatus {
+    match status {
+        Some("succeeded") => RefundStatus::Succeeded,
+        Some("failed" | "canceled") => RefundStatus::Failed,
+        _ => RefundStatus::Pending,
+    }
+}
+
+/// Decode a Stripe-format event (`{"type": ..., "data": {"object": ...}}`).
+fn parse_event(payload: &[u8]) -> Result<WebhookEvent, AppError> {
+    let event: EventEnvelope = serde_json::from_slice(payload)?;
+    let object = event.data.object;
+
+    Ok(match event.kind.as_str() {
+        "payment_intent.succeeded" => WebhookEvent::PaymentSucceeded {
+            provider_payment_id: object.id,
+        },
+        "payment_intent.payment_failed" => WebhookEvent::PaymentFailed {
+            provider_payment_id: object.id,
+            reason: object.last_payment_error.and_then(|e| e.message),
+        },
+        "refund.created" | "refund.updated" | "charge.refund.updated" => {
+            WebhookEvent::RefundUpdated {
+                status: refund_status(object.status.as_deref()),
+                provider_refund_id: object.id,
+                failure_reason: object.failure_reason,
+            }
+        }
+        _ => WebhookEvent::Ignored,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Stripe
+// ---------------------------------------------------------------------------
+
+/// Takes payments through Stripe payment intents.
+pub struct StripeProvider {
+    client: reqwest::Client,
+    api_base: String,
+    secret_key: String,
+    webhook_secret: String,
+}
+
+#[derive(Deserialize)]
+struct StripeIntent {
+    id: String,
+    client_secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StripeRefund {
+    id: String,
+    status: Option<String>,
+    failure_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StripeErrorBody {
+    error: StripeError,
+}
+
+#[derive(Deserialize)]
+struct StripeError {
+    message: Option<String>,
+}
+
+impl StripeProvider {
+    pub fn new(secret_key: impl Into<String>, webhook_secret: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: STRIPE_API_BASE.to_string(),
+            secret_key: secret_key.into(),
+            webhook_secret: webhook_secret.into(),
+        }
+    }
+
+    /// Configure from `STRIPE_SECRET_KEY` and `STRIPE_WEBHOOK_SECRET`.
+    pub fn from_env() -> Result<Self, AppError> {
+        let env = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| AppError::Internal(format!("Stripe configuration: {key} not set")))
+        };
+        Ok(Self::new(
+            env("STRIPE_SECRET_KEY")?,
+            env("STRIPE_WEBHOOK_SECRET")?,
+        ))
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        idempotency_key: Uuid,
+        form: &[(&str, String)],
+    ) -> Result<T, AppError> {
+        let provider_error = |msg: String| AppError::Internal(format!("Stripe error: {msg}"));
+
+        let response = self
+            .client
+            .post(format!("{}/{path}", self.api_base))
+            .basic_auth(&self.secret_key, None::<&str>)
+            .header("Idempotency-Key", idempotency_key.to_string())
+            .form(form)
+            .send()
+            .await
+            .map_err(|e| provider_error(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response
+                .json::<StripeErrorBody>()
+                .await
+                .ok()
+                .and_then(|body| body.error.message)
+                .unwrap_or_else(|| status.to_string());
+            return Err(provider_error(message));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| provider_error(e.to_string()))
+    }
+}
+
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    fn create_intent<'a>(
+        &'a self,
+        request: &'a IntentRequest,
+    ) -> ProviderFuture<'a, ProviderIntent> {
+        Box::pin(async move {
+            let form = [
+                (
+                    "amount",
+                    to_minor_units(request.amount, &request.currency)?.to_string(),
+                ),
+                ("currency", request.currency.to_lowercase()),
+                ("description", request.description.clone()),
+                ("automatic_payment_methods[enabled]", "true".to_string()),
+                ("metadata[booking_id]", request.booking_id.to_string()),
+                ("metadata[payment_id]", request.payment_id.to_string()),
+            ];
+            let intent: StripeIntent = self
+                .post("payment_intents", request.payment_id, &form)
+                .await?;
+
+            Ok(ProviderIntent {
+                id: intent.id,
+                client_secret: intent.client_secret,
+            })
+        })
+    }
+
+    fn refund<'a>(&'a self, request: &'a RefundRequest) -> ProviderFuture<'a, ProviderRefund> {
+        Box::pin(async move {
+            let form = [
+                ("payment_intent", request.provider_payment_id.clone()),
+                (
+                    "amount",
+                    to_minor_units(request.amount, &request.currency)?.to_string(),
+                ),
+                ("metadata[refund_id]", request.refund_id.to_string()),
+            ];
+            let refund: StripeRefund = self.post("refunds", request.refund_id, &form).await?;
+
+            Ok(ProviderRefund {
+                status: refund_status(refund.status.as_deref()),
+                id: refund.id,
+                failure_reason: refund.failure_reason,
+            })
+        })
+    }
+
+    fn parse_webhook(&self, headers: &HeaderMap, payload: &[u8]) -> Result<WebhookEvent, AppError> {
+        let header = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok());
+        verify_signature(
+            &self.webhook_secret,
+            header,
+            payload,
+            Utc::now().timestamp(),
+        )?;
+        parse_event(payload)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Fake
+// ---------------------------------------------------------------------------
+
+/// Records payment intents and refunds in memory instead of moving money.
+/// Refunds succeed immediately unless [`FakePaymentProvider::fail_refunds`]
+/// is set. Its webhooks use Stripe's format and signature scheme, and
+/// [`FakePaymentProvider::webhook`] builds signed ones for tests.
+pub struct FakePaymentProvider {
+    webhook_secret: String,
+    intents: Mutex<Vec<IntentRequest>>,
+    refunds: Mutex<Vec<RefundRequest>>,
+    fail_refunds: AtomicBool,
+}
+
+impl FakePaymentProvider {
+    pub fn new(webhook_secret: impl Into<String>) -> Self {
+        Self {
+            webhook_secret: webhook_secret.into(),
+            intents: Mutex::new(Vec::new()),
+            refunds: Mutex::new(Vec::new()),
+            fail_refunds: AtomicBool::new(false),
+        }
+    }
+
+    /// The payment intents created so far.
+    pub fn intents(&self) -> Vec<IntentRequest> {
+        self.intents.lock().unwrap().clone()
+    }
+
+    /// The refunds issued so far.
+    pub fn refunds(&self) -> Vec<RefundRequest> {
+        self.refunds.lock().unwrap().clone()
+    }
+
+    /// Make subsequent refunds fail (or succeed again).
+    pub fn fail_refunds(&self, fail: bool) {
+        self.fail_refunds.store(fail, Ordering::SeqCst);
+    }
+
+    /// A signed webhook for an event of `event_type` about `object`, as the
+    /// headers and body of the request.
+    pub fn webhook(&self, event_type: &str, object: serde_json::Value) -> (HeaderMap, Vec<u8>) {
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "id": format!("evt_fake_{}", Uuid::new_v4().simple()),
+            "type": event_type,
+            "data": { "object": object },
+        }))
+        .expect("event serializes");
+        let signature = sign_webhook(&self.webhook_secret, &payload, Utc::now().timestamp());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            SIGNATURE_HEADER,
+            HeaderValue::from_str(&signature).expect("signature is a valid header"),
+        );
+        (headers, payload)
+    }
+}
+
+impl PaymentProvider for FakePaymentProvider {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn create_intent<'a>(
+        &'a self,
+        request: &'a IntentRequest,
+    ) -> ProviderFuture<'a, ProviderIntent> {
+        Box::pin(async move {
+            self.intents.lock().unwrap().push(request.clone());
+            let id = format!("pi_fake_{}", request.payment_id.simple());
+            Ok(ProviderIntent {
+                client_secret: Some(format!("{id}_secret")),
+                id,
+            })
+        })
+    }
+
+    fn refund<'a>(&'a self, request: &'a RefundRequest) -> ProviderFuture<'a, ProviderRefund> {
+        Box::pin(async move {
+            if self.fail_refunds.load(Ordering::SeqCst) {
+                return Err(AppError::Internal("Fake refund failure".to_string()));
+            }
+            self.refunds.lock().unwrap().push(request.clone());
+            Ok(ProviderRefund {
+                id: format!("re_fake_{}", request.refund_id.simple()),
+                status: RefundStatus::Succeeded,
+                failure_reason: None,
+            })
+        })
+    }
+
+    fn parse_webhook(&self, headers: &HeaderMap, payload: &[u8]) -> Result<WebhookEvent, AppError> {
+        let header = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok());
+        verify_signature(
+            &self.webhook_secret,
+            header,
+            payload,
+            Utc::now().timestamp(),
+        )?;
+        parse_event(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRET: &str = "whsec_test";
+
+    #[test]
+    fn test_signature_round_trip() {
+        let payload = br#"{"type":"payment_intent.succeeded"}"#;
+        let header = sign_webhook(SECRET, payload, 1_700_000_000);
+
+        assert!(verify_signature(SECRET, Some(&header), payload, 1_700_000_060).is_ok());
+        // Tampered body, wrong secret, replayed too late, missing header.
+        assert!(verify_signature(SECRET, Some(&header), b"{}", 1_700_000_060).is_err());
+        assert!(verify_signature("whsec_other", Some(&header), payload, 1_700_000_060).is_err());
+        assert!(verify_signature(SECRET, Some(&header), payload, 1_700_000_400).is_err());
+        assert!(verify_signature(SECRET, None, payload, 1_700_000_000).is_err());
+    }
+
+    #[test]
+    fn test_unconfigured_secret_rejects_everything() {
+        let payload = b"{}";
+        let header = sign_webhook("", payload, 1_700_000_000);
+        assert!(matches!(
+            verify_signature("", Some(&header), payload, 1_700_000_000),
+            Err(AppError::Internal(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let event = |kind: &str, object: serde_json::Value| {
+            parse_event(
+                &serde_json::to_vec(&json!({"type": kind, "data": {"object": object}})).unwrap(),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            event(
+                "payment_intent.succeeded",
+                json!({"id": "pi_1", "status": "succeeded"})
+            ),
+            WebhookEvent::PaymentSucceeded {
+                provider_payment_id: "pi_1".to_string()
+            }
+        );
+        assert_eq!(
+            event(
+                "payment_intent.payment_failed",
+                json!({"id": "pi_1", "last_payment_error": {"message": "Card declined"}})
+            ),
+            WebhookEvent::PaymentFailed {
+                provider_payment_id: "pi_1".to_string(),
+                reason: Some("Card declined".to_string()),
+            }
+        );
+        assert_eq!(
+            event(
+                "refund.updated",
+                json!({"id": "re_1", "status": "failed", "failure_reason": "expired_or_canceled_card"})
+            ),
+            WebhookEvent::RefundUpdated {
+                provider_refund_id: "re_1".to_string(),
+                status: RefundStatus::Failed,
+                failure_reason: Some("expired_or_canceled_card".to_string()),
+            }
+        );
+        assert_eq!(
+            event("customer.created", json!({"id": "cus_1"})),
+            WebhookEvent::Ignored
+        );
+    }
+
+    #[test]
+    fn test_minor_units() {
+        assert_eq!(
+            to_minor_units(Decimal::new(45050, 2), "USD").unwrap(),
+            45050
+        );
+        assert_eq!(
+            to_minor_units(Decimal::new(1500000, 0), "idr").unwrap(),
+            150000000
+        );
+        assert_eq!(
+            to_minor_units(Decimal::new(12000, 0), "JPY").unwrap(),
+            12000
+        );
+    }
+}
@@ -0,0 +1,731 @@
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::{
+    Booking, BookingStatus, Payment, PaymentKind, PaymentStatus, Refund, RefundStatus,
+};
+use crate::notifications;
+use crate::payment_provider::{IntentRequest, PaymentProvider, RefundRequest, WebhookEvent};
+
+/// Share of the total paid up front when paying by deposit.
+pub const DEPOSIT_PERCENT: u32 = 30;
+
+/// The balance is due this many days before check-in, so stays starting
+/// sooner than that must be paid in full.
+pub const BALANCE_DUE_DAYS: i64 = 30;
+
+/// A booking's payments and refunds, with the totals the guest cares about.
+#[derive(Debug, Serialize)]
+pub struct PaymentSummary {
+    pub total_price: Decimal,
+    pub currency: String,
+    pub amount_paid: Decimal,
+    pub amount_refunded: Decimal,
+    /// What is still to be paid; zero once the booking is cancelled.
+    pub balance_due: Decimal,
+    pub payments: Vec<Payment>,
+    pub refunds: Vec<Refund>,
+}
+
+pub fn deposit_amount(total: Decimal) -> Decimal {
+    (total * Decimal::from(DEPOSIT_PERCENT) / Decimal::ONE_HUNDRED).round_dp(2)
+}
+
+/// The amount of a new payment of `kind` towards `booking`, of which `paid`
+/// has already been captured.
+pub fn amount_due(
+    booking: &Booking,
+    kind: PaymentKind,
+    paid: Decimal,
+    today: NaiveDate,
+) -> Result<Decimal, AppError> {
+    if !matches!(
+        booking.status,
+        BookingStatus::Pending | BookingStatus::Confirmed
+    ) {
+        return Err(AppError::Conflict(format!(
+            "A {} booking cannot be paid",
+            booking.status.as_str()
+        )));
+    }
+    let outstanding = booking.total_price - paid;
+    if outstanding <= Decimal::ZERO {
+        return Err(AppError::Conflict(
+            "This booking is already paid in full".to_string(),
+        ));
+    }
+
+    match kind {
+        PaymentKind::Full if paid > Decimal::ZERO => Err(AppError::Conflict(
+            "The deposit has already been paid; pay the balance instead".to_string(),
+        )),
+        PaymentKind::Full => Ok(outstanding),
+        PaymentKind::Deposit if paid > Decimal::ZERO => Err(AppError::Conflict(
+            "The deposit has already been paid".to_string(),
+        )),
+        PaymentKind::Deposit if (booking.check_in - today).num_days() <= BALANCE_DUE_DAYS => {
+            Err(AppError::BadRequest(format!(
+                "Stays starting within {BALANCE_DUE_DAYS} days must be paid in full"
+            )))
+        }
+        PaymentKind::Deposit => Ok(deposit_amount(booking.total_price)),
+        PaymentKind::Balance if paid == Decimal::ZERO => Err(AppError::BadRequest(
+            "Pay the deposit or the full amount first".to_string(),
+        )),
+        PaymentKind::Balance => Ok(outstanding),
+    }
+}
+
+/// How much of `paid` goes back to a guest who cancels a booking costing
+/// `total` and is owed `policy_refund` under the cancellation policy. The
+/// guest forfeits `total - policy_refund`, so a guest who has only paid a
+/// deposit may get less back than the policy figure, or nothing.
+pub fn refund_due(total: Decimal, policy_refund: Decimal, paid: Decimal) -> Decimal {
+    let forfeited = (total - policy_refund).max(Decimal::ZERO);
+    (paid - forfeited).max(Decimal::ZERO)
+}
+
+async fn captured_amount(conn: &mut PgConnection, booking_id: Uuid) -> Result<Decimal, AppError> {
+    let amount = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM payments WHERE booking_id = $1 AND status = 'succeeded'",
+    )
+    .bind(booking_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(amount)
+}
+
+/// The total that should end up refunded for `booking`: the refund due under
+/// the cancellation policy once it is cancelled, otherwise anything captured
+/// beyond the price.
+async fn refund_target(conn: &mut PgConnection, booking: &Booking) -> Result<Decimal, AppError> {
+    let captured = captured_amount(conn, booking.id).await?;
+    Ok(match booking.status {
+        BookingStatus::Cancelled | BookingStatus::Refunded => refund_due(
+            booking.total_price,
+            booking.refund_amount.unwrap_or_default(),
+            captured,
+        ),
+        _ => (captured - booking.total_price).max(Decimal::ZERO),
+    })
+}
+
+async fn lock_booking(conn: &mut PgConnection, booking_id: Uuid) -> Result<Booking, AppError> {
+    sqlx::query_as("SELECT * FROM bookings WHERE id = $1 FOR UPDATE")
+        .bind(booking_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))
+}
+
+/// Move a booking to `to` on the payment system's behalf, recording the
+/// change and queueing its notifications.
+async fn change_status(
+    conn: &mut PgConnection,
+    booking: &Booking,
+    to: BookingStatus,
+    reason: &str,
+) -> Result<(), AppError> {
+    booking.status.check_transition(&to)?;
+
+    sqlx::query("UPDATE bookings SET status = $2 WHERE id = $1")
+        .bind(booking.id)
+        .bind(&to)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        r#"INSERT INTO booking_status_history (booking_id, from_status, to_status, reason)
+           VALUES ($1, $2, $3, $4)"#,
+    )
+    .bind(booking.id)
+    .bind(&booking.status)
+    .bind(&to)
+    .bind(reason)
+    .execute(&mut *conn)
+    .await?;
+
+    notifications::booking_status_changed(conn, booking.id, &to).await
+}
+
+/// Start a payment of `kind` towards one of `guest_id`'s bookings. The
+/// returned payment carries the client secret the guest's browser uses to
+/// pay; the booking is confirmed when the provider's webhook reports the
+/// payment succeeded. An unfinished payment of the same kind is returned
+/// again rather than starting another.
+pub async fn start_payment(
+    pool: &PgPool,
+    provider: &dyn PaymentProvider,
+    booking_id: Uuid,
+    guest_id: Uuid,
+    kind: PaymentKind,
+) -> Result<Payment, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let booking: Booking =
+        sqlx::query_as("SELECT * FROM bookings WHERE id = $1 AND guest_id = $2 FOR UPDATE")
+            .bind(booking_id)
+            .bind(guest_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
+
+    let open: Option<Payment> = sqlx::query_as(
+        r#"SELECT * FROM payments
+           WHERE booking_id = $1 AND kind = $2 AND provider = $3
+             AND status = 'requires_payment' AND provider_payment_id IS NOT NULL
+           ORDER BY created_at DESC
+           LIMIT 1"#,
+    )
+    .bind(booking_id)
+    .bind(kind)
+    .bind(provider.name())
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(payment) = open {
+        return Ok(payment);
+    }
+
+    let paid = captured_amount(&mut tx, booking_id).await?;
+    let amount = amount_due(&booking, kind, paid, Utc::now().date_naive())?;
+
+    let payment: Payment = sqlx::query_as(
+        r#"INSERT INTO payments (booking_id, kind, amount, currency, provider)
+           VALUES ($1, $2, $3, $4, $5)
+           RETURNING *"#,
+    )
+    .bind(booking_id)
+    .bind(kind)
+    .bind(amount)
+    .bind(&booking.currency)
+    .bind(provider.name())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    // The provider is called outside the transaction so the booking isn't
+    // held locked on a network round trip.
+    let request = IntentRequest {
+        payment_id: payment.id,
+        booking_id,
+        amount,
+        currency: booking.currency.clone(),
+        description: format!("MyBaliVilla booking {booking_id}"),
+    };
+    match provider.create_intent(&request).await {
+        Ok(intent) => Ok(sqlx::query_as(
+            r#"UPDATE payments SET provider_payment_id = $2, client_secret = $3
+               WHERE id = $1
+               RETURNING *"#,
+        )
+        .bind(payment.id)
+        .bind(&intent.id)
+        .bind(&intent.client_secret)
+        .fetch_one(pool)
+        .await?),
+        Err(e) => {
+            sqlx::query("UPDATE payments SET status = 'failed', failure_reason = $2 WHERE id = $1")
+                .bind(payment.id)
+                .bind(e.to_string())
+                .execute(pool)
+                .await?;
+            Err(e)
+        }
+    }
+}
+
+/// Apply a verified webhook event. Providers may deliver an event more than
+/// once, so applying one again changes nothing.
+pub async fn handle_webhook_event(
+    pool: &PgPool,
+    provider: &dyn PaymentProvider,
+    event: WebhookEvent,
+) -> Result<(), AppError> {
+    match event {
+        WebhookEvent::PaymentSucceeded {
+            provider_payment_id,
+        } => payment_succeeded(pool, provider, &provider_payment_id).await,
+        WebhookEvent::PaymentFailed {
+            provider_payment_id,
+            reason,
+        } => {
+            sqlx::query(
+                r#"UPDATE payments SET status = 'failed', failure_reason = $3
+                   WHERE provider = $1 AND provider_payment_id = $2
+                     AND status = 'requires_payment'"#,
+            )
+            .bind(provider.name())
+            .bind(&provider_payment_id)
+            .bind(reason)
+            .execute(pool)
+            .await?;
+            Ok(())
+        }
+        WebhookEvent::RefundUpdated {
+            provider_refund_id,
+            status,
+            failure_reason,
+        } => {
+            let booking_id: Option<Uuid> = sqlx::query_scalar(
+                r#"UPDATE refunds SET status = $3, failure_reason = $4
+                   WHERE provider = $1 AND provider_refund_id = $2 AND status <> $3
+                   RETURNING booking_id"#,
+            )
+            .bind(provider.name())
+            .bind(&provider_refund_id)
+            .bind(status)
+            .bind(failure_reason)
+            .fetch_optional(pool)
+            .await?;
+
+            match booking_id {
+                Some(booking_id) => mark_refunded_if_settled(pool, booking_id).await,
+                None => Ok(()),
+            }
+        }
+        WebhookEvent::Ignored => Ok(()),
+    }
+}
+
+/// Record a captured payment and confirm the booking if it was pending.
+/// Money that arrives for a booking that has since been cancelled, or beyond
+/// its price, is refunded.
+async fn payment_succeeded(
+    pool: &PgPool,
+    provider: &dyn PaymentProvider,
+    provider_payment_id: &str,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let booking_id: Option<Uuid> = sqlx::query_scalar(
+        r#"UPDATE payments
+           SET status = 'succeeded', captured_at = NOW(), failure_reason = NULL
+           WHERE provider = $1 AND provider_payment_id = $2 AND status <> 'succeeded'
+           RETURNING booking_id"#,
+    )
+    .bind(provider.name())
+    .bind(provider_payment_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    // Already recorded, or not a payment of ours.
+    let Some(booking_id) = booking_id else {
+        return Ok(());
+    };
+
+    let booking = lock_booking(&mut tx, booking_id).await?;
+    if booking.status == BookingStatus::Pending {
+        change_status(
+            &mut tx,
+            &booking,
+            BookingStatus::Confirmed,
+            "Payment received",
+        )
+        .await?;
+    }
+    let refund_owed = refund_target(&mut tx, &booking).await? > Decimal::ZERO;
+
+    tx.commit().await?;
+
+    if refund_owed {
+        issue_refunds(pool, provider, booking_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Refund whatever is owed on a booking and not yet refunded: on a cancelled
+/// booking, the refund due under its cancellation policy (see
+/// [`refund_due`]). Refunds are drawn from the most recent payments first.
+/// Safe to call again, e.g. to retry after a refund failed. A cancelled
+/// booking moves to `refunded` once everything owed has been refunded.
+pub async fn issue_refunds(
+    pool: &PgPool,
+    provider: &dyn PaymentProvider,
+    booking_id: Uuid,
+) -> Result<Vec<Refund>, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let booking = lock_booking(&mut tx, booking_id).await?;
+    let target = refund_target(&mut tx, &booking).await?;
+    let refunded: Decimal = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM refunds WHERE booking_id = $1 AND status <> 'failed'",
+    )
+    .bind(booking_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let payments: Vec<(Uuid, String, String, Decimal, Decimal)> = sqlx::query_as(
+        r#"SELECT p.id, p.provider_payment_id, p.currency, p.amount,
+                  COALESCE((SELECT SUM(r.amount) FROM refunds r
+                            WHERE r.payment_id = p.id AND r.status <> 'failed'), 0)
+           FROM payments p
+           WHERE p.booking_id = $1 AND p.status = 'succeeded' AND p.provider = $2
+             AND p.provider_payment_id IS NOT NULL
+           ORDER BY p.captured_at DESC"#,
+    )
+    .bind(booking_id)
+    .bind(provider.name())
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut remaining = target - refunded;
+    let mut pending = Vec::new();
+    for (payment_id, provider_payment_id, currency, amount, already_refunded) in payments {
+        let refundable = amount - already_refunded;
+        if remaining <= Decimal::ZERO {
+            break;
+        }
+        if refundable <= Decimal::ZERO {
+            continue;
+        }
+        let amount = remaining.min(refundable);
+        remaining -= amount;
+
+        let refund: Refund = sqlx::query_as(
+            r#"INSERT INTO refunds (booking_id, payment_id, amount, currency, provider)
+               VALUES ($1, $2, $3, $4, $5)
+               RETURNING *"#,
+        )
+        .bind(booking_id)
+        .bind(payment_id)
+        .bind(amount)
+        .bind(&currency)
+        .bind(provider.name())
+        .fetch_one(&mut *tx)
+        .await?;
+        pending.push((refund, provider_payment_id));
+    }
+
+    tx.commit().await?;
+
+    let mut issued = Vec::new();
+    let mut first_error = None;
+    for (refund, provider_payment_id) in pending {
+        let request = RefundRequest {
+            refund_id: refund.id,
+            provider_payment_id,
+            amount: refund.amount,
+            currency: refund.currency.clone(),
+        };
+        let refund: Refund =
+            match provider.refund(&request).await {
+                Ok(result) => sqlx::query_as(
+                    r#"UPDATE refunds SET provider_refund_id = $2, status = $3, failure_reason = $4
+                       WHERE id = $1
+                       RETURNING *"#,
+                )
+                .bind(refund.id)
+                .bind(&result.id)
+                .bind(result.status)
+                .bind(&result.failure_reason)
+                .fetch_one(pool)
+                .await?,
+                Err(e) => {
+                    let failed = sqlx::query_as(
+                        r#"UPDATE refunds SET status = 'failed', failure_reason = $2
+                       WHERE id = $1
+                       RETURNING *"#,
+                    )
+                    .bind(refund.id)
+                    .bind(e.to_string())
+                    .fetch_one(pool)
+                    .await?;
+                    first_error.get_or_insert(e);
+                    failed
+                }
+            };
+        issued.push(refund);
+    }
+
+    mark_refunded_if_settled(pool, booking_id).await?;
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(issued),
+    }
+}
+
+/// Move a cancelled booking to `refunded` once refunds covering everything
+/// owed have succeeded and none are still pending.
+async fn mark_refunded_if_settled(pool: &PgPool, booking_id: Uuid) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let booking = lock_booking(&mut tx, booking_id).await?;
+    if booking.status != BookingStatus::Cancelled {
+        return Ok(());
+    }
+
+    let (succeeded, pending): (Decimal, i64) = sqlx::query_as(
+        r#"SELECT COALESCE(SUM(amount) FILTER (WHERE status = 'succeeded'), 0),
+                  COUNT(*) FILTER (WHERE status = 'pending')
+           FROM refunds WHERE booking_id = $1"#,
+    )
+    .bind(booking_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let target = refund_target(&mut tx, &booking).await?;
+
+    if pending == 0 && succeeded > Decimal::ZERO && succeeded >= target {
+        change_status(&mut tx, &booking, BookingStatus::Refunded, "Refund issued").await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// A booking's payments and refunds, oldest first.
+pub async fn payment_summary(pool: &PgPool, booking: &Booking) -> Result<PaymentSummary, AppError> {
+    let payments: Vec<Payment> =
+        sqlx::query_as("SELECT * FROM payments WHERE booking_id = $1 ORDER BY created_at")
+            .bind(booking.id)
+            .fetch_all(pool)
+            .await?;
+    let refunds: Vec<Refund> =
+        sqlx::query_as("SELECT * FROM refunds WHERE booking_id = $1 ORDER BY created_at")
+            .bind(booking.id)
+            .fetch_all(pool)
+            .await?;
+
+    let amount_paid: Decimal = payments
+        .iter()
+        .filter(|p| p.status == PaymentStatus::Succeeded)
+        .map(|p| p.amount)
+        .sum();
+    let amount_refunded: Decimal = refunds
+        .iter()
+        .filter(|r| r.status == RefundStatus::Succeeded)
+        .map(|r| r.amount)
+        .sum();
+    let balance_due = match booking.status {
+        BookingStatus::Pending | BookingStatus::Confirmed | BookingStatus::CheckedIn => {
+            (booking.total_price - amount_paid).max(Decimal::ZERO)
+        }
+        _ => Decimal::ZERO,
+    };
+
+    Ok(PaymentSummary {
+        total_price: booking.total_price,
+        currency: booking.currency.clone(),
+        amount_paid,
+        amount_refunded,
+        balance_due,
+        payments,
+        refunds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment_provider::FakePaymentProvider;
+    use serde_json::json;
+
+    fn booking(total: i64, check_in: NaiveDate, status: BookingStatus) -> Booking {
+        let now = Utc::now();
+        Booking {
+            id: Uuid::new_v4(),
+            property_id: Uuid::new_v4(),
+            guest_id: Uuid::new_v4(),
+            pricing_tier_id: None,
+            check_in,
+            check_out: check_in + chrono::Duration::days(5),
+            num_guests: 2,
+            special_requests: None,
+            base_price: Decimal::from(total),
+            cleaning_fee: None,
+            service_fee: None,
+            total_price: Decimal::from(total),
+            currency: "USD".to_string(),
+            duration_type: crate::models::RentalDurationType::Nightly,
+            duration_count: 5,
+            status,
+            cancelled_at: None,
+            cancellation_reason: None,
+            created_at: now,
+            updated_at: now,
+            has_pets: false,
+            expected_arrival_time: None,
+            refund_amount: None,
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_deposit_then_balance() {
+        let today = date(2026, 1, 1);
+        let b = booking(1000, date(2026, 6, 1), BookingStatus::Pending);
+
+        assert_eq!(
+            amount_due(&b, PaymentKind::Deposit, Decimal::ZERO, today).unwrap(),
+            Decimal::from(300)
+        );
+        assert_eq!(
+            amount_due(&b, PaymentKind::Full, Decimal::ZERO, today).unwrap(),
+            Decimal::from(1000)
+        );
+        assert!(amount_due(&b, PaymentKind::Balance, Decimal::ZERO, today).is_err());
+
+        let paid = Decimal::from(300);
+        assert_eq!(
+            amount_due(&b, PaymentKind::Balance, paid, today).unwrap(),
+            Decimal::from(700)
+        );
+        assert!(amount_due(&b, PaymentKind::Deposit, paid, today).is_err());
+        assert!(amount_due(&b, PaymentKind::Full, paid, today).is_err());
+        assert!(amount_due(&b, PaymentKind::Balance, Decimal::from(1000), today).is_err());
+    }
+
+    #[test]
+    fn test_no_deposit_close_to_check_in_or_once_cancelled() {
+        let today = date(2026, 1, 1);
+        let soon = booking(1000, date(2026, 1, 20), BookingStatus::Pending);
+        assert!(matches!(
+            amount_due(&soon, PaymentKind::Deposit, Decimal::ZERO, today),
+            Err(AppError::BadRequest(_))
+        ));
+
+        let cancelled = booking(1000, date(2026, 6, 1), BookingStatus::Cancelled);
+        assert!(matches!(
+            amount_due(&cancelled, PaymentKind::Full, Decimal::ZERO, today),
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_refund_due_keeps_the_forfeited_share() {
+        let total = Decimal::from(1000);
+        // Fully paid, half refundable under the policy.
+        assert_eq!(
+            refund_due(total, Decimal::from(500), total),
+            Decimal::from(500)
+        );
+        // Deposit paid, full refund under the policy.
+        assert_eq!(
+            refund_due(total, total, Decimal::from(300)),
+            Decimal::from(300)
+        );
+        // Deposit paid, but the guest forfeits more than the deposit.
+        assert_eq!(
+            refund_due(total, Decimal::from(500), Decimal::from(300)),
+            Decimal::ZERO
+        );
+        // Nothing paid, nothing to refund.
+        assert_eq!(refund_due(total, total, Decimal::ZERO), Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_deposit_confirms_and_cancellation_refunds() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        let provider = FakePaymentProvider::new("whsec_test");
+
+        let owner_id = Uuid::new_v4();
+        let guest_id = Uuid::new_v4();
+        let property_id = Uuid::new_v4();
+        for id in [owner_id, guest_id] {
+            sqlx::query(
+                "INSERT INTO users (id, email, full_name, role, email_verified) VALUES ($1, $2, 'Test', 'user', true)",
+            )
+            .bind(id)
+            .bind(format!("{id}@example.com"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            r#"INSERT INTO properties (id, owner_id, title, slug, property_type, listing_type,
+                                       price, price_period, area, listing_status)
+               VALUES ($1, $2, 'Paid Villa', $3, 'villa', 'short_term_rent',
+                       200, 'per_night', 'Canggu', 'approved')"#,
+        )
+        .bind(property_id)
+        .bind(owner_id)
+        .bind(format!("paid-villa-{property_id}"))
+        .execute(&pool)
+        .await
+        .unwrap();
+        let booking_id: Uuid = sqlx::query_scalar(
+            r#"INSERT INTO bookings (property_id, guest_id, check_in, check_out, num_guests,
+                                     base_price, total_price, currency)
+               VALUES ($1, $2, CURRENT_DATE + 90, CURRENT_DATE + 95, 2, 1000, 1000, 'USD')
+               RETURNING id"#,
+        )
+        .bind(property_id)
+        .bind(guest_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let deposit = start_payment(&pool, &provider, booking_id, guest_id, PaymentKind::Deposit)
+            .await
+            .unwrap();
+        assert_eq!(deposit.amount, Decimal::from(300));
+        assert!(deposit.client_secret.is_some());
+        // Asking again returns the same unfinished payment.
+        let again = start_payment(&pool, &provider, booking_id, guest_id, PaymentKind::Deposit)
+            .await
+            .unwrap();
+        assert_eq!(again.id, deposit.id);
+        assert_eq!(provider.intents().len(), 1);
+
+        let (headers, payload) = provider.webhook(
+            "payment_intent.succeeded",
+            json!({"id": deposit.provider_payment_id, "status": "succeeded"}),
+        );
+        let event = provider.parse_webhook(&headers, &payload).unwrap();
+        handle_webhook_event(&pool, &provider, event.clone())
+            .await
+            .unwrap();
+        // Redelivery is harmless.
+        handle_webhook_event(&pool, &provider, event).await.unwrap();
+
+        let status: BookingStatus = sqlx::query_scalar("SELECT status FROM bookings WHERE id = $1")
+            .bind(booking_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, BookingStatus::Confirmed);
+
+        // Cancelled with a full refund due under the policy: the deposit
+        // comes back and the booking ends up refunded.
+        sqlx::query("UPDATE bookings SET status = 'cancelled', refund_amount = 1000 WHERE id = $1")
+            .bind(booking_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let refunds = issue_refunds(&pool, &provider, booking_id).await.unwrap();
+        assert_eq!(refunds.len(), 1);
+        assert_eq!(refunds[0].amount, Decimal::from(300));
+        assert_eq!(refunds[0].status, RefundStatus::Succeeded);
+        assert!(issue_refunds(&pool, &provider, booking_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let booking: Booking = sqlx::query_as("SELECT * FROM bookings WHERE id = $1")
+            .bind(booking_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(booking.status, BookingStatus::Refunded);
+        let summary = payment_summary(&pool, &booking).await.unwrap();
+        assert_eq!(summary.amount_paid, Decimal::from(300));
+        assert_eq!(summary.amount_refunded, Decimal::from(300));
+        assert_eq!(summary.balance_due, Decimal::ZERO);
+
+        sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(vec![owner_id, guest_id])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
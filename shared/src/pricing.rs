@@ -0,0 +1,352 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::{PricePeriod, PricingTier, RentalDurationType};
+
+/// The listing-level price on `properties`, used when a property has no
+/// active pricing tiers.
+#[derive(Debug, Clone, Copy)]
+pub struct ListingPrice<'a> {
+    pub price: Decimal,
+    pub price_period: Option<&'a PricePeriod>,
+    pub currency: &'a str,
+}
+
+/// Itemised price for a stay. This is both what the quote endpoint shows and
+/// what booking creation stores, so the two can never disagree.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceQuote {
+    /// The tier that was applied, or `None` when the listing price was used.
+    pub pricing_tier_id: Option<Uuid>,
+    pub check_in: NaiveDate,
+    pub check_out: NaiveDate,
+    pub nights: i64,
+    pub duration_type: RentalDurationType,
+    /// Whole `duration_type` units in the stay (e.g. 2 for a 17-night weekly stay).
+    pub duration_count: i32,
+    /// Price of one `duration_type` unit.
+    pub unit_price: Decimal,
+    /// Accommodation subtotal; partial units are charged pro rata.
+    pub base_price: Decimal,
+    pub cleaning_fee: Decimal,
+    /// Service fee, charged as a percentage of base price plus cleaning fee.
+    pub service_fee: Decimal,
+    pub total_price: Decimal,
+    pub currency: String,
+}
+
+/// A tier (or the listing price) reduced to what the calculation needs.
+struct Rate {
+    tier_id: Option<Uuid>,
+    duration_type: RentalDurationType,
+    price: Decimal,
+    cleaning_fee: Decimal,
+    service_fee_percent: Decimal,
+    currency: String,
+}
+
+/// Price a stay from `check_in` to `check_out`.
+///
+/// Only active tiers priced in the listing's currency are considered. When
+/// `duration_type` is given, only tiers of that type are considered. Among the
+/// tiers whose `min_duration`/`max_duration` admit the stay, the one with the
+/// longest duration unit wins (so a 30-night stay gets the monthly rate), with
+/// ties going to the cheaper tier. Properties with no usable tiers at all fall
+/// back to the listing price and its `price_period`, without fees.
+pub fn quote_stay(
+    tiers: &[PricingTier],
+    listing: ListingPrice<'_>,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    duration_type: Option<&RentalDurationType>,
+) -> Result<PriceQuote, AppError> {
+    if check_out <= check_in {
+        return Err(AppError::BadRequest(
+            "Check-out must be after check-in".to_string(),
+        ));
+    }
+
+    let nights = (check_out - check_in).num_days();
+
+    let usable: Vec<&PricingTier> = tiers
+        .iter()
+        .filter(|t| t.is_active && t.currency.eq_ignore_ascii_case(listing.currency))
+        .collect();
+
+    let rate = if usable.is_empty() {
+        let period = listing.price_period.ok_or_else(|| {
+            AppError::BadRequest("This property is not available for rental".to_string())
+        })?;
+
+        Rate {
+            tier_id: None,
+            duration_type: RentalDurationType::from(period),
+            price: listing.price,
+            cleaning_fee: Decimal::ZERO,
+            service_fee_percent: Decimal::ZERO,
+            currency: listing.currency.to_string(),
+        }
+    } else {
+        let candidates: Vec<&PricingTier> = usable
+            .into_iter()
+            .filter(|t| duration_type.is_none_or(|d| *d == t.duration_type))
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(AppError::BadRequest(
+                "No pricing is available for the requested duration type".to_string(),
+            ));
+        }
+
+        let tier = select_tier(&candidates, nights)?;
+
+        Rate {
+            tier_id: Some(tier.id),
+            duration_type: tier.duration_type.clone(),
+            price: tier.price,
+            cleaning_fee: tier.cleaning_fee.unwrap_or_default(),
+            service_fee_percent: tier.service_fee_percent.unwrap_or_default(),
+            currency: tier.currency.clone(),
+        }
+    };
+
+    let unit_nights = rate.duration_type.nights();
+    let duration_count = (nights / unit_nights).max(1) as i32;
+    let base_price = prorated(rate.price, nights, unit_nights);
+    let service_fee = ((base_price + rate.cleaning_fee) * rate.service_fee_percent
+        / Decimal::ONE_HUNDRED)
+        .round_dp(2);
+    let total_price = base_price + rate.cleaning_fee + service_fee;
+
+    Ok(PriceQuote {
+        pricing_tier_id: rate.tier_id,
+        check_in,
+        check_out,
+        nights,
+        duration_type: rate.duration_type,
+        duration_count,
+        unit_price: rate.price,
+        base_price,
+        cleaning_fee: rate.cleaning_fee,
+        service_fee,
+        total_price,
+        currency: rate.currency,
+    })
+}
+
+/// Charge `price` per `unit_nights` for a stay of `nights`, rounded to cents.
+fn prorated(price: Decimal, nights: i64, unit_nights: i64) -> Decimal {
+    (price * Decimal::from(nights) / Decimal::from(unit_nights)).round_dp(2)
+}
+
+/// Pick the tier that applies to a stay of `nights`, or explain why none does.
+fn select_tier<'a>(
+    candidates: &[&'a PricingTier],
+    nights: i64,
+) -> Result<&'a PricingTier, AppError> {
+    let min_nights =
+        |t: &PricingTier| i64::from(t.min_duration.unwrap_or(1).max(1)) * t.duration_type.nights();
+    // A stay counts as `max_duration` units until the next whole unit begins.
+    let max_nights = |t: &PricingTier| {
+        t.max_duration
+            .map(|m| (i64::from(m) + 1) * t.duration_type.nights() - 1)
+    };
+
+    let best = candidates
+        .iter()
+        .copied()
+        .filter(|t| nights >= min_nights(t) && max_nights(t).is_none_or(|m| nights <= m))
+        .min_by(|a, b| {
+            b.duration_type
+                .nights()
+                .cmp(&a.duration_type.nights())
+                .then_with(|| {
+                    prorated(a.price, nights, a.duration_type.nights()).cmp(&prorated(
+                        b.price,
+                        nights,
+                        b.duration_type.nights(),
+                    ))
+                })
+        });
+
+    if let Some(tier) = best {
+        return Ok(tier);
+    }
+
+    let shortest = candidates.iter().map(|t| min_nights(t)).min().unwrap_or(1);
+    if nights < shortest {
+        return Err(AppError::BadRequest(format!(
+            "Minimum stay is {shortest} nights"
+        )));
+    }
+
+    let longest: Option<i64> = candidates
+        .iter()
+        .map(|t| max_nights(t))
+        .collect::<Option<Vec<i64>>>()
+        .and_then(|v| v.into_iter().max());
+    if let Some(longest) = longest.filter(|l| nights > *l) {
+        return Err(AppError::BadRequest(format!(
+            "Maximum stay is {longest} nights"
+        )));
+    }
+
+    Err(AppError::BadRequest(format!(
+        "No pricing tier covers a stay of {nights} nights"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn tier(
+        duration_type: RentalDurationType,
+        price: Decimal,
+        min: Option<i32>,
+        max: Option<i32>,
+    ) -> PricingTier {
+        PricingTier {
+            id: Uuid::new_v4(),
+            property_id: Uuid::nil(),
+            duration_type,
+            price,
+            currency: "USD".to_string(),
+            min_duration: min,
+            max_duration: max,
+            cleaning_fee: Some(Decimal::from(50)),
+            service_fee_percent: Some(Decimal::from(10)),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn listing() -> ListingPrice<'static> {
+        ListingPrice {
+            price: Decimal::from(200),
+            price_period: Some(&PricePeriod::PerNight),
+            currency: "USD",
+        }
+    }
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_nightly_quote_itemises_fees() {
+        let tiers = vec![tier(
+            RentalDurationType::Nightly,
+            Decimal::from(100),
+            Some(2),
+            None,
+        )];
+        let quote = quote_stay(&tiers, listing(), date(10), date(13), None).unwrap();
+
+        assert_eq!(quote.pricing_tier_id, Some(tiers[0].id));
+        assert_eq!(quote.nights, 3);
+        assert_eq!(quote.duration_count, 3);
+        assert_eq!(quote.base_price, Decimal::from(300));
+        assert_eq!(quote.cleaning_fee, Decimal::from(50));
+        assert_eq!(quote.service_fee, Decimal::from(35));
+        assert_eq!(quote.total_price, Decimal::from(385));
+    }
+
+    #[test]
+    fn test_longest_eligible_unit_wins() {
+        let tiers = vec![
+            tier(RentalDurationType::Nightly, Decimal::from(100), None, None),
+            tier(RentalDurationType::Weekly, Decimal::from(560), None, None),
+        ];
+
+        let short = quote_stay(&tiers, listing(), date(1), date(5), None).unwrap();
+        assert_eq!(short.duration_type, RentalDurationType::Nightly);
+
+        let long = quote_stay(&tiers, listing(), date(1), date(11), None).unwrap();
+        assert_eq!(long.duration_type, RentalDurationType::Weekly);
+        assert_eq!(long.duration_count, 1);
+        assert_eq!(long.base_price, Decimal::from(800));
+    }
+
+    #[test]
+    fn test_requested_duration_type_restricts_tiers() {
+        let tiers = vec![
+            tier(RentalDurationType::Nightly, Decimal::from(100), None, None),
+            tier(RentalDurationType::Weekly, Decimal::from(560), None, None),
+        ];
+        let quote = quote_stay(
+            &tiers,
+            listing(),
+            date(1),
+            date(15),
+            Some(&RentalDurationType::Nightly),
+        )
+        .unwrap();
+        assert_eq!(quote.duration_type, RentalDurationType::Nightly);
+        assert_eq!(quote.base_price, Decimal::from(1400));
+
+        let err = quote_stay(
+            &tiers,
+            listing(),
+            date(1),
+            date(15),
+            Some(&RentalDurationType::Monthly),
+        );
+        assert!(matches!(err, Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_min_and_max_duration() {
+        let tiers = vec![tier(
+            RentalDurationType::Nightly,
+            Decimal::from(100),
+            Some(3),
+            Some(10),
+        )];
+
+        match quote_stay(&tiers, listing(), date(1), date(2), None) {
+            Err(AppError::BadRequest(msg)) => assert_eq!(msg, "Minimum stay is 3 nights"),
+            other => panic!("unexpected {other:?}"),
+        }
+        match quote_stay(&tiers, listing(), date(1), date(20), None) {
+            Err(AppError::BadRequest(msg)) => assert_eq!(msg, "Maximum stay is 10 nights"),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_foreign_currency_tiers_fall_back_to_listing_price() {
+        let mut idr = tier(
+            RentalDurationType::Nightly,
+            Decimal::from(1500000),
+            None,
+            None,
+        );
+        idr.currency = "IDR".to_string();
+
+        let quote = quote_stay(&[idr], listing(), date(1), date(3), None).unwrap();
+        assert_eq!(quote.pricing_tier_id, None);
+        assert_eq!(quote.currency, "USD");
+        assert_eq!(quote.total_price, Decimal::from(400));
+    }
+
+    #[test]
+    fn test_sale_listing_without_tiers_cannot_be_quoted() {
+        let sale = ListingPrice {
+            price: Decimal::from(450000),
+            price_period: None,
+            currency: "USD",
+        };
+        assert!(quote_stay(&[], sale, date(1), date(3), None).is_err());
+    }
+
+    #[test]
+    fn test_check_out_must_follow_check_in() {
+        assert!(quote_stay(&[], listing(), date(5), date(5), None).is_err());
+    }
+}
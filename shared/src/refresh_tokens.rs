@@ -0,0 +1,222 @@
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{
+    create_token, generate_secret_token, hash_secret_token, Audience, ACCESS_TOKEN_TTL_MINUTES,
+};
+use crate::errors::AppError;
+use crate::models::UserRole;
+
+/// Lifetime of a refresh token. Each refresh issues a new one, so an active
+/// client stays signed in indefinitely.
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// The credentials returned by login and refresh endpoints.
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    /// Short-lived access token (JWT) for the `Authorization` header.
+    pub token: String,
+    /// Opaque token to exchange for a new pair once `token` expires.
+    pub refresh_token: String,
+    /// Seconds until `token` expires.
+    pub expires_in: i64,
+}
+
+/// Start a new session with `audience`: an access token plus the first
+/// refresh token of a new family.
+pub async fn issue_tokens(
+    pool: &PgPool,
+    user_id: Uuid,
+    email: &str,
+    role: &UserRole,
+    token_version: i32,
+    audience: Audience,
+    secret: &str,
+) -> Result<TokenPair, AppError> {
+    let mut conn = pool.acquire().await?;
+    let refresh_token = insert_refresh_token(&mut conn, user_id, Uuid::new_v4(), audience).await?;
+
+    Ok(TokenPair {
+        token: create_token(user_id, email, role, token_version, audience, secret)?,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+    })
+}
+
+/// Exchange a refresh token for a new one in the same family and return the
+/// owner's id. The presented token is revoked.
+///
+/// Presenting a token that was already rotated means it has been copied, so
+/// the whole family is revoked and the legitimate client has to log in again.
+/// Tokens from a session with another audience are unknown here. The caller
+/// checks the owner is still allowed in before creating the access token.
+pub async fn rotate_refresh_token(
+    pool: &PgPool,
+    token: &str,
+    audience: Audience,
+) -> Result<(Uuid, String), AppError> {
+    let invalid = || AppError::Unauthorized("Invalid or expired refresh token".to_string());
+
+    let mut tx = pool.begin().await?;
+
+    let row: Option<(Uuid, Uuid, bool, bool)> = sqlx::query_as(
+        r#"SELECT user_id, family_id, revoked_at IS NOT NULL, expires_at <= NOW()
+           FROM refresh_tokens WHERE token_hash = $1 AND audience = $2
+           FOR UPDATE"#,
+    )
+    .bind(hash_secret_token(token))
+    .bind(audience.as_str())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((user_id, family_id, revoked, expired)) = row else {
+        return Err(invalid());
+    };
+
+    if revoked {
+        revoke_family(&mut tx, family_id).await?;
+        tx.commit().await?;
+        return Err(invalid());
+    }
+    if expired {
+        return Err(invalid());
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE token_hash = $1")
+        .bind(hash_secret_token(token))
+        .execute(&mut *tx)
+        .await?;
+    let refresh_token = insert_refresh_token(&mut tx, user_id, family_id, audience).await?;
+
+    tx.commit().await?;
+
+    Ok((user_id, refresh_token))
+}
+
+/// Revoke the session a refresh token belongs to. Returns the owner's id, or
+/// `None` for an unknown token.
+pub async fn revoke_refresh_token(pool: &PgPool, token: &str) -> Result<Option<Uuid>, AppError> {
+    let mut conn = pool.acquire().await?;
+
+    let row: Option<(Uuid, Uuid)> =
+        sqlx::query_as("SELECT user_id, family_id FROM refresh_tokens WHERE token_hash = $1")
+            .bind(hash_secret_token(token))
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    let Some((user_id, family_id)) = row else {
+        return Ok(None);
+    };
+    revoke_family(&mut conn, family_id).await?;
+
+    Ok(Some(user_id))
+}
+
+/// End every session of a user: bump `token_version` so outstanding access
+/// tokens fail [`crate::auth::ensure_token_current`], and revoke all refresh
+/// tokens.
+pub async fn revoke_user_sessions(conn: &mut PgConnection, user_id: Uuid) -> Result<(), AppError> {
+    sqlx::query("UPDATE users SET token_version = token_version + 1 WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn revoke_family(conn: &mut PgConnection, family_id: Uuid) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(family_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_refresh_token(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    family_id: Uuid,
+    audience: Audience,
+) -> Result<String, AppError> {
+    let token = generate_secret_token();
+
+    sqlx::query(
+        r#"INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at, audience)
+           VALUES ($1, $2, $3, $4, $5)"#,
+    )
+    .bind(user_id)
+    .bind(family_id)
+    .bind(hash_secret_token(&token))
+    .bind(Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS))
+    .bind(audience.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_sessions_are_only_refreshed_by_their_audience() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, email, full_name, role, email_verified) VALUES ($1, $2, 'Test', 'super_admin', true)",
+        )
+        .bind(user_id)
+        .bind(format!("{user_id}@example.com"))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let email = format!("{user_id}@example.com");
+        let tokens = issue_tokens(
+            &pool,
+            user_id,
+            &email,
+            &UserRole::SuperAdmin,
+            0,
+            Audience::Api,
+            "secret",
+        )
+        .await
+        .unwrap();
+
+        // The admin portal does not know a public API session.
+        let refused = rotate_refresh_token(&pool, &tokens.refresh_token, Audience::Admin).await;
+        assert!(matches!(refused, Err(AppError::Unauthorized(_))));
+
+        // The refusal leaves the session intact for the public API.
+        let (owner, rotated) = rotate_refresh_token(&pool, &tokens.refresh_token, Audience::Api)
+            .await
+            .unwrap();
+        assert_eq!(owner, user_id);
+        assert!(rotate_refresh_token(&pool, &rotated, Audience::Admin)
+            .await
+            .is_err());
+
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
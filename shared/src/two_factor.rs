@@ -0,0 +1,475 @@
+use chrono::{Duration, Utc};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use sqlx::{PgConnection, PgPool};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::auth::{generate_secret_token, hash_secret_token};
+use crate::errors::AppError;
+use crate::login_attempts::LoginAttempt;
+use crate::models::User;
+
+/// Account issuer shown in authenticator apps.
+const ISSUER: &str = "MyBaliVilla";
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+
+/// Recovery codes issued on enrolment. Each works once in place of a code
+/// from the authenticator app.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+/// How long the second login step may take after the password is accepted.
+pub const CHALLENGE_TTL_MINUTES: i64 = 5;
+
+// ---------------------------------------------------------------------------
+// TOTP (RFC 6238)
+// ---------------------------------------------------------------------------
+
+/// A new random 160-bit secret, base32-encoded.
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn totp(secret: &str, account: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Internal(format!("Invalid TOTP secret: {e}")))?;
+    Ok(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        DIGITS,
+        0,
+        STEP_SECONDS,
+        bytes,
+        Some(ISSUER.to_string()),
+        account.to_string(),
+    ))
+}
+
+/// The `otpauth://` URI authenticator apps import, usually by scanning it as
+/// a QR code.
+pub fn provisioning_uri(secret: &str, email: &str) -> Result<String, AppError> {
+    Ok(totp(secret, email)?.get_url())
+}
+
+/// Render `uri` as an SVG QR code.
+pub fn qr_code_svg(uri: &str) -> Result<String, AppError> {
+    let code = QrCode::new(uri.as_bytes())
+        .map_err(|e| AppError::Internal(format!("Failed to render QR code: {e}")))?;
+    Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+/// The time step `code` belongs to at `unix_time`, allowing one step of clock
+/// drift either way, or `None` if it is not a current code.
+pub fn matching_step(secret: &str, code: &str, unix_time: u64) -> Result<Option<i64>, AppError> {
+    let totp = totp(secret, "")?;
+    let code = code.trim();
+    let current = unix_time / STEP_SECONDS;
+
+    Ok((current.saturating_sub(1)..=current + 1)
+        .find(|step| totp.check(code, step * STEP_SECONDS))
+        .map(|step| step as i64))
+}
+
+fn unix_now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+// ---------------------------------------------------------------------------
+// Recovery codes
+// ---------------------------------------------------------------------------
+
+/// Fresh recovery codes in the form `xxxxx-xxxxx` (40 random bits each).
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let hex = Uuid::new_v4().simple().to_string();
+            format!("{}-{}", &hex[..5], &hex[5..10])
+        })
+        .collect()
+}
+
+/// Recovery codes are compared without case, spaces or dashes.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    let codes = generate_recovery_codes();
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| hash_secret_token(&normalize_recovery_code(code)))
+        .collect();
+
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::text[])")
+        .bind(user_id)
+        .bind(&hashes)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(codes)
+}
+
+/// Replace the user's recovery codes with a new set and return it.
+pub async fn regenerate_recovery_codes(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    let mut tx = pool.begin().await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(codes)
+}
+
+// ---------------------------------------------------------------------------
+// Enrolment
+// ---------------------------------------------------------------------------
+
+/// Start enrolment by storing a new secret for the user, replacing any
+/// pending one. Two-factor authentication stays off until
+/// [`confirm_enrolment`] sees a code generated from it.
+pub async fn begin_enrolment(pool: &PgPool, user_id: Uuid) -> Result<String, AppError> {
+    let secret = generate_secret();
+    let updated = sqlx::query(
+        r#"UPDATE users SET totp_secret = $2, totp_last_used_step = NULL, updated_at = NOW()
+           WHERE id = $1 AND NOT totp_enabled"#,
+    )
+    .bind(user_id)
+    .bind(&secret)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    Ok(secret)
+}
+
+/// Turn two-factor authentication on once the user has entered a code from
+/// their app, and return their recovery codes.
+pub async fn confirm_enrolment(
+    pool: &PgPool,
+    user: &User,
+    code: &str,
+) -> Result<Vec<String>, AppError> {
+    if user.totp_enabled {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    let secret = user.totp_secret.as_deref().ok_or_else(|| {
+        AppError::BadRequest("Start two-factor authentication setup first".to_string())
+    })?;
+    let step = matching_step(secret, code, unix_now())?
+        .ok_or_else(|| AppError::BadRequest("Invalid authentication code".to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    // The secret must be the one the code was checked against, in case setup
+    // was restarted concurrently.
+    let updated = sqlx::query(
+        r#"UPDATE users SET totp_enabled = true, totp_last_used_step = $3, updated_at = NOW()
+           WHERE id = $1 AND totp_secret = $2 AND NOT totp_enabled"#,
+    )
+    .bind(user.id)
+    .bind(secret)
+    .bind(step)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err(AppError::Conflict(
+            "Two-factor authentication setup changed; start again".to_string(),
+        ));
+    }
+
+    let codes = replace_recovery_codes(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+/// Turn two-factor authentication off, discarding the secret and recovery
+/// codes.
+pub async fn disable(conn: &mut PgConnection, user_id: Uuid) -> Result<(), AppError> {
+    sqlx::query(
+        r#"UPDATE users
+           SET totp_secret = NULL, totp_enabled = false, totp_last_used_step = NULL,
+               updated_at = NOW()
+           WHERE id = $1"#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Check a code from the user's authenticator app, or one of their unused
+/// recovery codes. Each app code and recovery code is accepted only once.
+pub async fn verify_second_factor(
+    pool: &PgPool,
+    user: &User,
+    code: &str,
+) -> Result<bool, AppError> {
+    let Some(secret) = user.totp_secret.as_deref().filter(|_| user.totp_enabled) else {
+        return Ok(false);
+    };
+
+    if let Some(step) = matching_step(secret, code, unix_now())? {
+        let fresh = sqlx::query(
+            r#"UPDATE users SET totp_last_used_step = $2
+               WHERE id = $1 AND (totp_last_used_step IS NULL OR totp_last_used_step < $2)"#,
+        )
+        .bind(user.id)
+        .bind(step)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        return Ok(fresh == 1);
+    }
+
+    let used = sqlx::query(
+        r#"UPDATE recovery_codes SET used_at = NOW()
+           WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"#,
+    )
+    .bind(user.id)
+    .bind(hash_secret_token(&normalize_recovery_code(code)))
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(used == 1)
+}
+
+// ---------------------------------------------------------------------------
+// Login challenges
+// ---------------------------------------------------------------------------
+
+/// Issue a challenge token proving the user got past the password step.
+/// The password step's login attempt stays a failure until the challenge is
+/// completed.
+pub async fn issue_challenge(
+    pool: &PgPool,
+    user_id: Uuid,
+    attempt: LoginAttempt,
+) -> Result<String, AppError> {
+    let token = generate_secret_token();
+    sqlx::query(
+        r#"INSERT INTO two_factor_challenges (user_id, token_hash, expires_at, login_attempt_id)
+           VALUES ($1, $2, $3, $4)"#,
+    )
+    .bind(user_id)
+    .bind(hash_secret_token(&token))
+    .bind(Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES))
+    .bind(attempt.id())
+    .execute(pool)
+    .await?;
+    Ok(token)
+}
+
+/// The user a live challenge was issued to. A wrong code leaves the challenge
+/// usable until it expires; failed attempts are limited by the login lockout.
+pub async fn challenge_user(pool: &PgPool, token: &str) -> Result<Uuid, AppError> {
+    sqlx::query_scalar(
+        r#"SELECT user_id FROM two_factor_challenges
+           WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()"#,
+    )
+    .bind(hash_secret_token(token))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(invalid_challenge)
+}
+
+/// Use up a challenge once its second step has succeeded, and mark the
+/// password step's login attempt successful. Fails if a concurrent request
+/// already completed it.
+pub async fn complete_challenge(pool: &PgPool, token: &str) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let attempt_id: Option<Uuid> = sqlx::query_scalar(
+        r#"UPDATE two_factor_challenges SET used_at = NOW()
+           WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+           RETURNING login_attempt_id"#,
+    )
+    .bind(hash_secret_token(token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid_challenge)?;
+
+    if let Some(attempt_id) = attempt_id {
+        sqlx::query("UPDATE login_attempts SET succeeded = true WHERE id = $1")
+            .bind(attempt_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+fn invalid_challenge() -> AppError {
+    AppError::Unauthorized("Invalid or expired challenge token; log in again".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The RFC 6238 test secret, "12345678901234567890", in base32.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_matching_step_accepts_one_step_of_drift() {
+        // RFC 6238 appendix B: T = 59 gives 94287082; six digits keep 287082.
+        assert_eq!(matching_step(RFC_SECRET, "287082", 59).unwrap(), Some(1));
+        assert_eq!(matching_step(RFC_SECRET, " 287082 ", 89).unwrap(), Some(1));
+        assert_eq!(matching_step(RFC_SECRET, "287082", 119).unwrap(), None);
+        assert_eq!(matching_step(RFC_SECRET, "000000", 59).unwrap(), None);
+    }
+
+    #[test]
+    fn test_provisioning_uri_and_qr_code() {
+        let secret = generate_secret();
+        let uri = provisioning_uri(&secret, "admin@mybalivilla.com").unwrap();
+        assert!(uri.starts_with("otpauth://totp/MyBaliVilla:admin%40mybalivilla.com?"));
+        assert!(uri.contains(&format!("secret={secret}")));
+        assert!(uri.contains("issuer=MyBaliVilla"));
+
+        let svg = qr_code_svg(&uri).unwrap();
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn test_recovery_codes_are_distinct_and_normalized() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes
+            .iter()
+            .all(|code| code.len() == 11 && &code[5..6] == "-"));
+
+        let unique: std::collections::HashSet<_> = codes.iter().collect();
+        assert_eq!(unique.len(), RECOVERY_CODE_COUNT);
+
+        assert_eq!(normalize_recovery_code(" AB12C-3d4E5 "), "ab12c3d4e5");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_codes_are_accepted_once() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, email, full_name, role) VALUES ($1, $2, 'Test', 'admin')",
+        )
+        .bind(id)
+        .bind(format!("{id}@example.com"))
+        .execute(&pool)
+        .await
+        .unwrap();
+        let load = || async {
+            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        let secret = begin_enrolment(&pool, id).await.unwrap();
+        let now = unix_now();
+        let code = |time: u64| totp(&secret, "").unwrap().generate(time);
+        let recovery_codes = confirm_enrolment(&pool, &load().await, &code(now))
+            .await
+            .unwrap();
+        assert!(matches!(
+            begin_enrolment(&pool, id).await,
+            Err(AppError::Conflict(_))
+        ));
+
+        // The enrolment code, and any earlier one, cannot be replayed; the
+        // next step's code still works once.
+        let user = load().await;
+        assert!(!verify_second_factor(&pool, &user, &code(now))
+            .await
+            .unwrap());
+        let next = code(now + STEP_SECONDS);
+        assert!(verify_second_factor(&pool, &user, &next).await.unwrap());
+        assert!(!verify_second_factor(&pool, &user, &next).await.unwrap());
+
+        let recovery = recovery_codes[0].to_uppercase();
+        assert!(verify_second_factor(&pool, &user, &recovery).await.unwrap());
+        assert!(!verify_second_factor(&pool, &user, &recovery).await.unwrap());
+
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a migrated database"]
+    async fn test_completed_challenge_marks_the_password_step_succeeded() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = crate::db::create_pool(&database_url).await.unwrap();
+        let id = Uuid::new_v4();
+        let email = format!("{id}@example.com");
+        sqlx::query(
+            "INSERT INTO users (id, email, full_name, role) VALUES ($1, $2, 'Test', 'admin')",
+        )
+        .bind(id)
+        .bind(&email)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let attempt = crate::login_attempts::begin_login_attempt(&pool, &email, None)
+            .await
+            .unwrap();
+        let attempt_id = attempt.id();
+        let succeeded = || async {
+            sqlx::query_scalar::<_, bool>("SELECT succeeded FROM login_attempts WHERE id = $1")
+                .bind(attempt_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        let token = issue_challenge(&pool, id, attempt).await.unwrap();
+        assert!(!succeeded().await);
+
+        complete_challenge(&pool, &token).await.unwrap();
+        assert!(succeeded().await);
+        assert!(matches!(
+            complete_challenge(&pool, &token).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        sqlx::query("DELETE FROM login_attempts WHERE email = $1")
+            .bind(&email)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
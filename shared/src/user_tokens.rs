@@ -0,0 +1,87 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{generate_secret_token, hash_secret_token};
+use crate::errors::AppError;
+
+/// What an emailed token may be redeemed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    EmailVerification,
+    PasswordReset,
+}
+
+impl TokenPurpose {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::PasswordReset => "password_reset",
+        }
+    }
+
+    /// How long a link stays valid. Reset links are short-lived because they
+    /// grant access to the account.
+    pub fn ttl(self) -> Duration {
+        match self {
+            TokenPurpose::EmailVerification => Duration::hours(48),
+            TokenPurpose::PasswordReset => Duration::hours(1),
+        }
+    }
+}
+
+/// Create a token for `user_id` and return it. Unused tokens issued earlier
+/// for the same purpose stop working, so only the latest email's link is live.
+pub async fn issue_user_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    purpose: TokenPurpose,
+) -> Result<String, AppError> {
+    let token = generate_secret_token();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"UPDATE user_tokens SET used_at = NOW()
+           WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL"#,
+    )
+    .bind(user_id)
+    .bind(purpose.as_str())
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+           VALUES ($1, $2, $3, $4)"#,
+    )
+    .bind(user_id)
+    .bind(purpose.as_str())
+    .bind(hash_secret_token(&token))
+    .bind(Utc::now() + purpose.ttl())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(token)
+}
+
+/// Redeem a token and return the user it was issued to. A token works once:
+/// marking it used and reading it happen in one statement, so two concurrent
+/// requests cannot both succeed.
+pub async fn consume_user_token(
+    pool: &PgPool,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<Uuid, AppError> {
+    sqlx::query_scalar(
+        r#"UPDATE user_tokens SET used_at = NOW()
+           WHERE token_hash = $1 AND purpose = $2
+             AND used_at IS NULL AND expires_at > NOW()
+           RETURNING user_id"#,
+    )
+    .bind(hash_secret_token(token))
+    .bind(purpose.as_str())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Invalid or expired token".to_string()))
+}
@@ -0,0 +1,82 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use shared::calendar::{parse_calendar, render_calendar, CalendarEvent};
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+}
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn parses_airbnb_feed() {
+    let events = parse_calendar(&fixture("airbnb.ics")).unwrap();
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0],
+        CalendarEvent {
+            uid: "1418fb94e984-6f4c1b0e4d1a7b35b9f7c1a0d8e1f2a3@airbnb.com".to_string(),
+            // Checked out on the 15th, so the last blocked night is the 14th.
+            start_date: date(2026, 3, 10),
+            end_date: date(2026, 3, 14),
+            summary: Some("Reserved".to_string()),
+        }
+    );
+    assert_eq!(events[1].start_date, date(2026, 3, 28));
+    assert_eq!(events[1].end_date, date(2026, 4, 1));
+}
+
+#[test]
+fn parses_booking_com_feed_with_times_cancellations_and_escapes() {
+    let events = parse_calendar(&fixture("booking_com.ics")).unwrap();
+
+    let uids: Vec<&str> = events.iter().map(|e| e.uid.as_str()).collect();
+    assert_eq!(uids, ["booking-5512309871", "booking-5512309873"]);
+
+    assert_eq!(events[0].start_date, date(2026, 5, 1));
+    assert_eq!(events[0].end_date, date(2026, 5, 3));
+    assert_eq!(
+        events[0].summary.as_deref(),
+        Some("CLOSED - Not available, owner maintenance")
+    );
+
+    // No DTEND: a single night.
+    assert_eq!(events[1].start_date, date(2026, 6, 10));
+    assert_eq!(events[1].end_date, date(2026, 6, 10));
+}
+
+#[test]
+fn rejects_files_that_are_not_calendars() {
+    assert!(parse_calendar(&fixture("not_a_calendar.ics")).is_err());
+    assert!(parse_calendar(b"").is_err());
+}
+
+#[test]
+fn exported_feed_round_trips() {
+    let events = vec![
+        CalendarEvent {
+            uid: "booking-1@mybalivilla.com".to_string(),
+            start_date: date(2026, 7, 1),
+            end_date: date(2026, 7, 4),
+            summary: Some("Booked".to_string()),
+        },
+        CalendarEvent {
+            uid: "blocked-2@mybalivilla.com".to_string(),
+            start_date: date(2026, 7, 20),
+            end_date: date(2026, 7, 20),
+            summary: Some("Owner stay; pool repairs, week 2".to_string()),
+        },
+    ];
+    let generated_at = Utc.with_ymd_and_hms(2026, 6, 1, 8, 30, 0).unwrap();
+
+    let feed = render_calendar("Villa Seminyak", &events, generated_at);
+
+    assert!(feed.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(feed.contains("DTSTART;VALUE=DATE:20260701\r\nDTEND;VALUE=DATE:20260705\r\n"));
+    assert!(feed.contains("DTSTAMP:20260601T083000Z\r\n"));
+    assert!(feed.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(parse_calendar(feed.as_bytes()).unwrap(), events);
+}
@@ -0,0 +1,92 @@
+use rust_decimal::Decimal;
+use shared::currency::parse_rates_file;
+use shared::errors::AppError;
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+}
+
+fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+fn rejection(data: &str) -> String {
+    match parse_rates_file(data.as_bytes()) {
+        Err(AppError::BadRequest(msg)) => msg,
+        Err(other) => panic!("unexpected error: {other}"),
+        Ok(rates) => panic!("file was accepted: {rates:?}"),
+    }
+}
+
+#[test]
+fn parses_spreadsheet_csv_export() {
+    // Byte order mark, CRLF line endings, an extra column and a lower-case code.
+    let rates = parse_rates_file(&fixture("exchange_rates.csv")).unwrap();
+
+    assert_eq!(
+        rates,
+        vec![
+            ("IDR".to_string(), dec("16250.00")),
+            ("EUR".to_string(), dec("0.9200")),
+            ("AUD".to_string(), dec("1.52")),
+        ]
+    );
+}
+
+#[test]
+fn rebases_rates_quoted_against_another_currency() {
+    let mut rates = parse_rates_file(&fixture("exchange_rates_eur.json")).unwrap();
+    rates.sort();
+
+    assert_eq!(
+        rates,
+        vec![
+            ("EUR".to_string(), dec("0.8")),
+            ("GBP".to_string(), dec("0.68")),
+            ("IDR".to_string(), dec("16000")),
+            ("USD".to_string(), dec("1")),
+        ]
+    );
+}
+
+#[test]
+fn parses_json_list() {
+    let rates = parse_rates_file(
+        br#"[{"currency": "idr", "rate": 16250}, {"currency": "SGD", "rate": "1.34"}]"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rates,
+        vec![
+            ("IDR".to_string(), dec("16250")),
+            ("SGD".to_string(), dec("1.34")),
+        ]
+    );
+}
+
+#[test]
+fn rejects_bad_files() {
+    assert_eq!(
+        rejection("currency,rate\nIDR,16250\nXYZ,3\n"),
+        "Line 3: Unknown currency code 'XYZ'; expected an ISO 4217 code such as USD or IDR"
+    );
+    assert_eq!(
+        rejection("currency,rate\nIDR,lots\n"),
+        "Line 2: rate for IDR is not a number"
+    );
+    assert_eq!(
+        rejection("IDR,16250\n"),
+        "CSV must start with a header row naming the currency and rate columns"
+    );
+    assert_eq!(
+        rejection(r#"{"base": "EUR", "rates": {"IDR": 20000}}"#),
+        "Rates quoted against EUR must include USD"
+    );
+    assert_eq!(
+        rejection("currency,rate\n"),
+        "Exchange rate file contains no rates"
+    );
+    assert_eq!(rejection("  \n"), "Exchange rate file contains no rates");
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+use shared::errors::AppError;
+use shared::google::{GoogleVerifier, JwkSet, StaticKeySource};
+
+const CLIENT_ID: &str = "1234567890-test.apps.googleusercontent.com";
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+}
+
+/// A verifier that trusts only the test key in `google_test_jwks.json`.
+fn verifier() -> GoogleVerifier {
+    let keys: JwkSet = serde_json::from_slice(&fixture("google_test_jwks.json")).unwrap();
+    GoogleVerifier::new(CLIENT_ID, Arc::new(StaticKeySource::new(keys)))
+}
+
+fn claims() -> Value {
+    let now = Utc::now().timestamp();
+    json!({
+        "iss": "https://accounts.google.com",
+        "aud": CLIENT_ID,
+        "sub": "110169484474386276334",
+        "email": "wayan@example.com",
+        "email_verified": true,
+        "name": "Wayan Sudarsana",
+        "picture": "https://lh3.googleusercontent.com/a/photo.jpg",
+        "iat": now,
+        "exp": now + 3600,
+    })
+}
+
+/// Sign `claims` with the test key, as Google would with its own.
+fn sign(claims: &Value, kid: &str) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid.to_string());
+    let key = EncodingKey::from_rsa_pem(&fixture("google_test_key.pem")).unwrap();
+    encode(&header, claims, &key).unwrap()
+}
+
+async fn rejection(claims: &Value, kid: &str) -> String {
+    match verifier().verify(&sign(claims, kid)).await {
+        Err(AppError::Unauthorized(msg)) => msg,
+        Err(other) => panic!("unexpected error: {other}"),
+        Ok(_) => panic!("token was accepted"),
+    }
+}
+
+#[tokio::test]
+async fn accepts_valid_token() {
+    let user = verifier()
+        .verify(&sign(&claims(), "test-key-1"))
+        .await
+        .unwrap();
+
+    assert_eq!(user.google_id, "110169484474386276334");
+    assert_eq!(user.email, "wayan@example.com");
+    assert_eq!(user.name, "Wayan Sudarsana");
+    assert_eq!(
+        user.picture.as_deref(),
+        Some("https://lh3.googleusercontent.com/a/photo.jpg")
+    );
+}
+
+#[tokio::test]
+async fn accepts_issuer_without_scheme() {
+    let mut claims = claims();
+    claims["iss"] = json!("accounts.google.com");
+    assert!(verifier()
+        .verify(&sign(&claims, "test-key-1"))
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn rejects_token_for_another_client() {
+    let mut claims = claims();
+    claims["aud"] = json!("someone-else.apps.googleusercontent.com");
+    assert_eq!(
+        rejection(&claims, "test-key-1").await,
+        "Google token audience mismatch"
+    );
+}
+
+#[tokio::test]
+async fn rejects_expired_token() {
+    let mut claims = claims();
+    claims["exp"] = json!(Utc::now().timestamp() - 3600);
+    assert_eq!(
+        rejection(&claims, "test-key-1").await,
+        "Google token has expired"
+    );
+}
+
+#[tokio::test]
+async fn rejects_token_from_another_issuer() {
+    let mut claims = claims();
+    claims["iss"] = json!("https://evil.example.com");
+    assert_eq!(
+        rejection(&claims, "test-key-1").await,
+        "Google token issuer mismatch"
+    );
+}
+
+#[tokio::test]
+async fn rejects_unverified_email() {
+    let mut claims = claims();
+    claims["email_verified"] = json!(false);
+    assert_eq!(
+        rejection(&claims, "test-key-1").await,
+        "Google email not verified"
+    );
+}
+
+#[tokio::test]
+async fn rejects_unknown_signing_key() {
+    assert_eq!(
+        rejection(&claims(), "rotated-away").await,
+        "Invalid Google token"
+    );
+}
+
+#[tokio::test]
+async fn rejects_tampered_token() {
+    // Another user's claims under the original signature.
+    let mut other = claims();
+    other["sub"] = json!("100000000000000000001");
+    let token = sign(&claims(), "test-key-1");
+    let forged = sign(&other, "test-key-1");
+    let (header_and_payload, _) = forged.rsplit_once('.').unwrap();
+    let (_, signature) = token.rsplit_once('.').unwrap();
+
+    assert!(matches!(
+        verifier().verify(&format!("{header_and_payload}.{signature}")).await,
+        Err(AppError::Unauthorized(msg)) if msg == "Invalid Google token"
+    ));
+}
+
+#[tokio::test]
+async fn rejects_when_not_configured() {
+    let keys: JwkSet = serde_json::from_slice(&fixture("google_test_jwks.json")).unwrap();
+    let verifier = GoogleVerifier::new("", Arc::new(StaticKeySource::new(keys)));
+    assert!(matches!(
+        verifier.verify(&sign(&claims(), "test-key-1")).await,
+        Err(AppError::Unauthorized(msg)) if msg == "Google sign-in is not configured"
+    ));
+}